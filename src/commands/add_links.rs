@@ -1,30 +1,71 @@
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use super::common::{create_alias_links, install_to_icons_dirs, output_cursor_name};
+use super::{print_warnings, skipped_alias_warnings, CursorFilter, ThemeReport};
 use crate::{
-    cursor_mapping::get_cursor_symlinks,
-    theme_config::create_theme_files,
-    CursorTheme, FileUtils, CommandUtils, get_icons_dir,
+    theme_config::{check_theme_comment, create_theme_files},
+    xcursor::detect_native_size,
+    CursorTheme, FileUtils, InstallScope, LinkMode, copy_fs, get_icons_dir, sanitize_display_name, sanitize_theme_name, status, update_icon_caches,
 };
 
+/// Theme comment used when none is given
+pub const DEFAULT_COMMENT: &str = "Koosh cursor theme with all necessary symlinks";
+
 /// Arguments for the add-links command
 #[derive(Debug)]
 pub struct AddLinksArgs {
     pub theme_name: String,
+    /// `Name` written to index.theme and cursor.theme (default: the theme name)
+    pub display_name: Option<String>,
     pub source_dir: Option<PathBuf>,
+    /// Directory the theme is built in (default: the current directory)
+    pub output_dir: Option<PathBuf>,
+    /// Exact path to build the theme at, overriding `output_dir`
+    pub output: Option<PathBuf>,
+    /// Cursor size recommended in the Hyprland config
+    pub size: u32,
+    /// Recreate alias symlinks that already exist
+    pub relink: bool,
+    /// Write aliases as symlinks or as copies of their target
+    pub link_mode: LinkMode,
+    /// Source cursors to copy
+    pub filter: CursorFilter,
+    /// Lowercase cursor file names while copying
+    pub normalize_names: bool,
+    /// `Comment` written to index.theme and cursor.theme
+    pub comment: String,
+    /// Themes to inherit from (default: hicolor)
+    pub inherits: Vec<String>,
+    /// Where to install the built theme
+    pub install_scope: InstallScope,
+    pub jobs: Option<usize>,
+    /// Give copied and installed files the modification time of their source
+    pub preserve_times: bool,
+    pub command_timeout: Option<Duration>,
 }
 
 /// Add missing symlinks to a cursor theme
-pub fn add_missing_links(args: AddLinksArgs) -> Result<()> {
-    println!("Adding missing links to cursor theme...");
-    
-    let script_dir = std::env::current_dir()
-        .context("Failed to get current directory")?;
-    let root_dir = script_dir.parent()
-        .unwrap_or(&script_dir);
-    
-    let theme_path = root_dir.join(&args.theme_name);
+///
+/// The theme is built at `output` when given, otherwise at
+/// `<output_dir>/<theme_name>` (the current directory when no output
+/// directory is given), and then installed to the icons directory.
+pub fn add_missing_links(mut args: AddLinksArgs) -> Result<ThemeReport> {
+    args.theme_name = sanitize_theme_name(&args.theme_name)?;
+    args.display_name = args.display_name.as_deref().map(sanitize_display_name).transpose()?;
+    args.comment = check_theme_comment(&args.comment)?;
+    status!("Adding missing links to cursor theme...");
+    
+    let theme_path = match (&args.output, &args.output_dir) {
+        (Some(output), _) => output.clone(),
+        (None, Some(dir)) => dir.join(&args.theme_name),
+        (None, None) => std::env::current_dir()
+            .context("Failed to get current directory")?
+            .join(&args.theme_name),
+    };
     let theme = CursorTheme::new(args.theme_name.clone(), theme_path);
     
     // Remove existing theme and create new one
@@ -36,43 +77,68 @@ pub fn add_missing_links(args: AddLinksArgs) -> Result<()> {
     
     // Find and copy cursor files
     let source_cursors = find_cursor_source(&args.source_dir)?;
-    copy_cursor_files(&source_cursors, &theme.cursors_dir)?;
+    copy_cursor_files(&source_cursors, &theme.cursors_dir, &args.filter, args.normalize_names, args.preserve_times)?;
     
     // Create symlinks
-    create_cursor_symlinks(&theme.cursors_dir)?;
+    let skipped = create_alias_links(&theme.cursors_dir, args.relink, args.link_mode)?.skipped;
     
     // Create theme configuration files
     create_theme_files(
         &theme.path,
         &args.theme_name,
-        "Koosh cursor theme with all necessary symlinks",
+        args.display_name.as_deref(),
+        &args.comment,
         None,
+        &args.inherits,
     )?;
     
-    // Install to user's .icons directory
-    install_to_user_icons(&theme)?;
+    // Install to the icons directories
+    let install_paths = install_to_icons_dirs(&theme, args.install_scope, args.jobs, args.preserve_times)?;
     
     // Set permissions
     FileUtils::set_permissions_recursive(&theme.path, 0o755)?;
     
     // Update icon cache
-    update_icon_cache(&theme.name)?;
-    
-    println!("Done! Created new cursor theme: {:?}", theme.path);
-    println!("Also installed to: {:?}", get_icons_dir()?.join(&theme.name));
-    println!();
-    println!("To use with Hyprland, add to your config:");
-    println!("env = XCURSOR_THEME,{}", args.theme_name);
-    println!("env = XCURSOR_SIZE,24");
-    println!();
-    println!("cursor {{");
-    println!("    size = 24");
-    println!("}}");
-    println!();
-    println!("Note: Since your cursor files don't support multiple sizes yet,");
-    println!("it's best to use size 24 which is their native size.");
+    for path in &install_paths {
+        update_icon_caches(path, args.command_timeout);
+    }
     
-    Ok(())
+    status!("Done! Created new cursor theme: {:?}", theme.path);
+    for path in &install_paths {
+        status!("Also installed to: {:?}", path);
+    }
+    if !skipped.is_empty() {
+        status!("{} aliases skipped due to missing targets", skipped.len());
+    }
+    status!();
+    status!("To use with Hyprland, add to your config:");
+    status!("{}", hyprland_config(&args.theme_name, args.size));
+    status!();
+    match detect_native_size(&theme.cursors_dir) {
+        Ok(Some(native_size)) => {
+            status!("Note: The native size of these cursor files is {},", native_size);
+            status!("so size {} will look the sharpest.", native_size);
+        }
+        _ => {
+            status!("Note: Since your cursor files don't support multiple sizes yet,");
+            status!("it's best to use size 24 which is their native size.");
+        }
+    }
+    
+    let warnings = skipped_alias_warnings(&skipped);
+    print_warnings(&warnings);
+    ThemeReport::collect(&theme, install_paths, warnings)
+}
+
+/// Hyprland config snippet selecting the theme at the given size
+///
+/// Anything that prints or writes the config should go through this so
+/// the two never disagree.
+pub fn hyprland_config(theme_name: &str, size: u32) -> String {
+    format!(
+        "env = XCURSOR_THEME,{}\nenv = XCURSOR_SIZE,{}\n\ncursor {{\n    size = {}\n}}",
+        theme_name, size, size
+    )
 }
 
 /// Find the source directory for cursor files
@@ -111,9 +177,13 @@ fn find_cursor_source(source_dir: &Option<PathBuf>) -> Result<PathBuf> {
 }
 
 /// Copy cursor files from source to destination
-fn copy_cursor_files(source: &Path, dest: &Path) -> Result<()> {
-    println!("Copying cursor files from {:?} to {:?}", source, dest);
+///
+/// With `normalize` the copies get lowercase names; two sources that
+/// differ only in case are an error.
+fn copy_cursor_files(source: &Path, dest: &Path, filter: &CursorFilter, normalize: bool, preserve_times: bool) -> Result<()> {
+    status!("Copying cursor files from {:?} to {:?}", source, dest);
     
+    let mut copied: HashMap<String, PathBuf> = HashMap::new();
     for entry in fs::read_dir(source)? {
         let entry = entry?;
         let path = entry.path();
@@ -121,64 +191,18 @@ fn copy_cursor_files(source: &Path, dest: &Path) -> Result<()> {
         if path.is_file() {
             let file_name = path.file_name()
                 .ok_or_else(|| anyhow::anyhow!("Invalid file name"))?;
-            let dest_path = dest.join(file_name);
+            if !filter.allows(&file_name.to_string_lossy()) {
+                continue;
+            }
+            let name = output_cursor_name(&file_name.to_string_lossy(), normalize);
+            if let Some(previous) = copied.insert(name.clone(), path.clone()) {
+                return Err(anyhow::anyhow!("{:?} and {:?} both map to {}", previous, path, name));
+            }
+            let dest_path = dest.join(&name);
             
-            fs::copy(&path, &dest_path)
-                .with_context(|| format!("Failed to copy {:?} to {:?}", path, dest_path))?;
-        }
-    }
-    
-    Ok(())
-}
-
-/// Create cursor symlinks for compatibility
-fn create_cursor_symlinks(cursors_dir: &Path) -> Result<()> {
-    println!("Creating cursor symlinks...");
-    
-    let symlinks = get_cursor_symlinks();
-    
-    for (target, link_name) in symlinks {
-        let target_path = cursors_dir.join(target);
-        let link_path = cursors_dir.join(link_name);
-        
-        // Only create symlink if target exists and link doesn't exist
-        if target_path.exists() && !link_path.exists() {
-            FileUtils::create_symlink(target, &link_path)
-                .with_context(|| format!("Failed to create symlink: {} -> {}", link_name, target))?;
+            FileUtils::copy_file_with(copy_fs(preserve_times), &path, &dest_path)?;
         }
     }
     
     Ok(())
 }
-
-/// Install theme to user's .icons directory
-fn install_to_user_icons(theme: &CursorTheme) -> Result<()> {
-    let user_icons_dir = get_icons_dir()?;
-    let user_theme_dir = user_icons_dir.join(&theme.name);
-    
-    // Remove existing installation
-    if user_theme_dir.exists() {
-        fs::remove_dir_all(&user_theme_dir)?;
-    }
-    
-    // Copy theme to user directory
-    FileUtils::copy_dir_recursive(&theme.path, &user_theme_dir)?;
-    
-    // Set permissions
-    FileUtils::set_permissions_recursive(&user_theme_dir, 0o755)?;
-    
-    Ok(())
-}
-
-/// Update GTK icon cache
-fn update_icon_cache(theme_name: &str) -> Result<()> {
-    if CommandUtils::command_exists("gtk-update-icon-cache") {
-        let user_theme_dir = get_icons_dir()?.join(theme_name);
-        let _ = CommandUtils::run_command(
-            "gtk-update-icon-cache",
-            &["-f", "-t", user_theme_dir.to_str().unwrap()],
-        );
-        // Ignore errors as this is optional
-    }
-    Ok(())
-}
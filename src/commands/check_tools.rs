@@ -0,0 +1,139 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{CommandUtils, status};
+
+/// An external tool, or a set of interchangeable tools, some commands rely on
+struct Tool {
+    /// Any one of these is enough
+    names: &'static [&'static str],
+    used_by: &'static [&'static str],
+    /// Whether the commands fail or degrade without it (otherwise it is a nicety)
+    required: bool,
+    purpose: &'static str,
+}
+
+/// Every external tool the commands call
+const TOOLS: &[Tool] = &[
+    Tool {
+        names: &["xcur2png"],
+        used_by: &["create-animated"],
+        required: true,
+        purpose: "extract cursor frames",
+    },
+    Tool {
+        names: &["xcursorgen"],
+        used_by: &["create-animated"],
+        required: true,
+        purpose: "assemble multi-size cursors",
+    },
+    Tool {
+        names: &["magick", "convert"],
+        used_by: &["create-animated"],
+        required: false,
+        purpose: "scale frames; scaled natively when missing",
+    },
+    Tool {
+        names: &["identify"],
+        used_by: &["create-animated"],
+        required: false,
+        purpose: "read frame sizes; read natively when missing",
+    },
+    Tool {
+        names: &["hyprcursor-util"],
+        used_by: &["create-hyprcursor"],
+        required: true,
+        purpose: "extract X11 themes for hyprcursor",
+    },
+    Tool {
+        names: &[
+            "gtk-update-icon-cache",
+            "gtk4-update-icon-cache",
+            "xdg-icon-resource",
+            "kbuildsycoca6",
+            "kbuildsycoca5",
+        ],
+        used_by: &["add-links", "create-animated", "create-hyprcursor", "rename-cursors"],
+        required: false,
+        purpose: "refresh the icon caches",
+    },
+];
+
+/// Whether one tool was found
+#[derive(Debug, Serialize)]
+pub struct ToolStatus {
+    pub name: &'static str,
+    pub found: bool,
+    pub required: bool,
+    pub used_by: &'static [&'static str],
+    pub purpose: &'static str,
+}
+
+/// Result of probing for external tools
+#[derive(Debug, Serialize)]
+pub struct CheckToolsReport {
+    pub tools: Vec<ToolStatus>,
+    /// Required tools (or tool sets, as "a or b") that are missing
+    pub missing: Vec<String>,
+}
+
+impl CheckToolsReport {
+    /// Commands that can't run, with the required tools they lack
+    pub fn unavailable_commands(&self) -> BTreeMap<&'static str, Vec<String>> {
+        let mut unavailable: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+        for tool in TOOLS.iter().filter(|tool| tool.required) {
+            let found = self.tools.iter().any(|status| status.found && tool.names.contains(&status.name));
+            if found {
+                continue;
+            }
+            for &command in tool.used_by {
+                unavailable.entry(command).or_default().push(tool.names.join(" or "));
+            }
+        }
+        unavailable
+    }
+}
+
+/// Probe for every external tool and print which commands need it
+pub fn check_tools() -> Result<CheckToolsReport> {
+    let report = probe_tools();
+
+    status!("{:<24} {:<8} {}", "TOOL", "STATUS", "USED BY");
+    for tool in &report.tools {
+        let state = match (tool.found, tool.required) {
+            (true, _) => "found",
+            (false, true) => "missing",
+            (false, false) => "optional",
+        };
+        status!("{:<24} {:<8} {} ({})", tool.name, state, tool.used_by.join(", "), tool.purpose);
+    }
+
+    Ok(report)
+}
+
+/// Probe for every external tool without printing anything
+pub(crate) fn probe_tools() -> CheckToolsReport {
+    let mut tools = Vec::new();
+    let mut missing = Vec::new();
+
+    for tool in TOOLS {
+        let mut any_found = false;
+        for &name in tool.names {
+            let found = CommandUtils::command_exists(name);
+            any_found |= found;
+            tools.push(ToolStatus {
+                name,
+                found,
+                required: tool.required,
+                used_by: tool.used_by,
+                purpose: tool.purpose,
+            });
+        }
+        if tool.required && !any_found {
+            missing.push(tool.names.join(" or "));
+        }
+    }
+
+    CheckToolsReport { tools, missing }
+}
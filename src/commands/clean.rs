@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{get_temp_dir, status};
+
+/// Scratch directories left behind by interrupted builds
+///
+/// Only these names, and per-run `<name>.<suffix>` directories made from them,
+/// are ever removed.
+pub const SCRATCH_DIRS: [&str; 4] = ["koosh_animated_temp", "koosh_archive_temp", "koosh_extract", "koosh_hyprcursor"];
+
+/// Arguments for the clean command
+#[derive(Debug)]
+pub struct CleanArgs {
+    /// Directory to clean (default: the current directory)
+    pub dir: Option<PathBuf>,
+    pub temp_dir: Option<PathBuf>,
+}
+
+/// Summary of a clean run
+#[derive(Debug, Serialize)]
+pub struct CleanReport {
+    pub removed: Vec<PathBuf>,
+}
+
+/// Remove stale scratch directories from the working directory and the temp base
+pub fn clean_scratch_dirs(args: CleanArgs) -> Result<CleanReport> {
+    let dir = match args.dir {
+        Some(dir) => dir,
+        None => std::env::current_dir()?,
+    };
+    let temp_base = get_temp_dir(args.temp_dir.as_deref());
+
+    let mut removed = remove_scratch_dirs(&dir)?;
+    if temp_base != dir {
+        removed.extend(remove_scratch_dirs(&temp_base)?);
+    }
+
+    if removed.is_empty() {
+        status!("Nothing to clean");
+    } else {
+        status!("Done! Removed {} scratch directories", removed.len());
+    }
+
+    Ok(CleanReport { removed })
+}
+
+/// Whether `name` is a scratch directory name, fixed or per-run
+fn is_scratch_name(name: &str) -> bool {
+    SCRATCH_DIRS.iter().any(|scratch| {
+        name == *scratch
+            || name.strip_prefix(scratch)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .is_some_and(|suffix| !suffix.is_empty())
+    })
+}
+
+/// Remove the known scratch directories directly inside `dir`, returning the removed paths
+fn remove_scratch_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    if !dir.is_dir() {
+        return Ok(removed);
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {:?}", dir))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().is_some_and(is_scratch_name))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        // Never follow a symlink out of the directory being cleaned
+        if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Failed to remove {:?}", path))?;
+            status!("  Removed {:?}", path);
+            removed.push(path);
+        }
+    }
+
+    Ok(removed)
+}
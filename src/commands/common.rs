@@ -0,0 +1,548 @@
+use anyhow::{Context, Result};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cursor_mapping::get_cursor_symlinks,
+    hyprcursor::cursors_directory,
+    theme_config::HyprManifest,
+    ensure_within, symlink_graph, CursorError, CursorTheme, FileUtils, InstallScope, LinkMode, copy_fs, get_icons_dir, get_system_icons_dir,
+    sync_installs, verify_installs, detail, status,
+};
+
+/// Theme files installed next to the `cursors` directory
+const THEME_FILES: &[&str] = &["index.theme", "cursor.theme"];
+
+/// What an install copies from a built theme besides its `cursors` directory
+///
+/// [`THEME_FILES`], and for a hyprcursor theme its `manifest.hl` and the
+/// shapes directory the manifest names. Entries the build lacks are left out.
+fn installed_entries(theme: &CursorTheme) -> Result<Vec<String>> {
+    let mut entries: Vec<String> = THEME_FILES.iter().map(|file| file.to_string()).collect();
+    let manifest_path = theme.path.join("manifest.hl");
+    if manifest_path.is_file() {
+        let manifest = HyprManifest::read(&manifest_path)?;
+        let shapes = cursors_directory(&manifest);
+        ensure_within(&theme.path, &theme.path.join(shapes))
+            .with_context(|| format!("Invalid cursors_directory in {:?}: {}", manifest_path, shapes))?;
+        entries.push("manifest.hl".to_string());
+        if shapes != "cursors" {
+            entries.push(shapes.to_string());
+        }
+    }
+    Ok(entries.into_iter().filter(|entry| theme.path.join(entry).exists()).collect())
+}
+
+/// File name endings of editor backups and other leftovers that are never installed
+const JUNK_SUFFIXES: &[&str] = &["~", ".bak", ".orig", ".swp", ".tmp"];
+
+/// Whether an entry of a built theme belongs in an install
+///
+/// Dotfiles and directories (`.git`, build caches) and backup files are
+/// skipped.
+pub fn is_installable(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    !name.starts_with('.') && !JUNK_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// The file name a source cursor is written under
+///
+/// With `normalize` (`--normalize-names`) names are lowercased, so aliases
+/// find `left_ptr` even when the source calls it `Left_Ptr`.
+pub fn output_cursor_name(name: &str, normalize: bool) -> String {
+    if normalize {
+        name.to_lowercase()
+    } else {
+        name.to_string()
+    }
+}
+
+/// What [`create_alias_links`] did
+#[derive(Debug, Default)]
+pub struct AliasLinks {
+    /// Aliases written by this call
+    pub created: Vec<&'static str>,
+    /// `(target, link)` pairs skipped because the target cursor is missing
+    pub skipped: Vec<(&'static str, &'static str)>,
+}
+
+/// Create the standard alias symlinks in a cursors directory
+///
+/// Existing links are kept unless `relink` is set; real cursor files are never replaced.
+pub fn create_alias_links(cursors_dir: &Path, relink: bool, mode: LinkMode) -> Result<AliasLinks> {
+    status!("Creating cursor symlinks...");
+
+    let mut links = AliasLinks::default();
+    for (target, link_name) in get_cursor_symlinks() {
+        let target_path = cursors_dir.join(target);
+        let link_path = cursors_dir.join(link_name);
+
+        if !target_path.exists() {
+            detail!("  Skipping alias {}: target {} does not exist", link_name, target);
+            links.skipped.push((target, link_name));
+            continue;
+        }
+
+        // Only create the link if it doesn't exist (or is being relinked)
+        if !link_path.exists() || (relink && link_path.is_symlink()) {
+            FileUtils::create_link(mode, target, &link_path)
+                .with_context(|| format!("Failed to create symlink: {} -> {}", link_name, target))?;
+            detail!("  Created symlink: {} -> {}", link_name, target);
+            links.created.push(link_name);
+        }
+    }
+
+    Ok(links)
+}
+
+/// Install a built theme to the icons directories of `scope`
+///
+/// With [`InstallScope::Both`] a failed system install (usually for lack of
+/// permission) is a warning; the user install has already succeeded.
+///
+/// Returns every install directory, user first.
+pub fn install_to_icons_dirs(
+    theme: &CursorTheme,
+    scope: InstallScope,
+    jobs: Option<usize>,
+    preserve_times: bool,
+) -> Result<Vec<PathBuf>> {
+    install_to_scope(scope, |icons_dir| install_theme(theme, icons_dir, jobs, preserve_times))
+}
+
+/// Run `install` for each icons directory of `scope`, user first
+///
+/// `install` gets the icons directory and returns the installed theme's path.
+/// As with [`install_to_icons_dirs`], a failed system install is only a
+/// warning under [`InstallScope::Both`]. Returns every install directory.
+pub fn install_to_scope<F>(scope: InstallScope, mut install: F) -> Result<Vec<PathBuf>>
+where
+    F: FnMut(&Path) -> Result<PathBuf>,
+{
+    let mut installed = Vec::new();
+    if matches!(scope, InstallScope::User | InstallScope::Both) {
+        installed.push(install(&get_icons_dir()?)?);
+    }
+    match scope {
+        InstallScope::User => {}
+        InstallScope::System => installed.push(install(&get_system_icons_dir())?),
+        InstallScope::Both => match install(&get_system_icons_dir()) {
+            Ok(path) => installed.push(path),
+            Err(err) => warn!("Skipping system install: {:#}", err),
+        },
+    }
+    Ok(installed)
+}
+
+/// Install a built theme as `<icons_dir>/<theme name>`
+///
+/// Any previous installation is replaced. Only the theme itself is copied:
+/// the `cursors` directory, `index.theme` and `cursor.theme`, and for a
+/// hyprcursor theme `manifest.hl` and its shapes. Nothing is copied when the
+/// theme was built in place.
+///
+/// The theme is copied to a sibling `.<name>.tmp-<id>` directory first and
+/// renamed over the installation once complete, so apps never see a
+/// half-copied theme. Where the rename fails, the installation is removed
+/// and copied into directly instead.
+///
+/// With `--sync` the previous installation is updated in place instead, and
+/// files that haven't changed are not copied. With `--verify-install` the
+/// result is checked with [`verify_install`]. With `preserve_times` copied
+/// files keep the modification time of their source.
+pub fn install_theme(theme: &CursorTheme, icons_dir: &Path, jobs: Option<usize>, preserve_times: bool) -> Result<PathBuf> {
+    let user_theme_dir = icons_dir.join(&theme.name);
+    if theme.path == user_theme_dir {
+        return Ok(user_theme_dir);
+    }
+    status!("Installing to {:?}", user_theme_dir);
+
+    if sync_installs() {
+        sync_theme(theme, &user_theme_dir, preserve_times)?;
+        FileUtils::set_permissions_recursive(&user_theme_dir, 0o755)?;
+    } else {
+        copy_install(theme, &user_theme_dir, jobs, preserve_times)?;
+    }
+
+    if verify_installs() {
+        verify_install(theme, &user_theme_dir)?;
+    }
+    Ok(user_theme_dir)
+}
+
+/// Replace an installation with a fresh copy of the built theme
+fn copy_install(theme: &CursorTheme, user_theme_dir: &Path, jobs: Option<usize>, preserve_times: bool) -> Result<()> {
+    copy_install_with(theme, user_theme_dir, jobs, preserve_times, replace_dir)
+}
+
+/// [`copy_install`], moving the staged copy into place with `replace`
+fn copy_install_with(
+    theme: &CursorTheme,
+    user_theme_dir: &Path,
+    jobs: Option<usize>,
+    preserve_times: bool,
+    replace: fn(&Path, &Path) -> std::io::Result<()>,
+) -> Result<()> {
+    let staging = sibling_dir(user_theme_dir, "tmp");
+    if let Err(err) = copy_theme(theme, &staging, jobs, preserve_times) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(err);
+    }
+    if let Err(err) = replace(&staging, user_theme_dir) {
+        detail!("Renaming {:?} into place failed ({}); copying directly", staging, err);
+        let _ = fs::remove_dir_all(&staging);
+
+        if user_theme_dir.exists() {
+            fs::remove_dir_all(user_theme_dir)
+                .with_context(|| format!("Failed to remove {:?}", user_theme_dir))?;
+        }
+        copy_theme(theme, user_theme_dir, jobs, preserve_times)?;
+    }
+    Ok(())
+}
+
+/// Check that an installed theme has the same cursors and aliases as the build
+///
+/// Both cursor sets and symlink graphs are compared, skipping what isn't
+/// installed (see [`is_installable`]), and so are the shapes of a hyprcursor
+/// theme. Any difference, such as an alias that was installed as a copy, is a
+/// `CursorError::InstallMismatch`.
+pub fn verify_install(theme: &CursorTheme, installed: &Path) -> Result<()> {
+    if !installed.is_dir() {
+        return Err(CursorError::ThemeNotFound(installed.to_path_buf()).into());
+    }
+    let expected = installable_graph(&theme.cursors_dir)?;
+    let actual = installable_graph(&installed.join("cursors"))?;
+
+    let mut differences = Vec::new();
+    for (cursor, aliases) in &expected {
+        let Some(installed_aliases) = actual.get(cursor) else {
+            differences.push(format!("cursor {} is missing", cursor));
+            continue;
+        };
+        for alias in aliases.iter().filter(|alias| !installed_aliases.contains(alias)) {
+            differences.push(format!("alias {} of {} is missing", alias, cursor));
+        }
+        for alias in installed_aliases.iter().filter(|alias| !aliases.contains(alias)) {
+            differences.push(format!("alias {} of {} is not in the build", alias, cursor));
+        }
+    }
+    for cursor in actual.keys().filter(|cursor| !expected.contains_key(*cursor)) {
+        differences.push(format!("cursor {} is not in the build", cursor));
+    }
+    for entry in installed_entries(theme)? {
+        if !theme.path.join(&entry).is_dir() {
+            continue;
+        }
+        let expected = installable_files(&theme.path.join(&entry))?;
+        let actual = installable_files(&installed.join(&entry))?;
+        for shape in expected.difference(&actual) {
+            differences.push(format!("shape {}/{} is missing", entry, shape));
+        }
+        for shape in actual.difference(&expected) {
+            differences.push(format!("shape {}/{} is not in the build", entry, shape));
+        }
+    }
+
+    if !differences.is_empty() {
+        return Err(CursorError::InstallMismatch { path: installed.to_path_buf(), differences }.into());
+    }
+    detail!("Verified {:?}: {} cursors match the build", installed, expected.len());
+    Ok(())
+}
+
+/// [`symlink_graph`] of a cursors directory, without entries that are never installed
+fn installable_graph(cursors_dir: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    if !cursors_dir.is_dir() {
+        return Ok(BTreeMap::new());
+    }
+    let installable = |name: &String| is_installable(Path::new(name));
+    Ok(symlink_graph(cursors_dir)?
+        .into_iter()
+        .filter(|(cursor, _)| installable(cursor))
+        .map(|(cursor, aliases)| (cursor, aliases.into_iter().filter(installable).collect()))
+        .collect())
+}
+
+/// Names of the installable files directly in `dir`, if it exists
+fn installable_files(dir: &Path) -> Result<BTreeSet<String>> {
+    if !dir.is_dir() {
+        return Ok(BTreeSet::new());
+    }
+    let mut files = BTreeSet::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if is_installable(&path) {
+            files.insert(path.file_name().unwrap_or_default().to_string_lossy().into_owned());
+        }
+    }
+    Ok(files)
+}
+
+/// Copy the installable parts of a built theme into a new directory
+fn copy_theme(theme: &CursorTheme, dest: &Path, jobs: Option<usize>, preserve_times: bool) -> Result<()> {
+    fs::create_dir_all(dest)?;
+
+    let copier = copy_fs(preserve_times);
+    if theme.cursors_dir.exists() {
+        FileUtils::copy_dir_with_jobs_filtered_with(copier, &theme.cursors_dir, dest.join("cursors"), jobs, is_installable)?;
+    }
+    for entry in installed_entries(theme)? {
+        let source = theme.path.join(&entry);
+        if source.is_dir() {
+            FileUtils::copy_dir_with_jobs_filtered_with(copier, &source, dest.join(&entry), jobs, is_installable)?;
+        } else {
+            FileUtils::copy_file_with(copier, &source, dest.join(&entry))
+                .with_context(|| format!("Failed to install {:?}", source))?;
+        }
+    }
+
+    FileUtils::set_permissions_recursive(dest, 0o755)
+}
+
+/// An unused path next to `dir`, named `.<dir>.<tag>-<id>`
+///
+/// The leading dot keeps it out of theme lists while it exists.
+fn sibling_dir(dir: &Path, tag: &str) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.subsec_nanos())
+        .unwrap_or_default();
+    let mut name = std::ffi::OsString::from(".");
+    name.push(dir.file_name().unwrap_or_default());
+    name.push(format!(".{}-{}-{}", tag, std::process::id(), nanos));
+    dir.with_file_name(name)
+}
+
+/// Move `staging` to `dest`, replacing whatever is there
+///
+/// The old directory is renamed aside first and removed once `staging` is
+/// in place; if the second rename fails it is moved back.
+fn replace_dir(staging: &Path, dest: &Path) -> std::io::Result<()> {
+    if !dest.exists() {
+        return fs::rename(staging, dest);
+    }
+    let old = sibling_dir(dest, "old");
+    fs::rename(dest, &old)?;
+    if let Err(err) = fs::rename(staging, dest) {
+        let _ = fs::rename(&old, dest);
+        return Err(err);
+    }
+    if let Err(err) = fs::remove_dir_all(&old) {
+        warn!("Failed to remove the previous installation {:?}: {}", old, err);
+    }
+    Ok(())
+}
+
+/// Bring an existing installation up to date with a built theme
+fn sync_theme(theme: &CursorTheme, user_theme_dir: &Path, preserve_times: bool) -> Result<()> {
+    fs::create_dir_all(user_theme_dir)?;
+
+    let copier = copy_fs(preserve_times);
+    let mut installed = Vec::new();
+    if theme.cursors_dir.exists() {
+        FileUtils::copy_dir_recursive_sync_filtered_with(copier, &theme.cursors_dir, user_theme_dir.join("cursors"), &is_installable)?;
+        installed.push("cursors".to_string());
+    }
+    for entry in installed_entries(theme)? {
+        let source = theme.path.join(&entry);
+        if source.is_dir() {
+            FileUtils::copy_dir_recursive_sync_filtered_with(copier, &source, user_theme_dir.join(&entry), &is_installable)?;
+        } else {
+            FileUtils::sync_file_with(copier, &source, user_theme_dir.join(&entry))
+                .with_context(|| format!("Failed to install {:?}", source))?;
+        }
+        installed.push(entry);
+    }
+
+    // Whatever the build no longer produces
+    for entry in fs::read_dir(user_theme_dir)? {
+        let path = entry?.path();
+        if !path.file_name().is_some_and(|name| installed.iter().any(|entry| name == entry.as_str())) {
+            FileUtils::remove_path(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Render rows as a table with a header line and left-aligned, padded columns
+///
+/// The last column is not padded so lines carry no trailing spaces.
+pub fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let render_row = |cells: Vec<&str>| {
+        let last = cells.len().saturating_sub(1);
+        let mut line = String::new();
+        for (i, (cell, width)) in cells.iter().zip(&widths).enumerate() {
+            if i == last {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:<width$}  ", cell, width = width));
+            }
+        }
+        line
+    };
+
+    let mut lines = vec![render_row(headers.to_vec())];
+    for row in rows {
+        lines.push(render_row(row.iter().map(String::as_str).collect()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alias_links_skip_missing_targets_and_keep_existing_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cursors = dir.path();
+        fs::write(cursors.join("left_ptr"), b"arrow").unwrap();
+        fs::write(cursors.join("default"), b"real cursor").unwrap();
+
+        let links = create_alias_links(cursors, false, LinkMode::Symlink).unwrap();
+
+        assert_eq!(fs::read_link(cursors.join("arrow")).unwrap(), Path::new("left_ptr"));
+        assert_eq!(fs::read(cursors.join("default")).unwrap(), b"real cursor");
+        assert!(links.created.contains(&"arrow") && !links.created.contains(&"default"));
+        assert!(links.skipped.contains(&("text", "xterm")));
+        assert!(!cursors.join("ibeam").exists());
+    }
+
+    #[test]
+    fn normalized_names_are_lowercase() {
+        assert_eq!(output_cursor_name("Left_Ptr", true), "left_ptr");
+        assert_eq!(output_cursor_name("Left_Ptr", false), "Left_Ptr");
+    }
+
+    #[test]
+    fn install_copies_theme_files_and_replaces_old_install() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let icons = dir.path().join("icons");
+
+        let theme = CursorTheme::new("Common-Test".to_string(), dir.path().join("build"));
+        theme.create_directories().unwrap();
+        fs::write(theme.cursors_dir.join("left_ptr"), b"arrow").unwrap();
+        fs::write(theme.path.join("index.theme"), "[Icon Theme]\n").unwrap();
+        fs::write(theme.path.join("cursor.theme"), "[Icon Theme]\n").unwrap();
+        fs::write(theme.path.join("notes.txt"), "scratch").unwrap();
+        fs::write(theme.cursors_dir.join("left_ptr.bak"), b"old arrow").unwrap();
+        fs::create_dir_all(theme.cursors_dir.join(".git/objects")).unwrap();
+        fs::write(theme.cursors_dir.join(".git/objects/pack"), b"git").unwrap();
+
+        let stale = icons.join("Common-Test");
+        fs::create_dir_all(&stale).unwrap();
+        fs::write(stale.join("stale"), "old").unwrap();
+
+        let installed = install_theme(&theme, &icons, None, false).unwrap();
+
+        assert_eq!(installed, stale);
+        assert!(installed.join("cursors/left_ptr").is_file());
+        assert!(installed.join("index.theme").is_file());
+        assert!(installed.join("cursor.theme").is_file());
+        assert!(!installed.join("notes.txt").exists());
+        assert!(!installed.join("cursors/left_ptr.bak").exists());
+        assert!(!installed.join("cursors/.git").exists());
+        assert!(!installed.join("stale").exists());
+        // The staging and replaced directories are gone
+        assert_eq!(fs::read_dir(&icons).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn install_copies_directly_when_the_rename_fails() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let icons = dir.path().join("icons");
+        let theme = CursorTheme::new("Fallback-Test".to_string(), dir.path().join("build"));
+        theme.create_directories().unwrap();
+        fs::write(theme.cursors_dir.join("left_ptr"), b"arrow").unwrap();
+
+        let installed = icons.join("Fallback-Test");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(installed.join("stale"), "old").unwrap();
+
+        assert!(sibling_dir(&installed, "tmp").file_name().unwrap().to_string_lossy().starts_with(".Fallback-Test.tmp-"));
+        copy_install_with(&theme, &installed, None, false, |_, _| Err(std::io::ErrorKind::CrossesDevices.into())).unwrap();
+
+        assert_eq!(fs::read(installed.join("cursors/left_ptr")).unwrap(), b"arrow");
+        assert!(!installed.join("stale").exists());
+        assert_eq!(fs::read_dir(&icons).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn verify_install_catches_dereferenced_aliases() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = CursorTheme::new("Verify-Test".to_string(), dir.path().join("build"));
+        theme.create_directories().unwrap();
+        fs::write(theme.cursors_dir.join("left_ptr"), b"arrow").unwrap();
+        std::os::unix::fs::symlink("left_ptr", theme.cursors_dir.join("default")).unwrap();
+
+        let installed = install_theme(&theme, &dir.path().join("icons"), None, false).unwrap();
+        verify_install(&theme, &installed).unwrap();
+
+        fs::remove_file(installed.join("cursors/default")).unwrap();
+        fs::write(installed.join("cursors/default"), b"arrow").unwrap();
+        let err = verify_install(&theme, &installed).unwrap_err();
+        let Some(CursorError::InstallMismatch { differences, .. }) = err.downcast_ref::<CursorError>() else {
+            panic!("unexpected error: {:#}", err);
+        };
+        assert_eq!(differences, &["alias default of left_ptr is missing", "cursor default is not in the build"]);
+    }
+
+    #[test]
+    fn hyprcursor_installs_carry_and_verify_their_shapes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = CursorTheme::new("Hypr-Test".to_string(), dir.path().join("build"));
+        fs::create_dir_all(theme.path.join("shapes")).unwrap();
+        fs::write(theme.path.join("manifest.hl"), "name = Hypr-Test\ncursors_directory = shapes\n").unwrap();
+        fs::write(theme.path.join("shapes/left_ptr.hlc"), b"hlc").unwrap();
+        fs::write(theme.path.join("notes.txt"), "scratch").unwrap();
+
+        let installed = install_theme(&theme, &dir.path().join("icons"), None, false).unwrap();
+        assert!(installed.join("manifest.hl").is_file());
+        assert!(installed.join("shapes/left_ptr.hlc").is_file());
+        assert!(!installed.join("notes.txt").exists());
+        verify_install(&theme, &installed).unwrap();
+
+        fs::rename(installed.join("shapes/left_ptr.hlc"), installed.join("shapes/pointer.hlc")).unwrap();
+        let err = verify_install(&theme, &installed).unwrap_err();
+        let Some(CursorError::InstallMismatch { differences, .. }) = err.downcast_ref::<CursorError>() else {
+            panic!("unexpected error: {:#}", err);
+        };
+        assert_eq!(differences, &["shape shapes/left_ptr.hlc is missing", "shape shapes/pointer.hlc is not in the build"]);
+    }
+
+    #[test]
+    fn install_in_place_is_a_no_op() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = CursorTheme::new("In-Place".to_string(), dir.path().join("In-Place"));
+        theme.create_directories().unwrap();
+        fs::write(theme.cursors_dir.join("left_ptr"), b"arrow").unwrap();
+
+        let installed = install_theme(&theme, dir.path(), None, false).unwrap();
+
+        assert_eq!(installed, theme.path);
+        assert!(theme.cursors_dir.join("left_ptr").is_file());
+    }
+
+    #[test]
+    fn table_columns_are_padded_to_the_widest_cell() {
+        let rows = vec![
+            vec!["Bibata".to_string(), "12".to_string(), "yes".to_string()],
+            vec!["A".to_string(), "140".to_string(), "no".to_string()],
+        ];
+
+        let table = render_table(&["NAME", "CURSORS", "HYPRCURSOR"], &rows);
+
+        assert_eq!(table, "NAME    CURSORS  HYPRCURSOR\nBibata  12       yes\nA       140      no");
+    }
+}
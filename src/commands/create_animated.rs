@@ -1,363 +1,1666 @@
 use anyhow::{Context, Result};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::warn;
+use rayon::prelude::*;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::common::{create_alias_links, install_to_icons_dirs, output_cursor_name};
+use super::create_hyprcursor::{create_hyprcursor_theme_native, CreateHyprcursorArgs};
+use super::{
+    hex, print_warnings, read_source_index, skipped_alias_warnings, validate::check_index_sizes, CursorFilter, ThemeReport, Warning, WarningKind,
+    Warnings,
+};
 use crate::{
-    cursor_mapping::{get_cursor_symlinks, get_cursor_hotspot},
-    theme_config::{create_theme_files, STANDARD_SIZES},
-    CursorTheme, FileUtils, CommandUtils, get_icons_dir,
+    compose::{apply_shadow, HotspotShift, ShadowParams},
+    cursor_mapping::{get_cursor_hotspot, hotspot_ratio, resolve_hotspot, HotspotOverride, HotspotOverrides},
+    quantize::{dither_to_rgba8, FrameDepth},
+    recolor::{recolor_frame, RecolorOp},
+    theme_config::{check_theme_comment, create_theme_files, parse_xcursorgen_config, resolve_sizes, ConfigLine, IndexTheme, ResizeAlgorithm},
+    xcursor::Xcursor,
+    CursorError, CursorTheme, FileUtils, InstallScope, LinkMode, CommandUtils, copy_fs, get_icons_dir, get_theme_dir, create_scratch_dir,
+    sanitize_display_name, sanitize_theme_name, show_progress, update_icon_caches, detail, status,
 };
 
 /// Arguments for the create-animated command
 #[derive(Debug)]
 pub struct CreateAnimatedArgs {
-    pub input_theme: String,
+    /// Source themes, merged in order: a later theme's cursor replaces an
+    /// earlier one's of the same name
+    pub input_themes: Vec<String>,
     pub output_theme: String,
+    /// `Name` written to index.theme and cursor.theme (default: the input themes'
+    /// `Name`, else the theme name); variants append their suffix
+    pub display_name: Option<String>,
+    pub temp_dir: Option<PathBuf>,
+    /// Directory the output themes are built in (default: the current directory)
+    pub theme_dir: Option<PathBuf>,
+    /// Where to install the built themes
+    pub install_scope: InstallScope,
+    pub jobs: Option<usize>,
+    /// Give copied and installed files the modification time of their source
+    pub preserve_times: bool,
+    pub command_timeout: Option<Duration>,
+    /// Extra attempts for an xcursorgen run that exits with an error
+    pub retries: u32,
+    /// Re-extract each generated cursor to check its images
+    pub verify: bool,
+    /// Recolor every frame (uses the native image pipeline instead of ImageMagick)
+    pub recolor: Option<RecolorOp>,
+    /// Themes to generate from the same source; empty means a single
+    /// `output_theme` using `recolor`
+    pub variants: Vec<VariantSpec>,
+    /// Drop shadow composited beneath every frame (native image pipeline)
+    pub shadow: Option<ShadowParams>,
+    /// Recreate alias symlinks that already exist
+    pub relink: bool,
+    /// Keep only the source theme's own symlinks instead of adding the standard aliases
+    pub preserve_source_links: bool,
+    /// Write aliases as symlinks or as copies of their target
+    pub link_mode: LinkMode,
+    /// Record the build parameters in `<theme>/.koosh-build.json`
+    pub emit_build_manifest: bool,
+    /// Delay for frames whose source gives none, in milliseconds
+    pub frame_delay_ms: u32,
+    /// Multiplier applied to every frame delay after the base delay is chosen
+    pub delay_scale: f32,
+    /// Show progress bars over cursors and frames
+    pub progress: bool,
+    /// Abort on the first cursor that fails instead of collecting failures
+    pub fail_fast: bool,
+    /// Source cursors to build
+    pub filter: CursorFilter,
+    /// How each of `input_themes` is laid out
+    pub source_kind: SourceKind,
+    /// Absolute hotspots that replace the built-in ratios
+    pub hotspot_overrides: HotspotOverrides,
+    /// `Comment` written to index.theme and cursor.theme (default: the input
+    /// themes' `Comment`, else [`DEFAULT_COMMENT`])
+    pub comment: Option<String>,
+    /// Themes to inherit from (default: hicolor)
+    pub inherits: Vec<String>,
+    /// Rebuild every cursor, ignoring the cache from the previous build
+    pub force: bool,
+    /// Lowercase cursor and alias names in the output themes
+    pub normalize_names: bool,
+    /// Which themes to produce from each variant
+    pub out_format: OutFormat,
+    /// `resize_algorithm` of the hyprcursor shapes (default: bilinear)
+    pub resize_algorithm: Option<ResizeAlgorithm>,
+    /// `version` in the hyprcursor theme's manifest
+    pub theme_version: String,
+    /// Sizes to generate, ahead of `KOOSH_SIZES` and the standard sizes
+    pub sizes: Option<Vec<u32>>,
+    /// Sizes left out of the cursors and index.theme
+    pub exclude_sizes: Vec<u32>,
+    /// Size the source frames are taken to be, instead of the detected one
+    pub source_size: Option<u32>,
+    /// Bit depth and dithering of natively scaled frames
+    pub frame_depth: FrameDepth,
+    /// Directory of hand-written `<cursor>.config` xcursorgen configs, used
+    /// as they are for the cursors they cover
+    pub xcursorgen_config: Option<PathBuf>,
 }
 
-/// Create animated cursor theme with multi-size support
-pub fn create_animated_theme(args: CreateAnimatedArgs) -> Result<()> {
-    println!("=== Koosh Cursor Theme Creator ===");
-    println!("This tool will create a new cursor theme with:");
-    println!("- Multi-size support (24, 32, 48, 64, 72, 96)");
-    println!("- Proper hotspots for all cursors");
-    println!("- 'not-allowed' and 'unavailable' cursors using the same hotspot as 'left_ptr'");
-    println!("- All temporary files will be removed after completion");
-    println!("===============================");
-    println!("Input theme: {}", args.input_theme);
-    println!("Output theme: {}", args.output_theme);
-    println!("===============================");
-
-    // Check if input theme exists
-    let input_path = PathBuf::from(&args.input_theme);
-    if !input_path.exists() {
-        return Err(anyhow::anyhow!(
-            "Error: Input theme directory '{}' not found!",
-            args.input_theme
-        ));
+/// Theme comment used when none is given
+pub const DEFAULT_COMMENT: &str = "Koosh cursor theme with proper animation support";
+
+/// Layout of the source cursors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceKind {
+    /// A theme directory with XCursor files in `cursors/`, split with xcur2png
+    #[default]
+    Xcursor,
+    /// A directory with one subdirectory of numbered PNG frames per cursor,
+    /// plus an optional `hotspot` file holding `x y` in source pixels
+    PngFrames,
+}
+
+/// Themes produced from each built variant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutFormat {
+    /// The XCursor theme only
+    #[default]
+    Xcursor,
+    /// Only the hyprcursor theme built from it; the XCursor theme is built but not installed
+    Hyprcursor,
+    /// Both, installed as `<name>` and `<name>-Hyprcursor`
+    Both,
+}
+
+/// Appended to a variant's name for its hyprcursor theme
+pub const HYPRCURSOR_SUFFIX: &str = "-Hyprcursor";
+
+/// Name of the hotspot file inside a PNG frame directory
+pub const HOTSPOT_FILE: &str = "hotspot";
+
+/// Delay for frames whose source gives none, unless `--frame-delay` is given
+pub const DEFAULT_FRAME_DELAY_MS: u32 = 100;
+
+/// Unit of `--frame-delay`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DelayUnit {
+    /// Milliseconds, as xcursorgen expects
+    #[default]
+    Ms,
+    /// Centiseconds (1cs = 10ms), as used by GIF and some other tools
+    Cs,
+}
+
+impl DelayUnit {
+    /// Convert a delay in this unit to milliseconds
+    pub fn to_ms(self, delay: u32) -> u32 {
+        match self {
+            Self::Ms => delay,
+            Self::Cs => delay.saturating_mul(10),
+        }
     }
+}
 
-    let input_cursors = input_path.join("cursors");
-    if !input_cursors.exists() {
-        return Err(anyhow::anyhow!(
-            "Error: Input theme cursors directory '{}/cursors' not found!",
-            args.input_theme
-        ));
+/// Scale a frame delay, never going below 1ms
+pub fn scale_delay(delay_ms: u32, scale: f32) -> u32 {
+    ((delay_ms as f64 * scale as f64).round() as u32).max(1)
+}
+
+/// File the build parameters are written to with `--emit-build-manifest`
+pub const BUILD_MANIFEST_FILE: &str = ".koosh-build.json";
+
+/// File in each output theme recording the sources of its cursors
+pub const BUILD_CACHE_FILE: &str = ".koosh-cache.json";
+
+/// One output theme generated from the shared source frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantSpec {
+    /// Appended to the output theme name (empty for the base theme)
+    pub suffix: String,
+    pub recolor: Option<RecolorOp>,
+}
+
+/// An output theme being built in this run
+struct ThemeVariant {
+    theme: CursorTheme,
+    display_name: Option<String>,
+    recolor: Option<RecolorOp>,
+    /// The previous build, moved aside so unchanged cursors can be reused
+    previous: Option<PreviousBuild>,
+}
+
+/// A variant's previous output and the cache it was built with
+struct PreviousBuild {
+    cursors_dir: PathBuf,
+    cache: BuildCache,
+}
+
+/// Source hashes of the cursors in a built theme (`.koosh-cache.json`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildCache {
+    /// Fingerprint of the build options; entries only apply to the same options
+    parameters: String,
+    cursors: BTreeMap<String, CachedCursor>,
+}
+
+/// One built cursor: the hash of its source and the hotspot it was built with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedCursor {
+    source_hash: String,
+    hotspot: (f64, f64),
+    /// The original cursor was copied because a tool failed; such cursors are
+    /// left out of the cache so the next run tries (and warns) again
+    #[serde(skip)]
+    fallback: bool,
+}
+
+impl BuildCache {
+    /// Read the cache of a built theme; a missing or unreadable cache is `None`
+    fn read(theme_path: &Path) -> Option<Self> {
+        let data = fs::read_to_string(theme_path.join(BUILD_CACHE_FILE)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    fn write(&self, theme_path: &Path) -> Result<()> {
+        let path = theme_path.join(BUILD_CACHE_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+/// Parameters a variant was built with, so rebuilds can be compared
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildParameters {
+    /// Delay for frames whose source gives none, after `delay_scale`
+    pub frame_delay_ms: u32,
+    pub delay_scale: f32,
+    pub sizes: Vec<u32>,
+    /// `lanczos3` for the native pipeline, `imagemagick` when resizing with ImageMagick
+    pub scale_filter: &'static str,
+    /// Hotspot (x, y) ratios applied to each source cursor
+    pub hotspots: BTreeMap<String, (f64, f64)>,
+}
+
+/// Report for one built variant
+#[derive(Debug, Serialize)]
+pub struct VariantReport {
+    #[serde(flatten)]
+    pub theme: ThemeReport,
+    pub build: BuildParameters,
+    /// The hyprcursor theme built from this variant, with `--out-format`
+    pub hyprcursor: Option<ThemeReport>,
+}
+
+/// Summary of a create-animated run, one entry per built variant
+#[derive(Debug, Serialize)]
+pub struct CreateAnimatedReport {
+    pub themes: Vec<VariantReport>,
+    /// Cursors whose source was unchanged, copied from the previous build
+    pub reused: Vec<String>,
+    /// Warnings from processing the source cursors, shared by every variant
+    pub warnings: Vec<Warning>,
+    /// Cursors whose regenerated image count doesn't match the source
+    pub suspect: Vec<String>,
+}
+
+/// Per-run settings threaded through the cursor pipeline
+#[derive(Debug, Default)]
+struct BuildOptions {
+    command_timeout: Option<Duration>,
+    /// Times to run xcursorgen before giving up on a cursor
+    attempts: u32,
+    verify: bool,
+    /// How frames are scaled when nothing needs the native pipeline
+    image_backend: ImageBackend,
+    /// Sizes every cursor is generated at, ascending
+    sizes: Vec<u32>,
+    shadow: Option<ShadowParams>,
+    /// Delay for frames whose source gives none, after `delay_scale`
+    frame_delay_ms: u32,
+    /// Applied to delays read from the source cursor
+    delay_scale: f32,
+    progress: bool,
+    fail_fast: bool,
+    source_kind: SourceKind,
+    hotspot_overrides: HotspotOverrides,
+    normalize_names: bool,
+    /// Overrides the detected size of every cursor's frames
+    source_size: Option<u32>,
+    /// Where to look for a cursor's own xcursorgen config
+    xcursorgen_config: Option<PathBuf>,
+    /// How the native pipeline stores scaled frames
+    frame_depth: FrameDepth,
+    /// Copied cursors keep the modification time of their source
+    preserve_times: bool,
+    /// Collects warnings from every cursor and frame
+    warnings: Warnings,
+}
+
+impl BuildOptions {
+    /// Describe everything besides the sources that changes a variant's output
+    fn fingerprint(&self, recolor: Option<RecolorOp>) -> String {
+        let overrides: BTreeMap<_, _> = self.hotspot_overrides.iter().collect();
+        format!(
+            "{} {:?} {:?} {:?} {} {} {:?} {:?} {:?} {:?} {:?} {:?}",
+            env!("CARGO_PKG_VERSION"),
+            recolor,
+            self.image_backend,
+            self.shadow,
+            self.frame_delay_ms,
+            self.delay_scale,
+            self.source_kind,
+            overrides,
+            self.sizes,
+            self.source_size,
+            self.xcursorgen_config,
+            self.frame_depth,
+        )
     }
+}
 
-    // Create output theme
-    let output_theme = CursorTheme::new(args.output_theme.clone(), PathBuf::from(&args.output_theme));
-    if output_theme.path.exists() {
-        fs::remove_dir_all(&output_theme.path)?;
+/// Create animated cursor theme with multi-size support
+pub fn create_animated_theme(mut args: CreateAnimatedArgs) -> Result<CreateAnimatedReport> {
+    args.output_theme = sanitize_theme_name(&args.output_theme)?;
+    args.display_name = args.display_name.as_deref().map(sanitize_display_name).transpose()?;
+    args.comment = args.comment.as_deref().map(check_theme_comment).transpose()?;
+    for spec in &args.variants {
+        sanitize_theme_name(&format!("{}{}", args.output_theme, spec.suffix))?;
+    }
+    if args.resize_algorithm.is_some() && args.out_format == OutFormat::Xcursor {
+        anyhow::bail!("--resize-algorithm only applies to --out-format hyprcursor or both");
     }
-    output_theme.create_directories()?;
 
-    // Create user's .icons directory
-    let user_icons_dir = get_icons_dir()?.join(&args.output_theme);
-    if user_icons_dir.exists() {
-        fs::remove_dir_all(&user_icons_dir)?;
+    // There is no config file yet to take sizes from
+    let sizes = output_sizes(&resolve_sizes(args.sizes.as_deref(), None)?, &args.exclude_sizes)?;
+    let size_list: Vec<String> = sizes.iter().map(u32::to_string).collect();
+
+    status!("=== Koosh Cursor Theme Creator ===");
+    status!("This tool will create a new cursor theme with:");
+    status!("- Multi-size support ({})", size_list.join(", "));
+    status!("- Proper hotspots for all cursors");
+    status!("- 'not-allowed' and 'unavailable' cursors using the same hotspot as 'left_ptr'");
+    status!("- All temporary files will be removed after completion");
+    status!("===============================");
+    for input_theme in &args.input_themes {
+        status!("Input theme: {}", input_theme);
     }
-    fs::create_dir_all(&user_icons_dir.join("cursors"))?;
+    status!("Output theme: {}", args.output_theme);
+    status!("===============================");
+
+    // Release archives are unpacked into this run's own scratch directory,
+    // created with the first archive and removed on drop if the build fails
+    let mut archive_dir = None;
+    let mut input_dirs = Vec::new();
+    let mut source_index = IndexTheme::default();
+    for (index, input_theme) in args.input_themes.iter().enumerate() {
+        // Check if input theme exists
+        let mut input_path = resolve_input_theme(input_theme)?;
+        if is_theme_archive(&input_path) {
+            let archive_dir = match archive_dir.take() {
+                Some(dir) => archive_dir.insert(dir),
+                None => archive_dir.insert(create_scratch_dir(args.temp_dir.as_deref(), "koosh_archive_temp")?),
+            };
+            input_path = extract_theme_archive(&input_path, &archive_dir.path().join(index.to_string()), args.source_kind)?;
+        }
+        let index = read_source_index(&input_path);
+        source_index.name = index.name.or(source_index.name);
+        source_index.comment = index.comment.or(source_index.comment);
+
+        let input_cursors = match args.source_kind {
+            SourceKind::Xcursor => input_path.join("cursors"),
+            SourceKind::PngFrames => input_path,
+        };
+        if !input_cursors.exists() {
+            return Err(CursorError::ThemeNotFound(input_cursors).into());
+        }
+        input_dirs.push(input_cursors);
+    }
+
+    // Name and Comment that weren't given come from the sources' index.theme
+    if args.display_name.is_none() {
+        args.display_name = source_index.name;
+    }
+    let comment = args.comment.take()
+        .or(source_index.comment)
+        .unwrap_or_else(|| DEFAULT_COMMENT.to_string());
+
+    // Create output themes, one per variant
+    let variant_specs = if args.variants.is_empty() {
+        vec![VariantSpec { suffix: String::new(), recolor: args.recolor }]
+    } else {
+        args.variants.clone()
+    };
 
-    // Create temporary directory
-    let temp_dir = PathBuf::from("koosh_animated_temp");
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
+    let mut options = BuildOptions {
+        command_timeout: args.command_timeout,
+        attempts: args.retries + 1,
+        verify: args.verify,
+        image_backend: ImageBackend::detect(),
+        sizes,
+        shadow: args.shadow,
+        frame_delay_ms: scale_delay(args.frame_delay_ms, args.delay_scale),
+        delay_scale: args.delay_scale,
+        progress: args.progress,
+        fail_fast: args.fail_fast,
+        source_kind: args.source_kind,
+        hotspot_overrides: std::mem::take(&mut args.hotspot_overrides),
+        normalize_names: args.normalize_names,
+        source_size: args.source_size,
+        xcursorgen_config: args.xcursorgen_config.clone(),
+        frame_depth: args.frame_depth,
+        preserve_times: args.preserve_times,
+        warnings: Warnings::default(),
+    };
+    let theme_dir = get_theme_dir(args.theme_dir.as_deref())?;
+    let mut variants = Vec::new();
+    for spec in &variant_specs {
+        let theme_name = format!("{}{}", args.output_theme, spec.suffix);
+        let theme = CursorTheme::new(theme_name.clone(), theme_dir.join(&theme_name));
+        let previous = take_previous_build(&theme, &options.fingerprint(spec.recolor), args.force)?;
+        theme.create_directories()?;
+
+        variants.push(ThemeVariant {
+            theme,
+            display_name: args.display_name.as_ref().map(|name| format!("{}{}", name, spec.suffix)),
+            recolor: spec.recolor,
+            previous,
+        });
+    }
+    if options.frame_depth != FrameDepth::default() {
+        let scaled_elsewhere: Vec<&str> = variants.iter()
+            .filter(|variant| !uses_native_pipeline(variant, &options))
+            .map(|variant| variant.theme.name.as_str())
+            .collect();
+        if !scaled_elsewhere.is_empty() {
+            warn!(
+                "--dither and --bit-depth only apply to frames scaled natively; ImageMagick scales {} as usual",
+                scaled_elsewhere.join(", ")
+            );
+        }
     }
-    fs::create_dir_all(&temp_dir)?;
+
+    // Create this run's temporary directory; it is removed on drop if the build fails
+    let temp_dir = create_scratch_dir(args.temp_dir.as_deref(), "koosh_animated_temp")?;
 
     // Process each cursor file
-    process_cursor_files(&input_cursors, &output_theme, &temp_dir)?;
+    // Frame scaling runs on this pool, so --jobs bounds all of the build's threads
+    let mut pool = rayon::ThreadPoolBuilder::new();
+    if let Some(jobs) = args.jobs {
+        pool = pool.num_threads(jobs.max(1));
+    }
+    let pool = pool.build().context("Failed to create build thread pool")?;
+    let (built, reused, failures) = pool.install(|| {
+        process_cursor_files(&input_dirs, &variants, temp_dir.path(), &options, &args.filter)
+    })?;
+    let hotspots: Hotspots = built.iter()
+        .map(|(name, cursor)| (name.clone(), cursor.hotspot))
+        .collect();
+    if !reused.is_empty() {
+        status!("Reused {} unchanged cursors from the previous build", reused.len());
+    }
+
+    let warnings = std::mem::take(&mut options.warnings).into_vec();
+    let mut suspect: Vec<String> = warnings.iter()
+        .filter(|warning| warning.kind == WarningKind::SuspectCursor)
+        .filter_map(|warning| warning.cursor.clone())
+        .collect();
+    suspect.sort();
+    suspect.dedup();
+    let mut report = CreateAnimatedReport { themes: Vec::new(), reused, warnings, suspect };
+    for variant in &variants {
+        if let Some(previous) = &variant.previous {
+            if let Some(dir) = previous.cursors_dir.parent() {
+                fs::remove_dir_all(dir)?;
+            }
+        }
+        let output_theme = &variant.theme;
+
+        // Create additional symlinks, unless the source's own are all that's wanted
+        let skipped = if args.preserve_source_links {
+            Vec::new()
+        } else {
+            create_alias_links(&output_theme.cursors_dir, args.relink, args.link_mode)?.skipped
+        };
+        if !skipped.is_empty() {
+            status!("{}: {} aliases skipped due to missing targets", output_theme.name, skipped.len());
+        }
 
-    // Create additional symlinks
-    create_additional_symlinks(&output_theme.cursors_dir)?;
+        // Create theme files
+        create_theme_files(
+            &output_theme.path,
+            &output_theme.name,
+            variant.display_name.as_deref(),
+            &comment,
+            Some(&options.sizes),
+            &args.inherits,
+        )?;
+
+        let build = BuildParameters {
+            frame_delay_ms: options.frame_delay_ms,
+            delay_scale: args.delay_scale,
+            sizes: options.sizes.clone(),
+            scale_filter: if uses_native_pipeline(variant, &options) { "lanczos3" } else { "imagemagick" },
+            hotspots: hotspots.clone(),
+        };
+        let cache = BuildCache {
+            parameters: options.fingerprint(variant.recolor),
+            cursors: built.iter()
+                .filter(|(_, cursor)| !cursor.fallback)
+                .map(|(name, cursor)| (name.clone(), cursor.clone()))
+                .collect(),
+        };
+        cache.write(&output_theme.path)?;
+
+        if args.emit_build_manifest {
+            let manifest_path = output_theme.path.join(BUILD_MANIFEST_FILE);
+            fs::write(&manifest_path, serde_json::to_string_pretty(&build)?)
+                .with_context(|| format!("Failed to write {:?}", manifest_path))?;
+        }
 
-    // Create theme files
-    create_theme_files(
-        &output_theme.path,
-        &args.output_theme,
-        "Koosh cursor theme with proper animation support",
-        Some(STANDARD_SIZES),
-    )?;
+        // The index must not promise sizes xcursorgen didn't produce
+        let mut warnings = skipped_alias_warnings(&skipped);
+        for problem in check_index_sizes(output_theme)? {
+            warn!("{}: {}", output_theme.name, problem);
+            warnings.push(Warning::new(WarningKind::MissingSize, None, problem));
+        }
 
-    // Install to user's .icons directory
-    install_to_user_icons(&output_theme, &user_icons_dir)?;
+        // Install to the icons directories
+        let install_paths = if args.out_format == OutFormat::Hyprcursor {
+            Vec::new()
+        } else {
+            install_to_icons_dirs(output_theme, args.install_scope, args.jobs, args.preserve_times)?
+        };
 
-    // Set permissions
-    FileUtils::set_permissions_recursive(&output_theme.path, 0o755)?;
-    FileUtils::set_permissions_recursive(&user_icons_dir, 0o755)?;
+        // Set permissions
+        FileUtils::set_permissions_recursive(&output_theme.path, 0o755)?;
 
-    // Update icon cache
-    update_icon_cache(&args.output_theme)?;
+        // Update icon cache
+        for path in &install_paths {
+            update_icon_caches(path, args.command_timeout);
+        }
+
+        // The hyprcursor theme reads the cursors just built instead of extracting them again
+        let hyprcursor = match args.out_format {
+            OutFormat::Xcursor => None,
+            OutFormat::Hyprcursor | OutFormat::Both => Some(create_hyprcursor_theme_native(CreateHyprcursorArgs {
+                source: output_theme.path.clone(),
+                dest_theme: format!("{}{}", output_theme.name, HYPRCURSOR_SUFFIX),
+                // Named after this theme rather than the source it's built from
+                display_name: Some(format!(
+                    "{}{}",
+                    variant.display_name.as_deref().unwrap_or(&output_theme.name),
+                    HYPRCURSOR_SUFFIX
+                )),
+                comment: Some(comment.clone()),
+                inherits: args.inherits.clone(),
+                install_scope: args.install_scope,
+                svg_dir: None,
+                temp_dir: args.temp_dir.clone(),
+                jobs: args.jobs,
+                preserve_times: args.preserve_times,
+                command_timeout: args.command_timeout,
+                retries: args.retries,
+                keep_temp: false,
+                resize_algorithm: args.resize_algorithm,
+                theme_version: args.theme_version.clone(),
+            })?),
+        };
+
+        report.themes.push(VariantReport {
+            theme: ThemeReport::collect(
+                output_theme,
+                install_paths,
+                warnings,
+            )?,
+            build,
+            hyprcursor,
+        });
+    }
 
     // Clean up
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
+    temp_dir.close().context("Failed to remove the temporary directory")?;
+    if let Some(archive_dir) = archive_dir {
+        archive_dir.close().context("Failed to remove the unpacked archives")?;
     }
 
-    // Remove .conf files
-    println!("Removing .conf files...");
-    for entry in fs::read_dir(".")? {
-        let entry = entry?;
-        if let Some(ext) = entry.path().extension() {
-            if ext == "conf" {
-                fs::remove_file(entry.path())?;
+    for variant in &report.themes {
+        status!("Done! Created animated cursor theme: {:?}", variant.theme.output_path);
+        status!("  {}", variant.theme.contents_summary());
+        for path in &variant.theme.install_paths {
+            status!("Also installed to: {:?}", path);
+        }
+        if let Some(hyprcursor) = &variant.hyprcursor {
+            status!("Created hyprcursor theme: {:?}", hyprcursor.output_path);
+            status!("  {}", hyprcursor.contents_summary());
+        }
+    }
+    print_warnings(report.warnings.iter().chain(report.themes.iter().flat_map(|variant| &variant.theme.warnings)));
+
+    if !failures.is_empty() {
+        status!("Failed cursors:");
+        for (name, err) in &failures {
+            status!("  {}: {:#}", name, err);
+        }
+        let names = failures.into_iter().map(|(name, _)| name).collect();
+        return Err(CursorError::CursorsFailed(names).into());
+    }
+
+    Ok(report)
+}
+
+/// Resolve the input theme: the literal path, else a theme of that name in ~/.icons
+///
+/// A missing theme is reported as `CursorError::ThemeNotFound` for the
+/// literal path, with every location tried in the context.
+fn resolve_input_theme(input_theme: &str) -> Result<PathBuf> {
+    let literal = PathBuf::from(input_theme);
+    if literal.exists() {
+        return Ok(literal);
+    }
+
+    let mut tried = vec![literal.clone()];
+    // Only bare names are looked up in the icons directory
+    if literal.components().count() == 1 && sanitize_theme_name(input_theme).is_ok() {
+        let installed = get_icons_dir()?.join(input_theme);
+        if installed.exists() {
+            status!("Using installed theme: {:?}", installed);
+            return Ok(installed);
+        }
+        tried.push(installed);
+    }
+
+    let tried = tried.iter()
+        .map(|path| format!("{:?}", path))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(anyhow::Error::new(CursorError::ThemeNotFound(literal))
+        .context(format!("Input theme {:?} not found (tried {})", input_theme, tried)))
+}
+
+/// Whether an input theme path names a `.tar.gz` (or `.tgz`) archive
+fn is_theme_archive(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    path.is_file() && (name.ends_with(".tar.gz") || name.ends_with(".tgz"))
+}
+
+/// Unpack a theme archive into `dest` and return the theme directory inside it
+///
+/// For XCursor sources this is the parent of the shallowest `cursors`
+/// directory; for PNG frame sources it is the archive root, skipping a single
+/// top-level directory. Any previous contents of `dest` are removed.
+fn extract_theme_archive(archive: &Path, dest: &Path, source_kind: SourceKind) -> Result<PathBuf> {
+    status!("Extracting {:?}...", archive);
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::create_dir_all(dest)?;
+
+    let file = fs::File::open(archive)
+        .with_context(|| format!("Failed to open archive {:?}", archive))?;
+    tar::Archive::new(flate2::read::GzDecoder::new(file))
+        .unpack(dest)
+        .with_context(|| format!("Failed to extract archive {:?}", archive))?;
+
+    let theme_path = match source_kind {
+        SourceKind::Xcursor => walkdir::WalkDir::new(dest)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir() && entry.file_name() == "cursors")
+            .min_by_key(|entry| entry.depth())
+            .and_then(|entry| entry.path().parent().map(Path::to_path_buf)),
+        SourceKind::PngFrames => {
+            let top: Vec<PathBuf> = fs::read_dir(dest)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<_>>()?;
+            match top.as_slice() {
+                [] => None,
+                // A wrapper directory holds the per-cursor frame directories
+                [only] if only.is_dir() && fs::read_dir(only)?.flatten().any(|e| e.path().is_dir()) => {
+                    Some(only.clone())
+                }
+                _ => Some(dest.to_path_buf()),
             }
         }
+    };
+
+    let theme_path = theme_path.ok_or_else(|| anyhow::anyhow!(
+        "Archive {:?} does not contain a cursor theme (expected a cursors/ directory)",
+        archive
+    ))?;
+    detail!("Using extracted theme: {:?}", theme_path);
+    Ok(theme_path)
+}
+
+/// The tool frames are scaled with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ImageBackend {
+    /// ImageMagick 7
+    Magick,
+    /// ImageMagick 6
+    Convert,
+    /// The `image` crate, when ImageMagick is not installed
+    #[default]
+    Native,
+}
+
+impl ImageBackend {
+    /// Look for ImageMagick, once per run rather than once per frame
+    fn detect() -> Self {
+        if CommandUtils::command_exists("magick") {
+            Self::Magick
+        } else if CommandUtils::command_exists("convert") {
+            Self::Convert
+        } else {
+            Self::Native
+        }
     }
+}
 
-    println!("Done! Created animated cursor theme: {:?}", output_theme.path);
-    println!("Also installed to: {:?}", user_icons_dir);
+/// `sizes` without `exclude`
+///
+/// Fails when `exclude` names a size that isn't generated, or every size.
+fn output_sizes(sizes: &[u32], exclude: &[u32]) -> Result<Vec<u32>> {
+    if let Some(size) = exclude.iter().find(|size| !sizes.contains(size)) {
+        return Err(anyhow::anyhow!(
+            "--exclude-sizes: {} is not a generated size ({:?})",
+            size,
+            sizes
+        ));
+    }
+    let sizes: Vec<u32> = sizes.iter()
+        .copied()
+        .filter(|size| !exclude.contains(size))
+        .collect();
+    if sizes.is_empty() {
+        return Err(anyhow::anyhow!("--exclude-sizes leaves no sizes to generate"));
+    }
+    Ok(sizes)
+}
 
-    Ok(())
+/// Whether a variant's frames go through the `image` crate instead of ImageMagick
+///
+/// Recoloring and shadows need the native image pipeline, and it is used for
+/// everything when ImageMagick is not installed.
+fn uses_native_pipeline(variant: &ThemeVariant, options: &BuildOptions) -> bool {
+    variant.recolor.is_some() || options.shadow.is_some() || options.image_backend == ImageBackend::Native
+}
+
+/// A cursor that could not be processed, with the reason
+type CursorFailure = (String, anyhow::Error);
+
+/// Hotspot ratios applied to each built cursor
+type Hotspots = BTreeMap<String, (f64, f64)>;
+
+/// Source hash and hotspot of each built cursor
+type BuiltCursors = BTreeMap<String, CachedCursor>;
+
+/// Move a variant's previous build aside when its cache can be reused
+///
+/// Otherwise (no cache, other build options, or `force`) the old output is
+/// deleted. Previous builds live next to the theme as `.<name>.previous`.
+fn take_previous_build(theme: &CursorTheme, parameters: &str, force: bool) -> Result<Option<PreviousBuild>> {
+    let aside = theme.path.with_file_name(format!(".{}.previous", theme.name));
+    if aside.exists() {
+        fs::remove_dir_all(&aside)?;
+    }
+    if !theme.path.exists() {
+        return Ok(None);
+    }
+
+    let cache = BuildCache::read(&theme.path)
+        .filter(|cache| !force && cache.parameters == parameters);
+    let Some(cache) = cache else {
+        fs::remove_dir_all(&theme.path)?;
+        return Ok(None);
+    };
+
+    fs::rename(&theme.path, &aside)
+        .with_context(|| format!("Failed to move the previous build aside: {:?}", theme.path))?;
+    Ok(Some(PreviousBuild { cursors_dir: aside.join("cursors"), cache }))
+}
+
+/// Hash a source cursor file, or every file of a PNG frame directory
+///
+/// A cursor built from an xcursorgen config also hashes the config and
+/// every image it names.
+fn source_hash(path: &Path, config: Option<&Path>) -> Result<String> {
+    let mut hasher = Sha256::new();
+    if path.is_dir() {
+        for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                hasher.update(entry.path().strip_prefix(path)?.to_string_lossy().as_bytes());
+                hasher.update([0]);
+                hasher.update(fs::read(entry.path())?);
+            }
+        }
+    } else {
+        hasher.update(fs::read(path)?);
+    }
+    if let Some(config) = config {
+        hasher.update(b"\0config\0");
+        hasher.update(fs::read(config)?);
+        let base = config.parent().unwrap_or(Path::new("."));
+        for line in parse_xcursorgen_config(config)? {
+            hasher.update([0]);
+            hasher.update(fs::read(base.join(&line.image))
+                .with_context(|| format!("Failed to read {:?}, named in {:?}", line.image, config))?);
+        }
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// The `--xcursorgen-config` file for a cursor, when there is one
+fn cursor_config(cursor_name: &str, options: &BuildOptions) -> Option<PathBuf> {
+    options.xcursorgen_config.as_ref()
+        .map(|dir| dir.join(format!("{}.config", cursor_name)))
+        .filter(|config| config.is_file())
+}
+
+/// Copy a cursor from every variant's previous build if its source is unchanged
+///
+/// Returns the cached entry, or `None` when any variant has to rebuild it.
+fn reuse_cached_cursor(
+    cursor_name: &str,
+    source_hash: &str,
+    variants: &[ThemeVariant],
+    preserve_times: bool,
+) -> Result<Option<CachedCursor>> {
+    let mut cached = None;
+    for variant in variants {
+        let Some(previous) = &variant.previous else { return Ok(None) };
+        match previous.cache.cursors.get(cursor_name) {
+            Some(entry) if entry.source_hash == source_hash && previous.cursors_dir.join(cursor_name).is_file() => {
+                cached = Some(entry.clone());
+            }
+            _ => return Ok(None),
+        }
+    }
+
+    for variant in variants {
+        if let Some(previous) = &variant.previous {
+            FileUtils::copy_file_with(
+                copy_fs(preserve_times),
+                previous.cursors_dir.join(cursor_name),
+                variant.theme.cursors_dir.join(cursor_name),
+            )?;
+        }
+    }
+    Ok(cached)
+}
+
+/// Source entries of every input directory by file name
+///
+/// Directories are read in order, so an entry in a later one replaces the
+/// earlier entry of the same name; each replacement is logged.
+fn merge_input_dirs(input_dirs: &[PathBuf]) -> Result<BTreeMap<OsString, PathBuf>> {
+    let mut merged = BTreeMap::new();
+    for input_dir in input_dirs {
+        let mut entries = fs::read_dir(input_dir)
+            .with_context(|| format!("Failed to read {:?}", input_dir))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+        for path in entries {
+            let name = path.file_name().unwrap_or_default().to_os_string();
+            if let Some(previous) = merged.insert(name, path.clone()) {
+                status!("  {:?} overrides {:?}", path, previous);
+            }
+        }
+    }
+    Ok(merged)
 }
 
 /// Process cursor files to create multi-size animated versions
+///
+/// Returns the source hash and hotspot ratios of each real (non-symlink)
+/// cursor that was built, the cursors reused unchanged from the previous
+/// build, and the cursors that failed. Unless `fail_fast` is set, a failing cursor
+/// is logged and skipped so the rest of the theme still builds.
 fn process_cursor_files(
-    input_cursors: &Path,
-    output_theme: &CursorTheme,
+    input_dirs: &[PathBuf],
+    variants: &[ThemeVariant],
     temp_dir: &Path,
-) -> Result<()> {
-    println!("Processing cursor files...");
+    options: &BuildOptions,
+    filter: &CursorFilter,
+) -> Result<(BuiltCursors, Vec<String>, Vec<CursorFailure>)> {
+    status!("Processing cursor files...");
+
+    let entries: Vec<PathBuf> = merge_input_dirs(input_dirs)?
+        .into_values()
+        .filter(|path| filter.allows(&path.file_name().unwrap_or_default().to_string_lossy()))
+        .collect();
+
+    let is_cursor = |path: &Path| match options.source_kind {
+        SourceKind::Xcursor => path.is_file() && !path.is_symlink(),
+        SourceKind::PngFrames => path.is_dir() && !path.is_symlink(),
+    };
+    let cursor_count = entries.iter().filter(|path| is_cursor(path)).count();
+    let progress = if options.progress {
+        MultiProgress::new()
+    } else {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    };
+    let _progress_guard = options.progress.then(|| show_progress(&progress));
+    let cursors_bar = progress.add(ProgressBar::new(cursor_count as u64));
+    cursors_bar.set_style(progress_style("cursors"));
 
-    for entry in fs::read_dir(input_cursors)? {
-        let entry = entry?;
-        let cursor_file = entry.path();
+    let mut processed = BTreeMap::new();
+    let mut reused = Vec::new();
+    let mut failures = Vec::new();
 
-        if cursor_file.is_file() && !cursor_file.is_symlink() {
-            let cursor_name = cursor_file.file_name()
+    for cursor_file in &entries {
+        let result = if is_cursor(cursor_file) {
+            let result = cursor_file.file_name()
                 .and_then(|n| n.to_str())
-                .ok_or_else(|| anyhow::anyhow!("Invalid cursor file name"))?;
-
-            println!("  Processing: {}", cursor_name);
-
-            process_single_cursor(&cursor_file, cursor_name, output_theme, temp_dir)?;
+                .ok_or_else(|| anyhow::anyhow!("Invalid cursor file name: {:?}", cursor_file))
+                .and_then(|source_name| {
+                    let cursor_name = &output_cursor_name(source_name, options.normalize_names);
+                    if processed.contains_key(cursor_name) {
+                        return Err(anyhow::anyhow!("{} and another source cursor both map to {}", source_name, cursor_name));
+                    }
+                    cursors_bar.set_message(cursor_name.to_string());
+                    let source_hash = source_hash(cursor_file, cursor_config(cursor_name, options).as_deref())
+                        .with_context(|| format!("Failed to hash {:?}", cursor_file))?;
+                    if let Some(cached) = reuse_cached_cursor(cursor_name, &source_hash, variants, options.preserve_times)? {
+                        detail!("  Unchanged: {}", cursor_name);
+                        processed.insert(cursor_name.to_string(), cached);
+                        reused.push(cursor_name.to_string());
+                        return Ok(());
+                    }
+
+                    detail!("  Processing: {}", cursor_name);
+                    let ProcessedCursor { hotspot, fallback } =
+                        process_single_cursor(cursor_file, cursor_name, variants, temp_dir, options, &progress)?;
+                    processed.insert(cursor_name.to_string(), CachedCursor { source_hash, hotspot, fallback });
+                    Ok(())
+                });
+            cursors_bar.inc(1);
+            result
         } else if cursor_file.is_symlink() {
             // Copy symlinks
-            copy_symlink(&cursor_file, &output_theme.cursors_dir)?;
+            variants.iter()
+                .try_for_each(|variant| copy_symlink(cursor_file, &variant.theme.cursors_dir, options))
+        } else {
+            Ok(())
+        };
+
+        if let Err(err) = result {
+            let name = cursor_file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            if options.fail_fast {
+                return Err(err.context(format!("Failed to process cursor {}", name)));
+            }
+            warn!("Failed to process cursor {}: {:#}", name, err);
+            failures.push((name, err));
         }
     }
+    cursors_bar.finish_and_clear();
 
-    Ok(())
+    Ok((processed, reused, failures))
+}
+
+/// Bar style shared by the cursor and frame progress bars
+fn progress_style(unit: &str) -> ProgressStyle {
+    ProgressStyle::with_template(&format!("{{bar:40}} {{pos}}/{{len}} {} {{msg}}", unit))
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+}
+
+/// A source frame and how long it is shown
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    path: PathBuf,
+    delay_ms: u32,
+}
+
+/// What processing one cursor produced
+struct ProcessedCursor {
+    /// Hotspot ratios used
+    hotspot: (f64, f64),
+    /// A tool failed and the original cursor was copied instead
+    fallback: bool,
 }
 
-/// Process a single cursor file
+/// Process a single cursor file (or PNG frame directory)
+///
+/// Frames are extracted once and shared by every variant.
 fn process_single_cursor(
     cursor_file: &Path,
     cursor_name: &str,
-    output_theme: &CursorTheme,
+    variants: &[ThemeVariant],
     temp_dir: &Path,
-) -> Result<()> {
+    options: &BuildOptions,
+    progress: &MultiProgress,
+) -> Result<ProcessedCursor> {
+    let timeout = options.command_timeout;
     let cursor_temp_dir = temp_dir.join(cursor_name);
+    let mut fallback = false;
     fs::create_dir_all(&cursor_temp_dir)?;
 
-    // Extract cursor frames using xcur2png
-    let extract_result = Command::new("xcur2png")
-        .arg(cursor_file)
-        .arg("-d")
-        .arg(&cursor_temp_dir)
-        .output();
+    if let Some(config) = cursor_config(cursor_name, options) {
+        detail!("    Using xcursorgen config {:?}", config);
+        // The config's images are used as they are, for every variant
+        for variant in variants.iter().filter(|variant| variant.recolor.is_some() || options.shadow.is_some()) {
+            options.warnings.push(Warning::new(
+                WarningKind::Unstyled,
+                Some(cursor_name),
+                format!("{}: built from {:?}, without recoloring or shadows", variant.theme.name, config),
+            ));
+        }
+        let lines = parse_xcursorgen_config(&config)?;
+        match create_cursor_from_config(&cursor_temp_dir.join("config"), cursor_name, &config, &lines, variants, options)? {
+            BuildOutcome::Failed if options.source_kind == SourceKind::PngFrames => {
+                return Err(anyhow::anyhow!("xcursorgen could not build {} from {:?}", cursor_name, config));
+            }
+            BuildOutcome::Failed => {
+                options.warnings.push(Warning::new(
+                    WarningKind::ToolFallback,
+                    Some(cursor_name),
+                    "xcursorgen failed on its config; copied the original cursor",
+                ));
+                copy_original(cursor_file, cursor_name, variants, options.preserve_times)?;
+                fallback = true;
+            }
+            BuildOutcome::Suspect => variants.iter().for_each(|variant| record_suspect(cursor_name, variant, options)),
+            BuildOutcome::Built => {}
+        }
+        let first = &lines[0];
+        let size = first.size.max(1) as f64;
+        return Ok(ProcessedCursor { hotspot: (first.xhot as f64 / size, first.yhot as f64 / size), fallback });
+    }
 
-    match extract_result {
-        Ok(output) if output.status.success() => {
-            // Count extracted frames
-            let frame_count = count_extracted_frames(&cursor_temp_dir, cursor_name)?;
-
-            if frame_count == 0 {
-                println!("    Failed to extract cursor, copying original");
-                fs::copy(cursor_file, output_theme.cursors_dir.join(cursor_name))?;
-                return Ok(());
+    if options.source_kind == SourceKind::PngFrames {
+        let (paths, dir_hotspot) = find_png_frames(cursor_file)?;
+        detail!("    Found {} PNG frames", paths.len());
+        let frames: Vec<Frame> = paths.into_iter()
+            .map(|path| Frame { path, delay_ms: options.frame_delay_ms })
+            .collect();
+
+        // A hotspot file in the frame directory counts as an override,
+        // unless --hotspots already names this cursor
+        let mut overrides = Cow::Borrowed(&options.hotspot_overrides);
+        if let Some(hotspot) = dir_hotspot {
+            if !overrides.contains_key(cursor_name) {
+                overrides.to_mut().insert(cursor_name.to_string(), hotspot);
+            }
+        }
+        let orig_size = source_size(&frames[0].path, options)?;
+        warn_if_upscaled(cursor_name, orig_size, options);
+
+        for variant in variants {
+            let working_dir = cursor_temp_dir.join("working").join(&variant.theme.name);
+            let outcome = create_multi_size_cursor(
+                &working_dir,
+                cursor_name,
+                variant,
+                &frames,
+                orig_size,
+                &overrides,
+                options,
+                progress,
+            )?;
+            match outcome {
+                // There is no original XCursor to fall back to
+                BuildOutcome::Failed => return Err(anyhow::anyhow!("xcursorgen could not build {}", cursor_name)),
+                BuildOutcome::Suspect => record_suspect(cursor_name, variant, options),
+                BuildOutcome::Built => {}
             }
+        }
+        return Ok(ProcessedCursor { hotspot: hotspot_ratio(cursor_name, orig_size, &overrides), fallback });
+    }
 
-            println!("    Found {} animation frames", frame_count);
+    // Used when the cursor is copied unchanged
+    let hotspot = get_cursor_hotspot(cursor_name);
 
-            // Create multi-size cursor
-            create_multi_size_cursor(&cursor_temp_dir, cursor_name, output_theme, frame_count)?;
+    // Extract cursor frames using xcur2png; its config goes in the scratch
+    // directory too, rather than the current directory
+    let conf = cursor_temp_dir.join(format!("{}.conf", cursor_name));
+    let extract_result = CommandUtils::run_command_output_timeout(
+        "xcur2png",
+        &[&cursor_file.to_string_lossy(), "-d", &cursor_temp_dir.to_string_lossy(), "-c", &conf.to_string_lossy()],
+        timeout,
+    );
+
+    match extract_result {
+        Ok(_) => {
+            let paths = find_extracted_frames(&cursor_temp_dir, cursor_name)?;
+
+            if paths.is_empty() {
+                detail!("    Failed to extract cursor, copying original");
+                options.warnings.push(Warning::new(
+                    WarningKind::ToolFallback,
+                    Some(cursor_name),
+                    "xcur2png extracted no frames; copied the original cursor",
+                ));
+                copy_original(cursor_file, cursor_name, variants, options.preserve_times)?;
+                return Ok(ProcessedCursor { hotspot, fallback: true });
+            }
+
+            let source = Xcursor::read(cursor_file).ok();
+            let frames = match &source {
+                // xcur2png writes one PNG per image, so a static multi-size cursor
+                // looks like an animation; build it from its largest image instead
+                Some(cursor) if paths.len() > 1 && !cursor.is_animated() => {
+                    detail!("    Static cursor, using its largest image");
+                    vec![Frame { path: largest_frame(paths), delay_ms: options.frame_delay_ms }]
+                }
+                _ => {
+                    detail!("    Found {} animation frames", paths.len());
+                    pair_frame_delays(paths, source.as_ref(), options)
+                }
+            };
+            let orig_size = source_size(&frames[0].path, options)?;
+            warn_if_upscaled(cursor_name, orig_size, options);
+
+            // Create multi-size cursor for each variant
+            for variant in variants {
+                let working_dir = cursor_temp_dir.join("working").join(&variant.theme.name);
+                let outcome = create_multi_size_cursor(
+                    &working_dir,
+                    cursor_name,
+                    variant,
+                    &frames,
+                    orig_size,
+                    &options.hotspot_overrides,
+                    options,
+                    progress,
+                )?;
+                match outcome {
+                    BuildOutcome::Failed => {
+                        options.warnings.push(Warning::new(
+                            WarningKind::ToolFallback,
+                            Some(cursor_name),
+                            "xcursorgen failed; copied the original cursor",
+                        ));
+                        FileUtils::copy_file_with(copy_fs(options.preserve_times), cursor_file, variant.theme.cursors_dir.join(cursor_name))?;
+                        fallback = true;
+                    }
+                    BuildOutcome::Suspect => record_suspect(cursor_name, variant, options),
+                    BuildOutcome::Built => {}
+                }
+            }
+            return Ok(ProcessedCursor { hotspot: hotspot_ratio(cursor_name, orig_size, &options.hotspot_overrides), fallback });
         }
-        _ => {
-            println!("    xcur2png failed, copying original cursor");
-            fs::copy(cursor_file, output_theme.cursors_dir.join(cursor_name))?;
+        Err(err) => {
+            if matches!(err.downcast_ref::<CursorError>(), Some(CursorError::CommandTimeout { .. })) {
+                detail!("    {}", err);
+            }
+            detail!("    xcur2png failed, copying original cursor");
+            options.warnings.push(Warning::new(
+                WarningKind::ToolFallback,
+                Some(cursor_name),
+                "xcur2png failed; copied the original cursor",
+            ));
+            copy_original(cursor_file, cursor_name, variants, options.preserve_times)?;
         }
     }
 
-    Ok(())
+    Ok(ProcessedCursor { hotspot, fallback: true })
 }
 
-/// Count extracted PNG frames
-fn count_extracted_frames(temp_dir: &Path, cursor_name: &str) -> Result<usize> {
-    let mut count = 0;
+/// Record an upscaling warning when some output sizes exceed the source size
+fn warn_if_upscaled(cursor_name: &str, orig_size: u32, options: &BuildOptions) {
+    let upscaled: Vec<String> = options.sizes.iter()
+        .filter(|&&size| size > orig_size)
+        .map(u32::to_string)
+        .collect();
+    if !upscaled.is_empty() {
+        options.warnings.push(Warning::new(
+            WarningKind::Upscaling,
+            Some(cursor_name),
+            format!("source is {}px; sizes {} are upscaled", orig_size, upscaled.join(", ")),
+        ));
+    }
+}
+
+/// Give each extracted frame the delay of the matching image in the source cursor
+///
+/// xcur2png writes images in file order, so frame `i` is source image `i`.
+/// A source holding the animation at several sizes yields sizes x frames
+/// images; only those of its largest nominal size are kept. When the counts
+/// differ, or an image has no delay, the default delay is used.
+fn pair_frame_delays(paths: Vec<PathBuf>, source: Option<&Xcursor>, options: &BuildOptions) -> Vec<Frame> {
+    let images = source
+        .filter(|cursor| cursor.images.len() == paths.len())
+        .map(|cursor| cursor.images.as_slice());
+    let largest = images.and_then(|images| images.iter().map(|image| image.nominal_size).max());
+
+    paths.into_iter()
+        .enumerate()
+        .filter(|&(index, _)| match (images, largest) {
+            (Some(images), Some(largest)) => images[index].nominal_size == largest,
+            _ => true,
+        })
+        .map(|(index, path)| {
+            let delay_ms = match images.map(|images| images[index].delay) {
+                Some(delay) if delay > 0 => scale_delay(delay, options.delay_scale),
+                _ => options.frame_delay_ms,
+            };
+            Frame { path, delay_ms }
+        })
+        .collect()
+}
 
+/// List a directory of numbered PNG frames
+///
+/// Frames are ordered by the number at the end of their file name. Returns
+/// the frames and the hotspot from the `hotspot` file, if there is one.
+fn find_png_frames(frames_dir: &Path) -> Result<(Vec<PathBuf>, Option<HotspotOverride>)> {
+    let mut frames = Vec::new();
+    for entry in fs::read_dir(frames_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+            frames.push(path);
+        }
+    }
+    frames.sort_by_key(|path| (frame_number(path), path.clone()));
+
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!("No PNG frames in {:?}", frames_dir));
+    }
+
+    let hotspot_path = frames_dir.join(HOTSPOT_FILE);
+    let hotspot = if hotspot_path.exists() {
+        let (x, y) = parse_hotspot(&fs::read_to_string(&hotspot_path)?)
+            .with_context(|| format!("Invalid hotspot file: {:?}", hotspot_path))?;
+        Some(HotspotOverride { x, y })
+    } else {
+        None
+    };
+
+    Ok((frames, hotspot))
+}
+
+/// Trailing number of a frame's file stem (`frame_012.png` -> 12)
+fn frame_number(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
+
+/// Parse a hotspot descriptor: `x y` (or `x,y`) in pixels
+fn parse_hotspot(content: &str) -> Result<(u32, u32)> {
+    let mut parts = content.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(x), Some(y), None) => Ok((x.parse()?, y.parse()?)),
+        _ => Err(anyhow::anyhow!("expected \"x y\", got {:?}", content.trim())),
+    }
+}
+
+/// Copy an unprocessed cursor into every variant
+fn copy_original(cursor_file: &Path, cursor_name: &str, variants: &[ThemeVariant], preserve_times: bool) -> Result<()> {
+    for variant in variants {
+        FileUtils::copy_file_with(copy_fs(preserve_times), cursor_file, variant.theme.cursors_dir.join(cursor_name))?;
+    }
+    Ok(())
+}
+
+/// Find the PNG frames xcur2png extracted for a cursor, ordered by frame index
+///
+/// Accepts `<cursor>_NNN.png` and `<cursor>-NNN.png`, optionally with a size
+/// segment before the index (`<cursor>_24_NNN.png`, `<cursor>-24x24-NNN.png`).
+fn find_extracted_frames(temp_dir: &Path, cursor_name: &str) -> Result<Vec<PathBuf>> {
+    let pattern = Regex::new(&format!(
+        r"^{}(?:[_-]\d+(?:x\d+)?)?[_-](\d+)\.png$",
+        regex::escape(cursor_name)
+    ))?;
+
+    let mut frames = Vec::new();
     for entry in fs::read_dir(temp_dir)? {
         let entry = entry?;
         let file_name = entry.file_name();
-        let file_name_str = file_name.to_string_lossy();
-
-        if file_name_str.starts_with(&format!("{}_", cursor_name)) && file_name_str.ends_with(".png") {
-            count += 1;
+        let Some(captures) = file_name.to_str().and_then(|name| pattern.captures(name)) else {
+            continue;
+        };
+        if let Ok(index) = captures[1].parse::<u32>() {
+            frames.push((index, entry.path()));
         }
     }
 
-    Ok(count)
+    frames.sort();
+    Ok(frames.into_iter().map(|(_, path)| path).collect())
+}
+
+/// The widest of a set of frames
+fn largest_frame(paths: Vec<PathBuf>) -> PathBuf {
+    let width = |path: &PathBuf| image::image_dimensions(path).map_or(0, |(width, _)| width);
+    let first = paths[0].clone();
+    paths.into_iter()
+        .rev()
+        .max_by_key(width)
+        .unwrap_or(first)
+}
+
+/// Result of generating one cursor with xcursorgen
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BuildOutcome {
+    Built,
+    /// Built, but the cursor doesn't hold one image per frame and size
+    Suspect,
+    /// xcursorgen produced no cursor
+    Failed,
+}
+
+/// Note a generated cursor whose image count didn't check out
+fn record_suspect(cursor_name: &str, variant: &ThemeVariant, options: &BuildOptions) {
+    options.warnings.push(Warning::new(
+        WarningKind::SuspectCursor,
+        Some(cursor_name),
+        format!("{}: image count differs from frames x sizes", variant.theme.name),
+    ));
 }
 
 /// Create multi-size cursor from extracted frames
+#[allow(clippy::too_many_arguments)]
 fn create_multi_size_cursor(
-    temp_dir: &Path,
+    working_dir: &Path,
     cursor_name: &str,
-    output_theme: &CursorTheme,
-    frame_count: usize,
-) -> Result<()> {
-    // Get original size from first frame
-    let first_frame = temp_dir.join(format!("{}_000.png", cursor_name));
-    let orig_size = if first_frame.exists() {
-        get_image_size(&first_frame)?
-    } else {
-        48 // Default size
-    };
+    variant: &ThemeVariant,
+    frames: &[Frame],
+    orig_size: u32,
+    hotspot_overrides: &HotspotOverrides,
+    options: &BuildOptions,
+    progress: &MultiProgress,
+) -> Result<BuildOutcome> {
+    let timeout = options.command_timeout;
+    let frames_bar = progress.add(ProgressBar::new((options.sizes.len() * frames.len()) as u64));
+    frames_bar.set_style(progress_style("frames"));
+    frames_bar.set_message(format!("{} ({})", cursor_name, variant.theme.name));
 
-    println!("    Original size: {}x{}", orig_size, orig_size);
+    detail!("    Original size: {}x{}", orig_size, orig_size);
 
-    // Get hotspot ratios for this cursor
-    let (hotspot_x_ratio, hotspot_y_ratio) = get_cursor_hotspot(cursor_name);
+    let native = uses_native_pipeline(variant, options);
 
-    let working_dir = temp_dir.join("working");
-    fs::create_dir_all(&working_dir)?;
+    fs::create_dir_all(working_dir)?;
 
     // Create config file for xcursorgen
     let config_file = working_dir.join("cursor.config");
     let mut config_content = String::new();
 
-    // Process each size
-    for &size in STANDARD_SIZES {
-        // Calculate hotspot coordinates
-        let hotspot_x = ((size as f64 * hotspot_x_ratio) as u32).max(1);
-        let hotspot_y = ((size as f64 * hotspot_y_ratio) as u32).max(1);
-
-        // Process each frame
-        for frame in 0..frame_count {
+    // Scale every (size, frame) pair in parallel; each writes its own file
+    let tasks: Vec<(u32, usize)> = options.sizes.iter()
+        .flat_map(|&size| (0..frames.len()).map(move |frame| (size, frame)))
+        .collect();
+    let config_lines = tasks.par_iter()
+        .map(|&(size, frame)| -> Result<Option<String>> {
             let frame_num = format!("{:03}", frame);
-            let src_png = temp_dir.join(format!("{}_{}.png", cursor_name, frame_num));
+            let src_png = &frames[frame].path;
 
             if !src_png.exists() {
-                println!("    Warning: Missing frame {}", frame_num);
-                continue;
+                warn!("{}: missing frame {}", cursor_name, frame_num);
+                options.warnings.push(Warning::new(
+                    WarningKind::MissingFrame,
+                    Some(cursor_name),
+                    format!("frame {} was not extracted", frame_num),
+                ));
+                return Ok(None);
             }
 
             let dst_png = working_dir.join(format!("{}_{}.png", size, frame_num));
 
-            if size == orig_size {
+            let mut shift = HotspotShift::default();
+            if native {
+                // Scale, recolor and shadow natively, since ImageMagick doesn't know our ops
+                let shadow = options.shadow.map(|p| p.scaled(size as f32 / orig_size as f32));
+                shift = native_scale_frame(src_png, &dst_png, size, variant.recolor, shadow, options.frame_depth)?;
+            } else if size == orig_size {
                 // Use original for original size
-                fs::copy(&src_png, &dst_png)?;
+                fs::copy(src_png, &dst_png)?;
             } else {
                 // Scale the image
-                println!("    Creating {}x{} version of frame {}", size, size, frame_num);
-                scale_image(&src_png, &dst_png, size)?;
+                detail!("    Creating {}x{} version of frame {}", size, size, frame_num);
+                scale_image(src_png, &dst_png, size, options.image_backend, options.frame_depth, timeout)?;
             }
 
-            // Add to config file (100ms delay per frame)
-            config_content.push_str(&format!(
-                "{} {} {} {}_{}.png 100\n",
-                size, hotspot_x, hotspot_y, size, frame_num
-            ));
-        }
+            // Calculate hotspot coordinates
+            let (hotspot_x, hotspot_y) = resolve_hotspot(cursor_name, size, orig_size, hotspot_overrides);
+
+            // A static cursor gets no delay
+            let mut line = format!(
+                "{} {} {} {}_{}.png",
+                size, hotspot_x + shift.x, hotspot_y + shift.y, size, frame_num
+            );
+            if frames.len() > 1 {
+                line.push_str(&format!(" {}", frames[frame].delay_ms));
+            }
+            frames_bar.inc(1);
+            Ok(Some(line))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Lines come back in task order (size, then frame), so the config is reproducible
+    for line in config_lines.into_iter().flatten() {
+        config_content.push_str(&line);
+        config_content.push('\n');
     }
+    frames_bar.finish_and_clear();
 
     // Write config file
     fs::write(&config_file, config_content)?;
 
     // Generate cursor using xcursorgen
     let cursor_output = working_dir.join("cursor");
-    let result = Command::new("xcursorgen")
-        .arg("cursor.config")
-        .arg("cursor")
-        .current_dir(&working_dir)
-        .output();
+    let result = CommandUtils::retry("xcursorgen", options.attempts, || {
+        CommandUtils::run_command_in_dir(working_dir, "xcursorgen", &["cursor.config", "cursor"], timeout)
+    });
 
     match result {
-        Ok(output) if output.status.success() && cursor_output.exists() => {
+        Ok(_) if cursor_output.exists() => {
             // Copy the generated cursor to the theme directory
-            fs::copy(&cursor_output, output_theme.cursors_dir.join(cursor_name))?;
-            println!("    Successfully created multi-size animated cursor");
+            fs::copy(&cursor_output, variant.theme.cursors_dir.join(cursor_name))?;
+            detail!("    Successfully created multi-size animated cursor");
 
             // Verify the cursor
-            verify_generated_cursor(&cursor_output, cursor_name)?;
+            if !options.verify || verify_generated_cursor(&cursor_output, cursor_name, frames.len(), options.sizes.len(), timeout)? {
+                Ok(BuildOutcome::Built)
+            } else {
+                Ok(BuildOutcome::Suspect)
+            }
         }
-        _ => {
-            println!("    Failed to create cursor with xcursorgen, copying original");
-            // This would need the original cursor file path, which we'd need to pass through
+        result => {
+            if let Err(err) = result {
+                detail!("    {}", err);
+            }
+            detail!("    Failed to create cursor with xcursorgen");
+            Ok(BuildOutcome::Failed)
         }
     }
+}
 
-    Ok(())
+/// Build a cursor from its own xcursorgen config and copy it to every variant
+///
+/// The lines are passed to xcursorgen unchanged except for the image paths:
+/// each image, relative to the config's directory, is copied into
+/// `working_dir` first. Recoloring and shadows are not applied.
+fn create_cursor_from_config(
+    working_dir: &Path,
+    cursor_name: &str,
+    config: &Path,
+    lines: &[ConfigLine],
+    variants: &[ThemeVariant],
+    options: &BuildOptions,
+) -> Result<BuildOutcome> {
+    if lines.is_empty() {
+        anyhow::bail!("{:?} has no image lines", config);
+    }
+    fs::create_dir_all(working_dir)?;
+
+    let base = config.parent().unwrap_or(Path::new("."));
+    let mut config_content = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let image = base.join(&line.image);
+        let local = PathBuf::from(format!("{:03}.png", index));
+        fs::copy(&image, working_dir.join(&local))
+            .with_context(|| format!("Failed to copy {:?}, named in {:?}", image, config))?;
+        let relocated = ConfigLine { image: local, ..line.clone() };
+        config_content.push_str(&format!("{}\n", relocated));
+    }
+    fs::write(working_dir.join("cursor.config"), config_content)?;
+
+    let timeout = options.command_timeout;
+    let cursor_output = working_dir.join("cursor");
+    let result = CommandUtils::retry("xcursorgen", options.attempts, || {
+        CommandUtils::run_command_in_dir(working_dir, "xcursorgen", &["cursor.config", "cursor"], timeout)
+    });
+    match result {
+        Ok(_) if cursor_output.exists() => {
+            for variant in variants {
+                fs::copy(&cursor_output, variant.theme.cursors_dir.join(cursor_name))?;
+            }
+            let sizes = lines.iter().map(|line| line.size).collect::<HashSet<_>>().len();
+            if !options.verify || verify_generated_cursor(&cursor_output, cursor_name, lines.len() / sizes, sizes, timeout)? {
+                Ok(BuildOutcome::Built)
+            } else {
+                Ok(BuildOutcome::Suspect)
+            }
+        }
+        result => {
+            if let Err(err) = result {
+                detail!("    {}", err);
+            }
+            Ok(BuildOutcome::Failed)
+        }
+    }
+}
+
+/// The size a cursor's frames are treated as: `--source-size`, or the first frame's width
+///
+/// This picks the output size that gets the unscaled frames and is the base
+/// hotspots are scaled from.
+fn source_size(first_frame: &Path, options: &BuildOptions) -> Result<u32> {
+    match options.source_size {
+        Some(size) => Ok(size),
+        None => get_image_size(first_frame, options.command_timeout),
+    }
 }
 
 /// Get image dimensions using ImageMagick identify command
-fn get_image_size(image_path: &Path) -> Result<u32> {
-    let output = Command::new("identify")
-        .arg("-format")
-        .arg("%w")
-        .arg(image_path)
-        .output()
-        .context("Failed to run identify command")?;
-
-    if output.status.success() {
-        let size_str = String::from_utf8_lossy(&output.stdout);
-        size_str.trim().parse::<u32>()
-            .context("Failed to parse image size")
-    } else {
-        Ok(48) // Default size
+fn get_image_size(image_path: &Path, timeout: Option<Duration>) -> Result<u32> {
+    if !CommandUtils::command_exists("identify") {
+        return image::image_dimensions(image_path)
+            .map(|(width, _)| width)
+            .with_context(|| format!("Failed to read image size: {:?}", image_path));
+    }
+    let image_path = image_path.to_string_lossy();
+
+    match CommandUtils::run_command_output_timeout("identify", &["-format", "%w", &image_path], timeout) {
+        Ok(size_str) => size_str.parse::<u32>()
+            .context("Failed to parse image size"),
+        Err(err) if matches!(err.downcast_ref::<CursorError>(), Some(CursorError::CommandFailed { .. })) => {
+            Ok(48) // Default size
+        }
+        Err(err) => Err(err.context("Failed to run identify command")),
     }
 }
 
-/// Scale an image using ImageMagick
-fn scale_image(src: &Path, dst: &Path, size: u32) -> Result<()> {
+/// Scale an image with the run's image backend
+///
+/// `depth` only applies to the native backend; ImageMagick writes frames its own way.
+fn scale_image(src: &Path, dst: &Path, size: u32, backend: ImageBackend, depth: FrameDepth, timeout: Option<Duration>) -> Result<()> {
+    let command = match backend {
+        ImageBackend::Magick => "magick",
+        ImageBackend::Convert => "convert",
+        ImageBackend::Native => return native_scale_frame(src, dst, size, None, None, depth).map(|_| ()),
+    };
     let size_arg = format!("{}x{}", size, size);
+    let (src, dst) = (src.to_string_lossy(), dst.to_string_lossy());
 
-    // Try magick command first, then convert
-    let result = if CommandUtils::command_exists("magick") {
-        Command::new("magick")
-            .arg(src)
-            .arg("-resize")
-            .arg(&size_arg)
-            .arg(dst)
-            .output()
+    match CommandUtils::run_command_output_timeout(command, &[&src, "-resize", &size_arg, &dst], timeout) {
+        Ok(_) => Ok(()),
+        Err(err) => match err.downcast_ref::<CursorError>() {
+            Some(CursorError::CommandFailed { error, .. }) => {
+                Err(anyhow::anyhow!("Image scaling failed: {}", error))
+            }
+            Some(CursorError::CommandTimeout { .. }) => Err(err),
+            _ => Err(anyhow::anyhow!("Failed to run image scaling command: {:#}", err)),
+        },
+    }
+}
+
+/// Scale (and optionally recolor and shadow) a frame with the `image` crate
+///
+/// Scaling works in floating point, so `depth` can dither the rounding back
+/// to 8 bits; the frame is written at `depth` once recolored and shadowed.
+/// Returns how far a shadow moved the frame within its expanded canvas.
+fn native_scale_frame(
+    src: &Path,
+    dst: &Path,
+    size: u32,
+    recolor: Option<RecolorOp>,
+    shadow: Option<ShadowParams>,
+    depth: FrameDepth,
+) -> Result<HotspotShift> {
+    let img = image::open(src)
+        .with_context(|| format!("Failed to read frame: {:?}", src))?;
+
+    let mut frame = if img.width() == size && img.height() == size {
+        img.to_rgba8()
+    } else if depth.dither {
+        let scaled = image::DynamicImage::ImageRgba32F(img.to_rgba32f())
+            .resize(size, size, image::imageops::FilterType::Lanczos3);
+        dither_to_rgba8(&scaled.to_rgba32f())
     } else {
-        Command::new("convert")
-            .arg(src)
-            .arg("-resize")
-            .arg(&size_arg)
-            .arg(dst)
-            .output()
+        img.resize(size, size, image::imageops::FilterType::Lanczos3).to_rgba8()
     };
 
-    match result {
-        Ok(output) if output.status.success() => Ok(()),
-        Ok(output) => {
-            let error = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Image scaling failed: {}", error))
-        }
-        Err(e) => Err(anyhow::anyhow!("Failed to run image scaling command: {}", e)),
+    if let Some(op) = recolor {
+        recolor_frame(&mut frame, op);
+    }
+
+    let mut shift = HotspotShift::default();
+    if let Some(params) = shadow {
+        (frame, shift) = apply_shadow(&frame, params);
     }
+
+    depth.save(&frame, dst)?;
+    Ok(shift)
 }
 
 /// Copy a symlink to the destination
-fn copy_symlink(src: &Path, dest_dir: &Path) -> Result<()> {
+///
+/// Links that don't end at a real cursor in the source theme (dangling or
+/// circular) are skipped with a warning. Links pointing outside the theme
+/// are rewritten to the cursor of the same name inside it. With
+/// `normalize_names` the link and its target are lowercased to match the
+/// renamed cursors.
+fn copy_symlink(src: &Path, dest_dir: &Path, options: &BuildOptions) -> Result<()> {
     let target = fs::read_link(src)?;
     let link_name = src.file_name()
         .ok_or_else(|| anyhow::anyhow!("Invalid symlink name"))?;
+    let source_dir = src.parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid symlink path: {:?}", src))?;
+
+    let Some(resolved) = resolve_link_in_dir(source_dir, link_name) else {
+        warn!("Skipping symlink {:?} -> {:?}: it does not lead to a cursor in the theme", link_name, target);
+        options.warnings.push(Warning::new(
+            WarningKind::SkippedSymlink,
+            Some(&link_name.to_string_lossy()),
+            format!("-> {} does not lead to a cursor in the theme", target.display()),
+        ));
+        return Ok(());
+    };
 
-    println!("  Copying symlink: {:?} -> {:?}", link_name, target);
+    // Keep in-theme chains as they are; anything else points straight at the cursor
+    let target = if target.components().count() == 1 {
+        target
+    } else {
+        PathBuf::from(resolved)
+    };
+    let (link_name, target) = if options.normalize_names {
+        (
+            OsString::from(output_cursor_name(&link_name.to_string_lossy(), true)),
+            PathBuf::from(output_cursor_name(&target.to_string_lossy(), true)),
+        )
+    } else {
+        (link_name.to_os_string(), target)
+    };
+
+    detail!("  Copying symlink: {:?} -> {:?}", link_name, target);
 
-    let dest_link = dest_dir.join(link_name);
+    let dest_link = dest_dir.join(&link_name);
     FileUtils::create_symlink(&target, &dest_link)?;
 
     Ok(())
 }
 
+/// Follow a chain of cursor symlinks within `dir` to the real file it ends at
+///
+/// Each hop is looked up by file name in `dir`. Returns `None` for dangling
+/// links and cycles.
+fn resolve_link_in_dir(dir: &Path, name: &OsStr) -> Option<OsString> {
+    let mut current = name.to_os_string();
+    let mut seen = HashSet::new();
+
+    loop {
+        let path = dir.join(&current);
+        if !path.is_symlink() {
+            return path.exists().then_some(current);
+        }
+        if !seen.insert(current.clone()) {
+            return None;
+        }
+        current = fs::read_link(&path).ok()?.file_name()?.to_os_string();
+    }
+}
+
 /// Verify the generated cursor
-fn verify_generated_cursor(cursor_path: &Path, cursor_name: &str) -> Result<()> {
-    println!("    Verifying cursor...");
+///
+/// Returns false when the cursor doesn't hold one image per frame and size.
+/// A cursor that can't be extracted for checking passes.
+fn verify_generated_cursor(
+    cursor_path: &Path,
+    cursor_name: &str,
+    frames: usize,
+    sizes: usize,
+    timeout: Option<Duration>,
+) -> Result<bool> {
+    detail!("    Verifying cursor...");
 
     let verify_dir = cursor_path.parent()
         .ok_or_else(|| anyhow::anyhow!("Invalid cursor path"))?
@@ -365,41 +1668,51 @@ fn verify_generated_cursor(cursor_path: &Path, cursor_name: &str) -> Result<()>
 
     fs::create_dir_all(&verify_dir)?;
 
-    let result = Command::new("xcur2png")
-        .arg(cursor_path)
-        .arg("-d")
-        .arg(&verify_dir)
-        .output();
+    let conf = verify_dir.join(format!("{}.conf", cursor_name));
+    let result = CommandUtils::run_command_output_timeout(
+        "xcur2png",
+        &[&cursor_path.to_string_lossy(), "-d", &verify_dir.to_string_lossy(), "-c", &conf.to_string_lossy()],
+        timeout,
+    );
 
-    match result {
-        Ok(output) if output.status.success() => {
-            let frame_count = count_extracted_frames(&verify_dir, cursor_name)?;
-            println!("    New cursor has {} frames/sizes", frame_count);
+    let verified = match result {
+        Ok(_) => {
+            // xcur2png names its output after the file it read
+            let file_name = cursor_path.file_name().unwrap_or_default().to_string_lossy();
+            let frame_count = find_extracted_frames(&verify_dir, &file_name)?.len();
+            detail!("    New cursor has {} frames/sizes", frame_count);
 
             // Show available sizes
-            show_cursor_sizes(&verify_dir)?;
+            show_cursor_sizes(&verify_dir, timeout)?;
+
+            let expected = frames * sizes;
+            if frame_count != expected {
+                detail!("    Expected {} images ({} frames x {} sizes)", expected, frames, sizes);
+            }
+            frame_count == expected
         }
         _ => {
-            println!("    Warning: Could not verify cursor");
+            detail!("    Warning: Could not verify cursor");
+            true
         }
-    }
+    };
 
     // Clean up verification directory
     if verify_dir.exists() {
         fs::remove_dir_all(&verify_dir)?;
     }
 
-    Ok(())
+    Ok(verified)
 }
 
 /// Show available cursor sizes
-fn show_cursor_sizes(verify_dir: &Path) -> Result<()> {
+fn show_cursor_sizes(verify_dir: &Path, timeout: Option<Duration>) -> Result<()> {
     let mut sizes = std::collections::HashSet::new();
 
     for entry in fs::read_dir(verify_dir)? {
         let entry = entry?;
-        if entry.path().extension().map_or(false, |ext| ext == "png") {
-            if let Ok(size) = get_image_size(&entry.path()) {
+        if entry.path().extension().is_some_and(|ext| ext == "png") {
+            if let Ok(size) = get_image_size(&entry.path(), timeout) {
                 sizes.insert(size);
             }
         }
@@ -408,58 +1721,153 @@ fn show_cursor_sizes(verify_dir: &Path) -> Result<()> {
     let mut sizes_vec: Vec<_> = sizes.into_iter().collect();
     sizes_vec.sort();
 
-    println!("    Sizes: {:?}", sizes_vec);
+    detail!("    Sizes: {:?}", sizes_vec);
 
     Ok(())
 }
 
-/// Create additional symlinks for compatibility
-fn create_additional_symlinks(cursors_dir: &Path) -> Result<()> {
-    println!("Creating additional symlinks...");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme_config::STANDARD_SIZES;
+
+    #[test]
+    fn excluded_sizes_are_dropped_but_not_all_of_them() {
+        assert_eq!(output_sizes(STANDARD_SIZES, &[]).unwrap(), STANDARD_SIZES);
+        assert_eq!(output_sizes(STANDARD_SIZES, &[72, 96]).unwrap(), [24, 32, 48, 64]);
+        assert!(output_sizes(STANDARD_SIZES, &[40]).is_err());
+        assert!(output_sizes(&[48, 64], &[64]).is_ok());
+        assert!(output_sizes(STANDARD_SIZES, STANDARD_SIZES).unwrap_err().to_string().contains("no sizes"));
+    }
 
-    let symlinks = get_cursor_symlinks();
+    #[test]
+    fn png_frames_are_listed_in_numeric_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let frames = dir.path().join("wait");
+        fs::create_dir_all(&frames).unwrap();
+        for (name, width) in [("frame_10.png", 20), ("frame_2.png", 40), ("frame_1.png", 40)] {
+            image::RgbaImage::new(width, 40).save(frames.join(name)).unwrap();
+        }
+        fs::write(frames.join(HOTSPOT_FILE), "10 30\n").unwrap();
 
-    for (target, link_name) in symlinks {
-        let target_path = cursors_dir.join(target);
-        let link_path = cursors_dir.join(link_name);
+        let (paths, hotspot) = find_png_frames(&frames).unwrap();
+        assert_eq!(paths, ["frame_1.png", "frame_2.png", "frame_10.png"].map(|name| frames.join(name)));
+        assert_eq!(hotspot, Some(HotspotOverride { x: 10, y: 30 }));
+        assert_eq!(largest_frame(paths), frames.join("frame_1.png"));
+    }
 
-        if target_path.exists() && !link_path.exists() {
-            FileUtils::create_symlink(target, &link_path)?;
-            println!("  Created symlink: {} -> {}", link_name, target);
+    fn write_tar_gz(path: &Path, files: &[(&str, &[u8])]) {
+        let encoder = flate2::write::GzEncoder::new(fs::File::create(path).unwrap(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
         }
+        builder.into_inner().unwrap().finish().unwrap();
     }
 
-    Ok(())
-}
+    #[test]
+    fn theme_archives_extract_to_the_directory_holding_cursors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let archive = dir.path().join("Koosh-1.0.tar.gz");
+        write_tar_gz(&archive, &[
+            ("Koosh-1.0/README", b"readme"),
+            ("Koosh-1.0/Koosh/index.theme", b"[Icon Theme]\n"),
+            ("Koosh-1.0/Koosh/cursors/left_ptr", b"cursor"),
+        ]);
+        assert!(is_theme_archive(&archive));
+
+        let dest = dir.path().join("extracted");
+        let theme = extract_theme_archive(&archive, &dest, SourceKind::Xcursor).unwrap();
+
+        assert_eq!(theme, dest.join("Koosh-1.0/Koosh"));
+        assert!(theme.join("cursors/left_ptr").is_file());
+    }
 
-/// Install theme to user's .icons directory
-fn install_to_user_icons(theme: &CursorTheme, user_icons_dir: &Path) -> Result<()> {
-    println!("Installing to ~/.icons...");
+    #[test]
+    fn archives_without_a_theme_layout_are_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let archive = dir.path().join("notes.tgz");
+        write_tar_gz(&archive, &[("notes/todo.txt", b"nothing here")]);
+
+        let err = extract_theme_archive(&archive, &dir.path().join("extracted"), SourceKind::Xcursor).unwrap_err();
+        assert!(err.to_string().contains("does not contain a cursor theme"), "{}", err);
+    }
 
-    FileUtils::copy_dir_recursive(&theme.cursors_dir, &user_icons_dir.join("cursors"))?;
+    #[test]
+    fn source_size_overrides_the_frame_width() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let frame = dir.path().join("frame.png");
+        image::RgbaImage::new(32, 32).save(&frame).unwrap();
 
-    let index_theme = theme.path.join("index.theme");
-    if index_theme.exists() {
-        fs::copy(&index_theme, &user_icons_dir.join("index.theme"))?;
+        assert_eq!(source_size(&frame, &BuildOptions::default()).unwrap(), 32);
+        let options = BuildOptions { source_size: Some(24), ..Default::default() };
+        assert_eq!(source_size(&frame, &options).unwrap(), 24);
     }
 
-    let cursor_theme = theme.path.join("cursor.theme");
-    if cursor_theme.exists() {
-        fs::copy(&cursor_theme, &user_icons_dir.join("cursor.theme"))?;
+    #[test]
+    fn extracted_frames_are_found_in_either_naming_style() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in [
+            "wait_010.png", "wait_002.png",
+            "wait-001.png", "wait-24x24-003.png", "wait_32_000.png",
+            "wait_extra.png", "waiting_004.png", "wait_005.txt",
+        ] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+
+        let frames = find_extracted_frames(dir.path(), "wait").unwrap();
+        let names: Vec<_> = frames.iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["wait_32_000.png", "wait-001.png", "wait_002.png", "wait-24x24-003.png", "wait_010.png"]);
+
     }
 
-    Ok(())
-}
+    #[test]
+    fn frames_take_their_delay_from_the_source() {
+        let sized = |nominal_size, delay| crate::xcursor::XcursorImage {
+            nominal_size,
+            width: 1,
+            height: 1,
+            xhot: 0,
+            yhot: 0,
+            delay,
+            pixels: vec![0],
+        };
+        let image = |delay| sized(24, delay);
+        let source = Xcursor { images: vec![image(40), image(0), image(300)] };
+        let options = BuildOptions { frame_delay_ms: 100, delay_scale: 0.5, ..Default::default() };
+        let paths: Vec<PathBuf> = ["a.png", "b.png", "c.png"].map(PathBuf::from).to_vec();
+
+        let delays: Vec<u32> = pair_frame_delays(paths.clone(), Some(&source), &options)
+            .iter()
+            .map(|frame| frame.delay_ms)
+            .collect();
+        assert_eq!(delays, [20, 100, 150]);
+
+        // Counts that don't line up fall back to the default delay
+        let frames = pair_frame_delays(paths[..2].to_vec(), Some(&source), &options);
+        assert!(frames.iter().all(|frame| frame.delay_ms == 100));
+
+        // Two frames at two sizes: only the 48px frames are animation frames
+        let source = Xcursor { images: vec![sized(24, 40), sized(24, 60), sized(48, 40), sized(48, 60)] };
+        let paths: Vec<PathBuf> = ["a.png", "b.png", "c.png", "d.png"].map(PathBuf::from).to_vec();
+        let frames = pair_frame_delays(paths, Some(&source), &options);
+        assert_eq!(frames, [
+            Frame { path: PathBuf::from("c.png"), delay_ms: 20 },
+            Frame { path: PathBuf::from("d.png"), delay_ms: 30 },
+        ]);
+    }
 
-/// Update GTK icon cache
-fn update_icon_cache(theme_name: &str) -> Result<()> {
-    if CommandUtils::command_exists("gtk-update-icon-cache") {
-        let user_theme_dir = get_icons_dir()?.join(theme_name);
-        let _ = CommandUtils::run_command(
-            "gtk-update-icon-cache",
-            &["-f", "-t", user_theme_dir.to_str().unwrap()],
-        );
-        // Ignore errors as this is optional
+    #[test]
+    fn hotspot_file_accepts_spaces_or_commas() {
+        assert_eq!(parse_hotspot("3 4").unwrap(), (3, 4));
+        assert_eq!(parse_hotspot("3,4\n").unwrap(), (3, 4));
+        assert!(parse_hotspot("3").is_err());
+        assert!(parse_hotspot("3 4 5").is_err());
     }
-    Ok(())
-}
\ No newline at end of file
+}
@@ -1,59 +1,292 @@
 use anyhow::{Context, Result};
+use log::warn;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use super::common::{install_to_icons_dirs, is_installable};
+use super::{read_source_index, resolve_theme_path, ThemeReport};
 use crate::{
-    theme_config::{create_theme_files, create_hyprcursor_manifest},
-    CursorTheme, FileUtils, CommandUtils, get_icons_dir,
+    cursor_mapping::get_cursor_hotspot,
+    hyprcursor::{
+        compile_theme, cursors_directory, set_resize_algorithm, write_shape, write_shapes_from_xcursors, SourceAsset,
+        DEFAULT_CURSORS_DIRECTORY, HLC_EXTENSION, META_FILE,
+    },
+    theme_config::{
+        check_theme_comment, check_theme_version, create_hyprcursor_manifest, cursor_sizes, inherits_value, render_comment, CommentCtx, HyprManifest,
+        ResizeAlgorithm,
+    },
+    CursorError, CursorTheme, FileUtils, CommandUtils, InstallScope, copy_fs, ensure_within, create_scratch_dir,
+    sanitize_display_name, sanitize_theme_name, update_icon_caches, detail, status,
 };
 
+/// Theme comment and manifest description used when none is given
+pub const DEFAULT_COMMENT: &str = "Koosh cursor theme with hyprcursor support for Wayland";
+
 /// Arguments for the create-hyprcursor command
 #[derive(Debug)]
 pub struct CreateHyprcursorArgs {
-    pub source_theme: String,
+    /// Source theme directory, or the name of a theme in ~/.icons
+    pub source: PathBuf,
     pub dest_theme: String,
+    /// `Name` for index.theme and cursor.theme and the manifest `name` (default: the
+    /// source's `Name`, else `dest_theme`)
+    pub display_name: Option<String>,
+    /// `Comment` for index.theme and cursor.theme, and the manifest description
+    /// (default: the source's `Comment`, else [`DEFAULT_COMMENT`])
+    pub comment: Option<String>,
+    /// Themes to inherit from (default: hicolor)
+    pub inherits: Vec<String>,
+    /// Where to install the theme
+    pub install_scope: InstallScope,
+    /// `<shape>.svg` files to embed instead of the extracted raster images
+    pub svg_dir: Option<PathBuf>,
+    pub temp_dir: Option<PathBuf>,
+    pub jobs: Option<usize>,
+    /// Give copied and installed files the modification time of their source
+    pub preserve_times: bool,
+    pub command_timeout: Option<Duration>,
+    /// Extra attempts for a hyprcursor-util run that exits with an error
+    pub retries: u32,
+    /// Leave the `koosh_extract.*` and `koosh_hyprcursor.*` scratch directories behind for debugging
+    pub keep_temp: bool,
+    /// `resize_algorithm` for every shape; shapes keep theirs (or get `bilinear`) when unset
+    pub resize_algorithm: Option<ResizeAlgorithm>,
+    /// Manifest `version` (default: [`DEFAULT_THEME_VERSION`](crate::theme_config::DEFAULT_THEME_VERSION))
+    pub theme_version: String,
 }
 
 /// Create a hyprcursor theme from an existing cursor theme
-pub fn create_hyprcursor_theme(args: CreateHyprcursorArgs) -> Result<()> {
-    println!("Creating hyprcursor theme from {}...", args.source_theme);
+pub fn create_hyprcursor_theme(mut args: CreateHyprcursorArgs) -> Result<ThemeReport> {
+    let source = resolve_source(&mut args)?;
+
+    status!("Creating hyprcursor theme from {:?}...", source.path);
+    
+    if !CommandUtils::command_exists("hyprcursor-util") {
+        return Err(CursorError::MissingDependency("hyprcursor-util".to_string()).into());
+    }
     
     // Define working directories
-    let extract_dir = PathBuf::from("koosh_extract");
-    let output_dir = PathBuf::from("koosh_hyprcursor");
+    let mut work = WorkDirs::new(&args)?;
+    
+    let comment = source_comment(&source);
     
     // Step 1: Extract the source theme
-    extract_source_theme(&args.source_theme, &extract_dir)?;
+    extract_source_theme(&source.path, &work.extract_dir, args.command_timeout, args.retries + 1)?;
     
     // Step 2: Update the manifest file
-    update_manifest(&extract_dir, &args.source_theme, &args.dest_theme)?;
+    update_manifest(&work.extract_dir, &source.theme, &source.display_name, &comment, &args.theme_version)?;
     
-    // Step 3: Create the hyprcursor theme
-    create_hyprcursor(&extract_dir, &args.source_theme, &output_dir, &args.dest_theme)?;
+    finish_theme(&args, &source, &comment, &mut work)
+}
+
+/// Create a hyprcursor theme from an XCursor theme without hyprcursor-util
+///
+/// The shapes are written from the source cursors in process. When a cursor
+/// is not a valid XCursor file this falls back to [`create_hyprcursor_theme`].
+pub fn create_hyprcursor_theme_native(mut args: CreateHyprcursorArgs) -> Result<ThemeReport> {
+    let source = resolve_source(&mut args)?;
+
+    status!("Creating hyprcursor theme from {:?}...", source.path);
+    
+    // Define working directories
+    let mut work = WorkDirs::new(&args)?;
+    let working_dir = work.extract_dir.join(format!("extracted_{}", source.theme));
+    
+    let comment = source_comment(&source);
+    
+    // Step 1: Write the shapes from the source cursors
+    status!("Step 1: Reading source cursors...");
+    if work.extract_dir.exists() {
+        fs::remove_dir_all(&work.extract_dir)?;
+    }
+    fs::create_dir_all(&working_dir)?;
+    let unparsed = write_shapes_from_xcursors(
+        &source.cursors_dir,
+        &working_dir.join(DEFAULT_CURSORS_DIRECTORY),
+    )?;
+    if !unparsed.is_empty() {
+        warn!("Not XCursor files: {}; using hyprcursor-util", unparsed.join(", "));
+        work.cleanup()?;
+        return create_hyprcursor_theme(args);
+    }
+    
+    // Step 2: Write the manifest file
+    status!("Step 2: Writing manifest file...");
+    create_hyprcursor_manifest(
+        &working_dir,
+        &args.dest_theme,
+        Some(&source.display_name),
+        &comment,
+        &args.theme_version,
+        DEFAULT_CURSORS_DIRECTORY,
+    )?;
+    
+    finish_theme(&args, &source, &comment, &mut work)
+}
+
+/// The temporary directories of one build
+///
+/// Each run gets its own `koosh_extract.<random>` and `koosh_hyprcursor.<random>`
+/// under the temp base. They are removed by [`WorkDirs::cleanup`], or when the
+/// build fails before getting there, on drop. `--keep-temp` keeps them either way.
+struct WorkDirs {
+    extract_dir: PathBuf,
+    output_dir: PathBuf,
+    keep: bool,
+}
+
+impl WorkDirs {
+    fn new(args: &CreateHyprcursorArgs) -> Result<Self> {
+        // The guards are released at once: cleanup is up to `keep`
+        let extract_dir = create_scratch_dir(args.temp_dir.as_deref(), "koosh_extract")?.keep();
+        let output_dir = match create_scratch_dir(args.temp_dir.as_deref(), "koosh_hyprcursor") {
+            Ok(dir) => dir.keep(),
+            Err(err) => {
+                let _ = fs::remove_dir_all(&extract_dir);
+                return Err(err);
+            }
+        };
+        Ok(Self { extract_dir, output_dir, keep: args.keep_temp })
+    }
+
+    /// Remove both directories, unless they are kept
+    fn cleanup(&mut self) -> Result<()> {
+        if self.keep {
+            return Ok(());
+        }
+        for dir in [&self.extract_dir, &self.output_dir] {
+            if dir.exists() {
+                fs::remove_dir_all(dir)
+                    .with_context(|| format!("Failed to remove {:?}", dir))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WorkDirs {
+    fn drop(&mut self) {
+        if let Err(err) = self.cleanup() {
+            warn!("{:#}", err);
+        }
+    }
+}
+
+/// The theme a hyprcursor theme is built from
+struct HyprcursorSource {
+    path: PathBuf,
+    /// Where the source keeps its cursors (see [`CursorTheme::find_cursors_dir`])
+    cursors_dir: PathBuf,
+    /// Directory name of `path`; hyprcursor-util names its output after it
+    theme: String,
+    /// `Name` of the new theme
+    display_name: String,
+    /// `--comment` template, or the source's own comment
+    comment: String,
+}
+
+/// Validate the theme names and find the source theme
+fn resolve_source(args: &mut CreateHyprcursorArgs) -> Result<HyprcursorSource> {
+    args.dest_theme = sanitize_theme_name(&args.dest_theme)?;
+    args.theme_version = check_theme_version(&args.theme_version)?;
+    args.comment = args.comment.as_deref().map(check_theme_comment).transpose()?;
+    let path = resolve_theme_path(&args.source.to_string_lossy())?;
+    if !path.is_dir() {
+        return Err(CursorError::ThemeNotFound(path).into());
+    }
+    let path = fs::canonicalize(&path)?;
+    let cursors_dir = CursorTheme::find_cursors_dir(&path)?;
+    let theme = path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid source theme path: {:?}", path))?
+        .to_string();
+
+    // Name and Comment that weren't given come from the source's index.theme
+    let index = read_source_index(&path);
+    let display_name = match args.display_name.as_deref() {
+        Some(name) => sanitize_display_name(name)?,
+        None => index.name.unwrap_or_else(|| args.dest_theme.clone()),
+    };
+    let comment = args.comment.clone()
+        .or(index.comment)
+        .unwrap_or_else(|| DEFAULT_COMMENT.to_string());
+    Ok(HyprcursorSource { path, cursors_dir, theme, display_name, comment })
+}
+
+/// The rendered comment template
+fn source_comment(source: &HyprcursorSource) -> String {
+    render_comment(
+        &source.comment,
+        &CommentCtx::new(&source.display_name, cursor_sizes(&source.cursors_dir)),
+    )
+}
+
+/// Compile, complete and install a working theme under `extract_dir`
+///
+/// The whole theme, with its X11 cursors and configuration files, is built
+/// under `output_dir` and then installed like any other theme, so an
+/// interrupted run never leaves a half-written installation.
+fn finish_theme(
+    args: &CreateHyprcursorArgs,
+    source: &HyprcursorSource,
+    comment: &str,
+    work: &mut WorkDirs,
+) -> Result<ThemeReport> {
+    let extract_dir = &work.extract_dir;
+    let output_dir = &work.output_dir;
+    
+    // Vector sources replace the extracted raster images
+    let theme_dir = extract_dir.join(format!("extracted_{}", source.theme));
+    if let Some(svg_dir) = &args.svg_dir {
+        apply_svg_sources(&theme_dir, svg_dir)?;
+    }
+    if let Some(algorithm) = args.resize_algorithm {
+        let updated = set_resize_algorithm(&shapes_dir(&theme_dir)?, algorithm)?;
+        detail!("Set resize_algorithm = {} for {} shapes", algorithm, updated);
+    }
     
-    // Step 4: Install the theme
-    install_hyprcursor_theme(&output_dir, &args.dest_theme)?;
+    // Step 3: Create the hyprcursor theme
+    let built = create_hyprcursor(extract_dir, &source.theme, output_dir, &args.dest_theme)?;
     
-    // Step 5: Copy X11 cursors for compatibility
-    copy_x11_cursors(&args.source_theme, &args.dest_theme)?;
+    // Steps 4-5: Add the X11 cursors and configuration files
+    copy_x11_cursors(&source.cursors_dir, &built, args.jobs, args.preserve_times)?;
+    create_hyprcursor_config(&built.path, &source.display_name, comment, &args.inherits)?;
     
-    // Step 6: Create theme configuration files
-    create_hyprcursor_config(&args.dest_theme)?;
+    // Step 6: Install the complete theme
+    status!("Step 6: Installing theme...");
+    let install_paths = install_to_icons_dirs(&built, args.install_scope, args.jobs, args.preserve_times)?;
     
     // Step 7: Update icon cache
-    update_icon_cache(&args.dest_theme)?;
+    status!("Step 7: Updating icon cache...");
+    for path in &install_paths {
+        update_icon_caches(path, args.command_timeout);
+    }
     
     // Step 8: Clean up
-    cleanup(&extract_dir, &output_dir)?;
+    if work.keep {
+        status!("Step 8: Keeping temporary files in {:?} and {:?}", work.extract_dir, work.output_dir);
+    } else {
+        status!("Step 8: Cleaning up...");
+    }
+    work.cleanup()?;
     
-    println!("Done! Created hyprcursor theme: {}", args.dest_theme);
+    let theme = CursorTheme::new(args.dest_theme.clone(), install_paths[0].clone());
+    let report = ThemeReport::collect(&theme, install_paths, Vec::new())?;
     
-    Ok(())
+    status!("Done! Created hyprcursor theme: {}", args.dest_theme);
+    status!("  {}", report.contents_summary());
+    Ok(report)
 }
 
 /// Extract the source theme using hyprcursor-util
-fn extract_source_theme(source_theme: &str, extract_dir: &Path) -> Result<()> {
-    println!("Step 1: Extracting source theme...");
+fn extract_source_theme(
+    source_path: &Path,
+    extract_dir: &Path,
+    timeout: Option<Duration>,
+    attempts: u32,
+) -> Result<()> {
+    status!("Step 1: Extracting source theme...");
     
     // Remove existing extract directory
     if extract_dir.exists() {
@@ -61,25 +294,16 @@ fn extract_source_theme(source_theme: &str, extract_dir: &Path) -> Result<()> {
     }
     fs::create_dir_all(extract_dir)?;
     
-    let source_path = get_icons_dir()?.join(source_theme);
-    
-    if !source_path.exists() {
-        return Err(anyhow::anyhow!(
-            "Source theme not found: {:?}",
-            source_path
-        ));
-    }
-    
     // Run hyprcursor-util extract
-    CommandUtils::run_command(
-        "hyprcursor-util",
-        &[
-            "--extract",
-            source_path.to_str().unwrap(),
-            "--output",
-            extract_dir.to_str().unwrap(),
-        ],
-    ).context("Failed to extract source theme with hyprcursor-util")?;
+    let args = [
+        "--extract",
+        source_path.to_str().unwrap(),
+        "--output",
+        extract_dir.to_str().unwrap(),
+    ];
+    CommandUtils::retry("hyprcursor-util", attempts, || {
+        CommandUtils::run_command_output_timeout("hyprcursor-util", &args, timeout)
+    }).context("Failed to extract source theme with hyprcursor-util")?;
     
     Ok(())
 }
@@ -88,55 +312,85 @@ fn extract_source_theme(source_theme: &str, extract_dir: &Path) -> Result<()> {
 fn update_manifest(
     extract_dir: &Path,
     source_theme: &str,
-    dest_theme: &str,
+    display_name: &str,
+    description: &str,
+    version: &str,
 ) -> Result<()> {
-    println!("Step 2: Updating manifest file...");
+    status!("Step 2: Updating manifest file...");
     
     let manifest_path = extract_dir
         .join(format!("extracted_{}", source_theme))
         .join("manifest.hl");
     
     if !manifest_path.exists() {
-        return Err(anyhow::anyhow!(
-            "Manifest file not found: {:?}",
-            manifest_path
-        ));
+        return Err(CursorError::ManifestNotFound(manifest_path).into());
     }
     
-    // Read the existing manifest
-    let manifest_content = fs::read_to_string(&manifest_path)?;
-    
-    // Update the manifest content
-    let updated_content = manifest_content
-        .lines()
-        .map(|line| {
-            if line.starts_with("name = ") {
-                format!("name = {}", dest_theme)
-            } else if line.starts_with("description = ") {
-                "description = Koosh cursor theme with hyprcursor support for Wayland".to_string()
-            } else if line.starts_with("version = ") {
-                "version = 1.0".to_string()
-            } else {
-                line.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+    // Set the theme fields, keeping every other key as-is
+    let mut manifest = HyprManifest::read(&manifest_path)?;
+    manifest.set("name", display_name);
+    manifest.set("description", description);
+    manifest.set("version", version);
+    manifest.write(&manifest_path)?;
     
-    // Write the updated manifest
-    fs::write(&manifest_path, updated_content)?;
+    Ok(())
+}
+
+/// Embed `<shape>.svg` files from `svg_dir` into the extracted theme
+///
+/// Shapes keep their hotspot from the extracted `meta.hl`, or take the
+/// built-in one when the SVG adds a new shape. Shapes without an SVG keep
+/// their raster images.
+fn apply_svg_sources(theme_dir: &Path, svg_dir: &Path) -> Result<()> {
+    let shapes_dir = shapes_dir(theme_dir)?;
+    
+    let mut embedded = 0;
+    for entry in fs::read_dir(svg_dir).with_context(|| format!("Failed to read {:?}", svg_dir))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| !ext.eq_ignore_ascii_case("svg")) {
+            continue;
+        }
+        let Some(shape) = path.file_stem().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        
+        let shape_dir = shapes_dir.join(shape);
+        ensure_within(&shapes_dir, &shape_dir)?;
+        let hotspot = read_meta_hotspot(&shape_dir.join(META_FILE))
+            .unwrap_or_else(|| get_cursor_hotspot(shape));
+        write_shape(&shape_dir, hotspot, &SourceAsset::Svg(path.clone()))?;
+        detail!("  {}: embedded {:?}", shape, path);
+        embedded += 1;
+    }
     
+    status!("Embedded {} SVG cursor(s)", embedded);
     Ok(())
 }
 
-/// Create the hyprcursor theme using hyprcursor-util
+/// The shapes directory of a working theme, from its manifest
+fn shapes_dir(theme_dir: &Path) -> Result<PathBuf> {
+    let manifest = HyprManifest::read(theme_dir.join("manifest.hl"))?;
+    Ok(theme_dir.join(cursors_directory(&manifest)))
+}
+
+/// The `hotspot_x`/`hotspot_y` ratios of a shape's `meta.hl`, if it has both
+fn read_meta_hotspot(meta_path: &Path) -> Option<(f64, f64)> {
+    let meta = HyprManifest::read(meta_path).ok()?;
+    let x = meta.get("hotspot_x")?.parse().ok()?;
+    let y = meta.get("hotspot_y")?.parse().ok()?;
+    Some((x, y))
+}
+
+/// Compile the extracted theme into a hyprcursor theme under `output_dir`
+///
+/// Shapes are packed natively; the result matches `hyprcursor-util --create`.
 fn create_hyprcursor(
     extract_dir: &Path,
     source_theme: &str,
     output_dir: &Path,
     dest_theme: &str,
-) -> Result<()> {
-    println!("Step 3: Creating hyprcursor theme...");
+) -> Result<CursorTheme> {
+    status!("Step 3: Creating hyprcursor theme {}...", dest_theme);
     
     // Remove existing output directory
     if output_dir.exists() {
@@ -144,76 +398,41 @@ fn create_hyprcursor(
     }
     fs::create_dir_all(output_dir)?;
     
+    let theme_dir = output_dir.join(format!("theme_{}", dest_theme));
     let extracted_theme_dir = extract_dir.join(format!("extracted_{}", source_theme));
+    compile_theme(&extracted_theme_dir, &theme_dir)
+        .context("Failed to create hyprcursor theme")?;
     
-    // Run hyprcursor-util create
-    CommandUtils::run_command(
-        "hyprcursor-util",
-        &[
-            "--create",
-            extracted_theme_dir.to_str().unwrap(),
-            "--output",
-            output_dir.to_str().unwrap(),
-        ],
-    ).context("Failed to create hyprcursor theme")?;
-    
-    Ok(())
-}
-
-/// Install the hyprcursor theme to user's .icons directory
-fn install_hyprcursor_theme(output_dir: &Path, dest_theme: &str) -> Result<()> {
-    println!("Step 4: Installing theme to ~/.icons/{}...", dest_theme);
-    
-    let user_icons_dir = get_icons_dir()?;
-    let user_theme_dir = user_icons_dir.join(dest_theme);
-    
-    // Remove existing installation
-    if user_theme_dir.exists() {
-        fs::remove_dir_all(&user_theme_dir)?;
-    }
-    fs::create_dir_all(&user_theme_dir)?;
-    
-    // Copy the generated theme
-    let theme_output_dir = output_dir.join(format!("theme_{}", dest_theme));
-    if theme_output_dir.exists() {
-        FileUtils::copy_dir_recursive(&theme_output_dir, &user_theme_dir)?;
-    } else {
-        return Err(anyhow::anyhow!(
-            "Generated theme directory not found: {:?}",
-            theme_output_dir
-        ));
-    }
-    
-    Ok(())
+    Ok(CursorTheme::new(dest_theme.to_string(), theme_dir))
 }
 
-/// Copy X11 cursors for compatibility
-fn copy_x11_cursors(source_theme: &str, dest_theme: &str) -> Result<()> {
-    println!("Step 5: Copying X11 cursors for compatibility...");
-    
-    let source_cursors = get_icons_dir()?.join(source_theme).join("cursors");
-    let dest_cursors = get_icons_dir()?.join(dest_theme).join("cursors");
-    
+/// Copy X11 cursors into the built theme for compatibility
+///
+/// Hyprcursor shapes of a source that is itself a hyprcursor theme are left out.
+fn copy_x11_cursors(source_cursors: &Path, theme: &CursorTheme, jobs: Option<usize>, preserve_times: bool) -> Result<()> {
+    status!("Step 4: Copying X11 cursors for compatibility...");
+    
+    let is_x11_cursor = |path: &Path| {
+        is_installable(path) && path.extension().is_none_or(|ext| ext != HLC_EXTENSION)
+    };
     if source_cursors.exists() {
-        fs::create_dir_all(&dest_cursors)?;
-        FileUtils::copy_dir_recursive(&source_cursors, &dest_cursors)?;
+        FileUtils::copy_dir_with_jobs_filtered_with(copy_fs(preserve_times), source_cursors, &theme.cursors_dir, jobs, is_x11_cursor)?;
     }
     
     Ok(())
 }
 
-/// Create theme configuration files
-fn create_hyprcursor_config(dest_theme: &str) -> Result<()> {
-    println!("Step 6: Creating theme configuration files...");
-    
-    let user_theme_dir = get_icons_dir()?.join(dest_theme);
+/// Create the built theme's configuration files
+fn create_hyprcursor_config(theme_dir: &Path, display_name: &str, comment: &str, inherits: &[String]) -> Result<()> {
+    let inherits = inherits_value(inherits);
+    status!("Step 5: Creating theme configuration files...");
     
     // Create index.theme
     let index_content = format!(
         r#"[Icon Theme]
 Name={}
-Comment=Koosh cursor theme with hyprcursor support for Wayland
-Inherits=hicolor
+Comment={}
+Inherits={}
 
 # Directory list
 Directories=cursors hyprcursors
@@ -226,51 +445,126 @@ Type=Fixed
 Context=Cursors
 Type=Fixed
 "#,
-        dest_theme
+        display_name, comment, inherits
     );
     
-    fs::write(user_theme_dir.join("index.theme"), index_content)?;
+    fs::write(theme_dir.join("index.theme"), index_content)?;
     
     // Create cursor.theme
     let cursor_content = format!(
         r#"[Icon Theme]
 Name={}
-Comment=Koosh cursor theme with hyprcursor support for Wayland
+Comment={}
 Inherits={}
 "#,
-        dest_theme, dest_theme
+        display_name, comment, inherits
     );
     
-    fs::write(user_theme_dir.join("cursor.theme"), cursor_content)?;
+    fs::write(theme_dir.join("cursor.theme"), cursor_content)?;
     
     Ok(())
 }
 
-/// Update GTK icon cache
-fn update_icon_cache(dest_theme: &str) -> Result<()> {
-    if CommandUtils::command_exists("gtk-update-icon-cache") {
-        println!("Step 7: Updating icon cache...");
-        let user_theme_dir = get_icons_dir()?.join(dest_theme);
-        let _ = CommandUtils::run_command(
-            "gtk-update-icon-cache",
-            &["-f", "-t", user_theme_dir.to_str().unwrap()],
-        );
-        // Ignore errors as this is optional
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme_config::DEFAULT_THEME_VERSION;
+
+    #[test]
+    fn svg_sources_keep_extracted_hotspots() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = dir.path().join("extracted_Test");
+        let shape = theme.join("hyprcursors/pointer");
+        fs::create_dir_all(&shape).unwrap();
+        fs::write(theme.join("manifest.hl"), "name = Test\ncursors_directory = hyprcursors\n").unwrap();
+        fs::write(shape.join(META_FILE), "hotspot_x = 0.25\nhotspot_y = 0.1\ndefine_size = 24, pointer_24.png\n").unwrap();
+        fs::write(shape.join("pointer_24.png"), b"png").unwrap();
+
+        let svgs = dir.path().join("svg");
+        fs::create_dir_all(&svgs).unwrap();
+        fs::write(svgs.join("pointer.svg"), "<svg/>").unwrap();
+        fs::write(svgs.join("wait.svg"), "<svg/>").unwrap();
+        fs::write(svgs.join("notes.txt"), "ignored").unwrap();
+
+        apply_svg_sources(&theme, &svgs).unwrap();
+
+        let pointer = fs::read_to_string(shape.join(META_FILE)).unwrap();
+        assert!(pointer.starts_with("hotspot_x = 0.250\nhotspot_y = 0.100\n"));
+        assert!(pointer.ends_with("define_size = 0, pointer.svg\n"));
+        let wait = fs::read_to_string(theme.join("hyprcursors/wait").join(META_FILE)).unwrap();
+        assert!(wait.starts_with("hotspot_x = 0.500\nhotspot_y = 0.500\n"));
+        assert!(!theme.join("hyprcursors/notes").exists());
     }
-    Ok(())
-}
 
-/// Clean up temporary directories
-fn cleanup(extract_dir: &Path, output_dir: &Path) -> Result<()> {
-    println!("Step 8: Cleaning up...");
-    
-    if extract_dir.exists() {
-        fs::remove_dir_all(extract_dir)?;
+    #[test]
+    fn work_dirs_are_removed_on_drop_unless_kept() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let args = |keep_temp| CreateHyprcursorArgs {
+            source: PathBuf::new(),
+            dest_theme: String::new(),
+            display_name: None,
+            comment: None,
+            inherits: Vec::new(),
+            install_scope: InstallScope::User,
+            svg_dir: None,
+            temp_dir: Some(dir.path().to_path_buf()),
+            jobs: None,
+            preserve_times: false,
+            command_timeout: None,
+            retries: 0,
+            keep_temp,
+            resize_algorithm: None,
+            theme_version: DEFAULT_THEME_VERSION.to_string(),
+        };
+        let kept = WorkDirs::new(&args(true)).unwrap();
+        let (extract_dir, output_dir) = (kept.extract_dir.clone(), kept.output_dir.clone());
+        fs::create_dir_all(extract_dir.join("extracted_Test")).unwrap();
+        drop(kept);
+        assert!(extract_dir.join("extracted_Test").is_dir());
+        assert!(output_dir.is_dir());
+
+        let work = WorkDirs::new(&args(false)).unwrap();
+        assert_ne!(work.extract_dir, extract_dir);
+        let (extract_dir, output_dir) = (work.extract_dir.clone(), work.output_dir.clone());
+        fs::create_dir_all(extract_dir.join("extracted_Test")).unwrap();
+        drop(work);
+        assert!(!extract_dir.exists());
+        assert!(!output_dir.exists());
     }
-    
-    if output_dir.exists() {
-        fs::remove_dir_all(output_dir)?;
+
+    #[test]
+    fn source_index_gives_the_default_name_and_comment() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("Source");
+        fs::create_dir_all(&source).unwrap();
+        let mut args = CreateHyprcursorArgs {
+            source: source.clone(),
+            dest_theme: "Dest".to_string(),
+            display_name: None,
+            comment: None,
+            inherits: Vec::new(),
+            install_scope: InstallScope::User,
+            svg_dir: None,
+            temp_dir: None,
+            jobs: None,
+            preserve_times: false,
+            command_timeout: None,
+            retries: 0,
+            keep_temp: false,
+            resize_algorithm: None,
+            theme_version: DEFAULT_THEME_VERSION.to_string(),
+        };
+
+        let resolved = resolve_source(&mut args).unwrap();
+        assert_eq!((resolved.display_name.as_str(), resolved.comment.as_str()), ("Dest", DEFAULT_COMMENT));
+
+        fs::write(source.join("index.theme"), "[Icon Theme]\nName=Source Theme\nComment=Drawn by hand\n").unwrap();
+        let resolved = resolve_source(&mut args).unwrap();
+        assert_eq!((resolved.display_name.as_str(), resolved.comment.as_str()), ("Source Theme", "Drawn by hand"));
+
+        args.display_name = Some("Mine".to_string());
+        args.comment = Some("My {name}".to_string());
+        let resolved = resolve_source(&mut args).unwrap();
+        assert_eq!((resolved.display_name.as_str(), resolved.comment.as_str()), ("Mine", "My {name}"));
     }
-    
-    Ok(())
 }
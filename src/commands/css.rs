@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::resolve_theme_path;
+use crate::cursor_mapping::get_cursor_symlinks;
+use crate::xcursor::Xcursor;
+use crate::{CursorTheme, status};
+
+/// Cursor keywords from the CSS `cursor` property
+pub const CSS_CURSOR_KEYWORDS: &[&str] = &[
+    "default", "context-menu", "help", "pointer", "progress", "wait",
+    "cell", "crosshair", "text", "vertical-text",
+    "alias", "copy", "move", "no-drop", "not-allowed", "grab", "grabbing",
+    "all-scroll", "col-resize", "row-resize",
+    "n-resize", "e-resize", "s-resize", "w-resize",
+    "ne-resize", "nw-resize", "se-resize", "sw-resize",
+    "ew-resize", "ns-resize", "nesw-resize", "nwse-resize",
+    "zoom-in", "zoom-out",
+];
+
+/// Arguments for the css command
+#[derive(Debug)]
+pub struct CssArgs {
+    pub theme: String,
+    pub out: PathBuf,
+}
+
+/// Summary of a css export
+#[derive(Debug, Serialize)]
+pub struct CssReport {
+    pub theme: String,
+    pub stylesheet: PathBuf,
+}
+
+/// Export a theme as a CSS stylesheet plus one PNG per cursor
+pub fn export_css(args: CssArgs) -> Result<CssReport> {
+    let theme_path = resolve_theme_path(&args.theme)?;
+    let theme = CursorTheme::load(&theme_path)?;
+
+    status!("Writing CSS for {:?} to {:?}...", theme.name, args.out);
+    write_css(&theme, &args.out)?;
+
+    Ok(CssReport {
+        theme: theme.name,
+        stylesheet: args.out,
+    })
+}
+
+/// Map each CSS keyword to the theme cursors that can stand in for it
+///
+/// A cursor named after the keyword comes first, then the targets that
+/// `get_cursor_symlinks` aliases to that keyword.
+pub fn css_keyword_map() -> BTreeMap<&'static str, Vec<&'static str>> {
+    let mut map: BTreeMap<&str, Vec<&str>> = CSS_CURSOR_KEYWORDS.iter()
+        .map(|&keyword| (keyword, vec![keyword]))
+        .collect();
+    for (target, link) in get_cursor_symlinks() {
+        if let Some(candidates) = map.get_mut(link) {
+            candidates.push(target);
+        }
+    }
+    map
+}
+
+/// Write a stylesheet for a theme to `out`
+///
+/// Each cursor's first frame is saved as `<keyword>.png` next to the
+/// stylesheet and referenced with its hotspot, e.g.
+/// `cursor: url(pointer.png) 9 3, pointer;`. Keywords with no matching
+/// cursor in the theme get no rule.
+pub fn write_css(theme: &CursorTheme, out: &Path) -> Result<()> {
+    let cursors_dir = &theme.cursors_dir;
+    let out_dir = out.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create output directory: {:?}", out_dir))?;
+
+    let mut css = String::new();
+    let mut rules = 0;
+    for (keyword, candidates) in css_keyword_map() {
+        let Some(cursor_path) = candidates.iter()
+            .map(|name| cursors_dir.join(name))
+            .find(|path| path.is_file())
+        else {
+            continue;
+        };
+
+        let cursor = Xcursor::read(&cursor_path)?;
+        let Some(frame) = cursor.images.first() else {
+            continue;
+        };
+
+        let png_name = format!("{}.png", keyword);
+        frame.to_rgba().save(out_dir.join(&png_name))
+            .with_context(|| format!("Failed to write {:?}", out_dir.join(&png_name)))?;
+
+        css.push_str(&format!(
+            ".cursor-{} {{\n  cursor: url({}) {} {}, {};\n}}\n\n",
+            keyword, png_name, frame.xhot, frame.yhot, keyword
+        ));
+        rules += 1;
+    }
+
+    fs::write(out, css).with_context(|| format!("Failed to write {:?}", out))?;
+    status!("Done! Wrote {} cursor rules", rules);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::resolve_theme_path;
+use crate::{CursorTheme, FileUtils, status};
+
+/// Arguments for the dedupe command
+#[derive(Debug)]
+pub struct DedupeArgs {
+    pub theme: String,
+}
+
+/// Summary of a dedupe run
+#[derive(Debug, Serialize)]
+pub struct DedupeReport {
+    pub theme: String,
+    pub path: PathBuf,
+    pub replaced: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// Replace byte-identical cursor files with symlinks to a single copy
+pub fn dedupe_theme(args: DedupeArgs) -> Result<DedupeReport> {
+    let theme_path = resolve_theme_path(&args.theme)?;
+    let theme = CursorTheme::load(&theme_path)?;
+
+    status!("Deduplicating cursors in {:?}...", theme.cursors_dir);
+
+    let groups = find_duplicate_cursors(&theme.cursors_dir)?;
+    let mut replaced = 0;
+    let mut reclaimed: u64 = 0;
+
+    for group in &groups {
+        let (canonical, duplicates) = group.split_first()
+            .ok_or_else(|| anyhow::anyhow!("Empty duplicate group"))?;
+        let canonical_name = canonical.file_name()
+            .ok_or_else(|| anyhow::anyhow!("Invalid cursor path: {:?}", canonical))?;
+
+        for duplicate in duplicates {
+            let size = fs::metadata(duplicate)?.len();
+            FileUtils::create_symlink(canonical_name, duplicate)?;
+            status!("  Linked {:?} -> {:?}", duplicate.file_name().unwrap_or_default(), canonical_name);
+            replaced += 1;
+            reclaimed += size;
+        }
+    }
+
+    status!("Done! Replaced {} duplicate cursors, reclaimed {} bytes", replaced, reclaimed);
+
+    Ok(DedupeReport {
+        theme: theme.name,
+        path: theme.path,
+        replaced,
+        reclaimed_bytes: reclaimed,
+    })
+}
+
+/// Group regular cursor files by identical content
+///
+/// Symlinks are ignored. Each group holds at least two paths, sorted by
+/// name so the first entry is a stable canonical choice.
+pub fn find_duplicate_cursors(cursors_dir: &Path) -> Result<Vec<Vec<PathBuf>>> {
+    let mut by_hash: BTreeMap<(u64, [u8; 32]), Vec<PathBuf>> = BTreeMap::new();
+
+    for entry in fs::read_dir(cursors_dir)
+        .with_context(|| format!("Failed to read cursors directory: {:?}", cursors_dir))?
+    {
+        let path = entry?.path();
+        if path.is_symlink() || !path.is_file() {
+            continue;
+        }
+
+        let data = fs::read(&path)
+            .with_context(|| format!("Failed to read cursor: {:?}", path))?;
+        let digest: [u8; 32] = Sha256::digest(&data).into();
+        by_hash.entry((data.len() as u64, digest)).or_default().push(path);
+    }
+
+    let mut groups: Vec<Vec<PathBuf>> = by_hash
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .map(|mut paths| {
+            paths.sort();
+            paths
+        })
+        .collect();
+    groups.sort();
+
+    Ok(groups)
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::check_tools::{probe_tools, ToolStatus};
+use super::list::installed_theme_paths;
+use crate::{CursorKind, CursorTheme, get_home_dir, get_icons_dir, get_system_icons_dir, status};
+
+/// Cursor-related environment variables worth showing
+const CURSOR_ENV_VARS: &[&str] = &["XCURSOR_THEME", "XCURSOR_SIZE", "XCURSOR_PATH", "HYPRCURSOR_THEME", "HYPRCURSOR_SIZE"];
+
+/// An installed theme with aliases that point nowhere
+#[derive(Debug, Serialize)]
+pub struct BrokenTheme {
+    pub name: String,
+    pub path: PathBuf,
+    /// Aliases whose target does not exist
+    pub dangling: Vec<String>,
+}
+
+/// Result of checking the environment
+#[derive(Debug, Serialize)]
+pub struct DoctorReport {
+    pub tools: Vec<ToolStatus>,
+    /// Commands that can't run, with the required tools they lack
+    pub unavailable_commands: BTreeMap<String, Vec<String>>,
+    pub icons_dir: PathBuf,
+    pub icons_dir_writable: bool,
+    /// The cursor variables that are set
+    pub env: BTreeMap<String, String>,
+    /// Cursor themes found in the icons directory
+    pub themes: usize,
+    pub broken_themes: Vec<BrokenTheme>,
+    /// Things that are clearly broken; any of them fails the command
+    pub problems: Vec<String>,
+    /// Suggestions for the problems and for anything that limits the commands
+    pub hints: Vec<String>,
+}
+
+/// Check the tools, the icons directory, the cursor variables and the installed themes
+pub fn doctor() -> Result<DoctorReport> {
+    let mut problems = Vec::new();
+    let mut hints = Vec::new();
+
+    // Tools
+    let tools = probe_tools();
+    let unavailable: BTreeMap<String, Vec<String>> = tools.unavailable_commands()
+        .into_iter()
+        .map(|(command, missing)| (command.to_string(), missing))
+        .collect();
+    status!("Tools:");
+    if unavailable.is_empty() {
+        status!("  every command's required tools are installed");
+    }
+    for (command, missing) in &unavailable {
+        status!("  {} is unavailable: missing {}", command, missing.join(", "));
+    }
+    if !unavailable.is_empty() {
+        hints.push("Install the missing tools to enable every command; `check-tools` lists what each one is for".to_string());
+    }
+
+    // Icons directory
+    let icons_dir = get_icons_dir()?;
+    let icons_dir_writable = is_writable(&icons_dir);
+    status!("Icons directory: {:?}", icons_dir);
+    if icons_dir_writable {
+        status!("  writable{}", if icons_dir.exists() { "" } else { " (will be created)" });
+    } else {
+        status!("  not writable");
+        problems.push(format!("Themes can't be installed: {:?} is not writable", icons_dir));
+        hints.push("Fix the permissions of the icons directory, or pass --icons-dir to install elsewhere".to_string());
+    }
+
+    // Environment
+    let env: BTreeMap<String, String> = CURSOR_ENV_VARS.iter()
+        .filter_map(|name| std::env::var(name).ok().map(|value| (name.to_string(), value)))
+        .collect();
+    status!("Environment:");
+    for name in CURSOR_ENV_VARS {
+        status!("  {}={}", name, env.get(*name).map_or("(unset)", String::as_str));
+    }
+    match env.get("XCURSOR_THEME") {
+        Some(theme) if find_installed_theme(&icons_dir, theme).is_none() => {
+            problems.push(format!("XCURSOR_THEME is {}, which is not installed", theme));
+            hints.push("Install the theme, or point XCURSOR_THEME at one that `list --themes` shows".to_string());
+        }
+        Some(_) => {}
+        None => hints.push("Set XCURSOR_THEME (and XCURSOR_SIZE) to choose a cursor theme; `add-links` prints a Hyprland snippet".to_string()),
+    }
+
+    // Installed themes
+    let paths = installed_theme_paths(&icons_dir)?;
+    let mut broken_themes = Vec::new();
+    for path in &paths {
+        let theme = match CursorTheme::load(path) {
+            Ok(theme) => theme,
+            Err(err) => {
+                problems.push(format!("{:?} can't be read: {:#}", path, err));
+                continue;
+            }
+        };
+        let dangling = dangling_links(&theme)?;
+        if !dangling.is_empty() {
+            problems.push(format!("{} has {} dangling aliases: {}", theme.name, dangling.len(), dangling.join(", ")));
+            broken_themes.push(BrokenTheme { name: theme.name, path: theme.path, dangling });
+        }
+    }
+    status!("Installed themes: {} ({} broken)", paths.len(), broken_themes.len());
+    if !broken_themes.is_empty() {
+        hints.push("Remove the dangling aliases, or rebuild the themes; `relink` adds aliases whose targets exist".to_string());
+    }
+
+    status!();
+    if problems.is_empty() {
+        status!("No problems found");
+    } else {
+        status!("Problems:");
+        for problem in &problems {
+            status!("  - {}", problem);
+        }
+    }
+    if !hints.is_empty() {
+        status!("Hints:");
+        for hint in &hints {
+            status!("  - {}", hint);
+        }
+    }
+
+    Ok(DoctorReport {
+        tools: tools.tools,
+        unavailable_commands: unavailable,
+        icons_dir,
+        icons_dir_writable,
+        env,
+        themes: paths.len(),
+        broken_themes,
+        problems,
+        hints,
+    })
+}
+
+/// Whether files can be created in `dir`, or in the ancestor it would be created under
+fn is_writable(dir: &Path) -> bool {
+    let Some(existing) = dir.ancestors().find(|ancestor| ancestor.exists()) else {
+        return false;
+    };
+    if !existing.is_dir() {
+        return false;
+    }
+    let probe = existing.join(format!(".koosh-doctor-{}", std::process::id()));
+    match fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// A theme by name in any directory libXcursor or this tool looks in
+fn find_installed_theme(icons_dir: &Path, name: &str) -> Option<PathBuf> {
+    theme_search_dirs(icons_dir)
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_dir())
+}
+
+/// The icons directory, `$XDG_DATA_HOME/icons`, the system icons directory,
+/// then every `XCURSOR_PATH` entry (a leading `~` is the home directory)
+fn theme_search_dirs(icons_dir: &Path) -> Vec<PathBuf> {
+    let home = get_home_dir().ok();
+    let mut dirs = vec![icons_dir.to_path_buf()];
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+    dirs.extend(data_home.map(|dir| dir.join("icons")));
+    dirs.push(get_system_icons_dir());
+
+    if let Some(xcursor_path) = std::env::var_os("XCURSOR_PATH") {
+        for dir in std::env::split_paths(&xcursor_path).filter(|dir| !dir.as_os_str().is_empty()) {
+            match (dir.strip_prefix("~"), &home) {
+                (Ok(rest), Some(home)) => dirs.push(home.join(rest)),
+                _ => dirs.push(dir),
+            }
+        }
+    }
+    dirs
+}
+
+/// Aliases in a theme whose target does not exist
+fn dangling_links(theme: &CursorTheme) -> Result<Vec<String>> {
+    if !theme.cursors_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    Ok(theme.cursors()?
+        .into_iter()
+        .filter(|entry| matches!(entry.kind, CursorKind::Symlink { .. }))
+        .filter(|entry| !theme.cursors_dir.join(&entry.name).exists())
+        .map(|entry| entry.name)
+        .collect())
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{hex, resolve_theme_path};
+use crate::{theme_config::IndexTheme, CursorError, CursorTheme, status};
+
+/// Arguments for the fingerprint command
+#[derive(Debug)]
+pub struct FingerprintArgs {
+    pub theme: String,
+}
+
+/// A theme's content hash and what went into it
+#[derive(Debug, Serialize)]
+pub struct FingerprintReport {
+    pub theme: String,
+    pub path: PathBuf,
+    pub fingerprint: String,
+    /// SHA-256 of each cursor file, by name
+    pub cursors: BTreeMap<String, String>,
+    /// Target of each symlink, by name
+    pub links: BTreeMap<String, String>,
+}
+
+/// Print a hash of a theme's cursors, links and index.theme
+pub fn fingerprint_theme(args: FingerprintArgs) -> Result<FingerprintReport> {
+    let path = resolve_theme_path(&args.theme)?;
+    if !path.is_dir() {
+        return Err(CursorError::ThemeNotFound(path).into());
+    }
+
+    let contents = ThemeContents::read(&path)?;
+    let fingerprint = contents.fingerprint();
+    status!("{}", fingerprint);
+
+    Ok(FingerprintReport {
+        theme: args.theme,
+        path,
+        fingerprint,
+        cursors: contents.cursors,
+        links: contents.links,
+    })
+}
+
+/// Hash a theme's content for change detection
+///
+/// Covers every cursor's bytes, every symlink's name and target, and the
+/// `Name`, `Inherits`, `Directories` and sizes of index.theme. The comment is
+/// left out, as it may carry a build date. Timestamps and file order don't
+/// matter.
+pub fn theme_fingerprint(path: &Path) -> Result<String> {
+    Ok(ThemeContents::read(path)?.fingerprint())
+}
+
+/// The parts of a theme that [`theme_fingerprint`] hashes
+#[derive(Debug, Default)]
+struct ThemeContents {
+    cursors: BTreeMap<String, String>,
+    links: BTreeMap<String, String>,
+    index: Option<IndexTheme>,
+}
+
+impl ThemeContents {
+    fn read(path: &Path) -> Result<Self> {
+        let mut contents = Self {
+            index: IndexTheme::read(path)?,
+            ..Default::default()
+        };
+
+        let cursors_dir = CursorTheme::load(path)?.cursors_dir;
+        if !cursors_dir.is_dir() {
+            return Ok(contents);
+        }
+        for entry in fs::read_dir(&cursors_dir)
+            .with_context(|| format!("Failed to read cursors directory: {:?}", cursors_dir))?
+        {
+            let entry_path = entry?.path();
+            let name = entry_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            if entry_path.is_symlink() {
+                let target = fs::read_link(&entry_path)?;
+                contents.links.insert(name, target.to_string_lossy().into_owned());
+            } else if entry_path.is_file() {
+                let data = fs::read(&entry_path)
+                    .with_context(|| format!("Failed to read cursor: {:?}", entry_path))?;
+                contents.cursors.insert(name, hex(&Sha256::digest(&data)));
+            }
+        }
+        Ok(contents)
+    }
+
+    fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        // Every field ends with a NUL so neighbouring values can't run together
+        let mut field = |value: &str| {
+            hasher.update(value.as_bytes());
+            hasher.update([0]);
+        };
+
+        for (name, hash) in &self.cursors {
+            field("cursor");
+            field(name);
+            field(hash);
+        }
+        for (name, target) in &self.links {
+            field("link");
+            field(name);
+            field(target);
+        }
+        if let Some(index) = &self.index {
+            field("index");
+            field(index.name.as_deref().unwrap_or_default());
+            field(&index.inherits.join(","));
+            field(&index.directories.join(","));
+            let sizes: Vec<String> = index.sizes.iter().map(u32::to_string).collect();
+            field(&sizes.join(","));
+        }
+
+        hex(&hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn fingerprint_ignores_comments_but_not_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = dir.path();
+        fs::create_dir_all(theme.join("cursors")).unwrap();
+        fs::write(theme.join("cursors/left_ptr"), b"arrow").unwrap();
+        fs::write(theme.join("cursors/text"), b"ibeam").unwrap();
+        std::os::unix::fs::symlink("left_ptr", theme.join("cursors/default")).unwrap();
+        fs::write(theme.join("index.theme"), "[Icon Theme]\nName=Test\nComment=Built today\n").unwrap();
+
+        let before = theme_fingerprint(theme).unwrap();
+        assert_eq!(before.len(), 64);
+
+        fs::write(theme.join("index.theme"), "[Icon Theme]\nName=Test\nComment=Built tomorrow\n").unwrap();
+        assert_eq!(theme_fingerprint(theme).unwrap(), before);
+
+        fs::remove_file(theme.join("cursors/default")).unwrap();
+        std::os::unix::fs::symlink("text", theme.join("cursors/default")).unwrap();
+        assert_ne!(theme_fingerprint(theme).unwrap(), before);
+    }
+
+    #[test]
+    fn fingerprint_reads_the_manifest_cursors_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = dir.path();
+        fs::create_dir_all(theme.join("shapes")).unwrap();
+        fs::write(theme.join("manifest.hl"), "name = Hypr\ncursors_directory = shapes\n").unwrap();
+        fs::write(theme.join("shapes/left_ptr.hlc"), b"arrow").unwrap();
+
+        let contents = ThemeContents::read(theme).unwrap();
+        assert_eq!(contents.cursors.keys().collect::<Vec<_>>(), ["left_ptr.hlc"]);
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::xcursor::Xcursor;
+use crate::status;
+
+/// Arguments for the inspect command
+#[derive(Debug)]
+pub struct InspectArgs {
+    pub cursor: PathBuf,
+}
+
+/// One image chunk of an XCursor file
+#[derive(Debug, Serialize)]
+pub struct ImageInfo {
+    pub nominal_size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    /// Frame delay in milliseconds
+    pub delay: u32,
+}
+
+/// Contents of an XCursor file
+#[derive(Debug, Serialize)]
+pub struct InspectReport {
+    pub path: PathBuf,
+    /// Distinct nominal sizes, ascending
+    pub sizes: Vec<u32>,
+    /// Total number of image chunks
+    pub frames: usize,
+    /// Whether any nominal size has more than one frame
+    pub animated: bool,
+    /// "animated (N frames)" or "static (1 frame)"
+    pub label: String,
+    pub images: Vec<ImageInfo>,
+}
+
+/// Print every image chunk of a cursor file
+pub fn inspect_cursor(args: InspectArgs) -> Result<InspectReport> {
+    let cursor = Xcursor::read(&args.cursor)?;
+    let sizes = cursor.nominal_sizes();
+    let label = cursor.animation_label();
+
+    status!("{:?}", args.cursor);
+    status!("  Sizes: {:?}", sizes);
+    status!("  Images: {}, {}", cursor.images.len(), label);
+    for image in &cursor.images {
+        status!(
+            "  size {:>3}: {}x{} hotspot {},{} delay {}ms",
+            image.nominal_size, image.width, image.height, image.xhot, image.yhot, image.delay
+        );
+    }
+
+    Ok(InspectReport {
+        path: args.cursor,
+        sizes,
+        frames: cursor.images.len(),
+        animated: cursor.is_animated(),
+        label,
+        images: cursor.images.iter()
+            .map(|image| ImageInfo {
+                nominal_size: image.nominal_size,
+                width: image.width,
+                height: image.height,
+                xhot: image.xhot,
+                yhot: image.yhot,
+                delay: image.delay,
+            })
+            .collect(),
+    })
+}
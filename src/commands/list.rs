@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::common::render_table;
+use super::resolve_theme_path;
+use crate::theme_config::IndexTheme;
+use crate::xcursor::Xcursor;
+use crate::{CursorKind, CursorTheme, get_icons_dir, status};
+
+/// Arguments for the list command
+#[derive(Debug)]
+pub struct ListArgs {
+    pub theme: String,
+    /// Print an aligned table instead of one line per cursor
+    pub pretty: bool,
+}
+
+/// Arguments for listing every installed theme
+#[derive(Debug)]
+pub struct ListThemesArgs {
+    pub pretty: bool,
+}
+
+/// One entry of a theme's cursors directory
+#[derive(Debug, Serialize)]
+pub struct ListEntry {
+    pub name: String,
+    /// Link target, for aliases
+    pub target: Option<PathBuf>,
+    /// Animation frames, for cursor files that parse
+    pub frames: Option<usize>,
+    pub animated: Option<bool>,
+}
+
+/// The cursors of a theme
+#[derive(Debug, Serialize)]
+pub struct ListReport {
+    pub theme: String,
+    pub path: PathBuf,
+    pub cursors: Vec<ListEntry>,
+}
+
+/// Summary of one installed theme
+#[derive(Debug, Serialize)]
+pub struct ThemeSummary {
+    pub name: String,
+    pub path: PathBuf,
+    /// Real cursor files, not counting aliases
+    pub cursors: usize,
+    pub animated: usize,
+    /// Sizes declared in index.theme, or else the sizes found in the cursors
+    pub sizes: Vec<u32>,
+    /// Whether the theme has a hyprcursor `manifest.hl`
+    pub hyprcursor: bool,
+}
+
+/// The themes installed in the icons directory
+#[derive(Debug, Serialize)]
+pub struct ThemeListReport {
+    pub icons_dir: PathBuf,
+    pub themes: Vec<ThemeSummary>,
+}
+
+/// List a theme's cursors with their aliases and animation labels
+pub fn list_cursors(args: ListArgs) -> Result<ListReport> {
+    let theme_path = resolve_theme_path(&args.theme)?;
+    let theme = CursorTheme::load(&theme_path)?;
+
+    status!("{} ({:?})", theme.name, theme.path);
+
+    let mut cursors = Vec::new();
+    let mut rows = Vec::new();
+    for entry in theme.cursors()? {
+        let item = match entry.kind {
+            CursorKind::Symlink { target } => {
+                if args.pretty {
+                    rows.push(vec![entry.name.clone(), format!("-> {}", target.display()), String::new()]);
+                } else {
+                    status!("  {} -> {}", entry.name, target.display());
+                }
+                ListEntry { name: entry.name, target: Some(target), frames: None, animated: None }
+            }
+            CursorKind::File { .. } => match Xcursor::read(theme.cursors_dir.join(&entry.name)) {
+                Ok(cursor) => {
+                    if args.pretty {
+                        let kind = if cursor.is_animated() { "animated" } else { "static" };
+                        rows.push(vec![entry.name.clone(), kind.to_string(), cursor.frame_count().to_string()]);
+                    } else {
+                        status!("  {}: {}", entry.name, cursor.animation_label());
+                    }
+                    ListEntry {
+                        name: entry.name,
+                        target: None,
+                        frames: Some(cursor.frame_count()),
+                        animated: Some(cursor.is_animated()),
+                    }
+                }
+                Err(_) => {
+                    if args.pretty {
+                        rows.push(vec![entry.name.clone(), "not an XCursor file".to_string(), String::new()]);
+                    } else {
+                        status!("  {}: not an XCursor file", entry.name);
+                    }
+                    ListEntry { name: entry.name, target: None, frames: None, animated: None }
+                }
+            },
+        };
+        cursors.push(item);
+    }
+    if args.pretty {
+        status!("{}", render_table(&["NAME", "TYPE", "FRAMES"], &rows));
+    }
+
+    Ok(ListReport {
+        theme: theme.name,
+        path: theme.path,
+        cursors,
+    })
+}
+
+/// List the themes in the icons directory with their cursor counts and sizes
+///
+/// Directories with neither a `cursors` directory nor a hyprcursor manifest
+/// are not cursor themes and are skipped. So are themes that can't be read,
+/// with a warning.
+pub fn list_themes(args: ListThemesArgs) -> Result<ThemeListReport> {
+    let icons_dir = get_icons_dir()?;
+
+    let mut themes = Vec::new();
+    for path in installed_theme_paths(&icons_dir)? {
+        match CursorTheme::load(&path).and_then(|theme| summarize_theme(&theme)) {
+            Ok(summary) => themes.push(summary),
+            Err(err) => warn!("Skipping unreadable theme {:?}: {:#}", path, err),
+        }
+    }
+
+    if args.pretty {
+        let rows: Vec<Vec<String>> = themes.iter()
+            .map(|theme| vec![
+                theme.name.clone(),
+                theme.cursors.to_string(),
+                theme.animated.to_string(),
+                join_sizes(&theme.sizes),
+                if theme.hyprcursor { "yes" } else { "no" }.to_string(),
+            ])
+            .collect();
+        status!("{}", render_table(&["NAME", "CURSORS", "ANIMATED", "SIZES", "HYPRCURSOR"], &rows));
+    } else {
+        for theme in &themes {
+            status!(
+                "{}: {} cursors, {} animated, sizes {}{}",
+                theme.name,
+                theme.cursors,
+                theme.animated,
+                join_sizes(&theme.sizes),
+                if theme.hyprcursor { ", hyprcursor" } else { "" },
+            );
+        }
+    }
+
+    Ok(ThemeListReport { icons_dir, themes })
+}
+
+/// The cursor themes in an icons directory, sorted
+///
+/// Directories with neither a `cursors` directory nor a hyprcursor manifest
+/// are skipped.
+pub(crate) fn installed_theme_paths(icons_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    if icons_dir.is_dir() {
+        for entry in fs::read_dir(icons_dir)
+            .with_context(|| format!("Failed to read icons directory: {:?}", icons_dir))?
+        {
+            let path = entry?.path();
+            if path.join("cursors").is_dir() || path.join("manifest.hl").is_file() {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Count a theme's cursors and collect its sizes
+fn summarize_theme(theme: &CursorTheme) -> Result<ThemeSummary> {
+    let mut cursors = 0;
+    let mut animated = 0;
+    let mut found_sizes = Vec::new();
+    if theme.cursors_dir.is_dir() {
+        for entry in theme.cursors()? {
+            if !matches!(entry.kind, CursorKind::File { .. }) {
+                continue;
+            }
+            cursors += 1;
+            if let Ok(cursor) = Xcursor::read(theme.cursors_dir.join(&entry.name)) {
+                if cursor.is_animated() {
+                    animated += 1;
+                }
+                found_sizes.extend(cursor.nominal_sizes());
+            }
+        }
+    }
+
+    let mut sizes = IndexTheme::read(&theme.path)?
+        .map(|index| index.sizes)
+        .filter(|sizes| !sizes.is_empty())
+        .unwrap_or(found_sizes);
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    Ok(ThemeSummary {
+        name: theme.name.clone(),
+        path: theme.path.clone(),
+        cursors,
+        animated,
+        sizes,
+        hyprcursor: theme.path.join("manifest.hl").is_file(),
+    })
+}
+
+fn join_sizes(sizes: &[u32]) -> String {
+    if sizes.is_empty() {
+        return "-".to_string();
+    }
+    sizes.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+}
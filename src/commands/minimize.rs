@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use super::resolve_theme_path;
+use crate::theme_config::{create_theme_files, read_inherits, IndexTheme};
+use crate::{symlink_graph, theme_disk_usage, CursorTheme, FileUtils, copy_fs, sanitize_theme_name, status};
+
+/// Cursors a theme can't do without: the pointer, text, link and busy shapes
+pub const MINIMAL_CURSORS: &[&str] = &["left_ptr", "text", "pointer", "wait", "watch"];
+
+/// Arguments for the minimize command
+#[derive(Debug)]
+pub struct MinimizeArgs {
+    /// Theme directory, or the name of a theme in ~/.icons
+    pub source: String,
+    /// Directory to create the minimal theme at; its name is the theme name
+    pub dest: PathBuf,
+    /// Cursors to keep, by real name or alias (default: [`MINIMAL_CURSORS`])
+    pub keep: Vec<String>,
+    /// Give copied cursors the modification time of their source
+    pub preserve_times: bool,
+}
+
+/// Summary of a minimize run
+#[derive(Debug, Serialize)]
+pub struct MinimizeReport {
+    pub theme: String,
+    pub path: PathBuf,
+    pub source: PathBuf,
+    /// Real cursor files copied
+    pub cursors: Vec<String>,
+    /// Aliases recreated, each pointing at its real cursor
+    pub links: Vec<String>,
+    /// Cursors to keep that the source doesn't have
+    pub missing: Vec<String>,
+    pub source_bytes: u64,
+    pub bytes: u64,
+}
+
+/// Copy a theme with only the kept cursors and the aliases that lead to them
+///
+/// A kept name that is an alias in the source keeps the cursor it resolves
+/// to. Every source alias of a kept cursor is recreated as a direct link, and
+/// all other cursors and aliases are left out.
+pub fn minimize_theme(args: MinimizeArgs) -> Result<MinimizeReport> {
+    let source_path = resolve_theme_path(&args.source)?;
+    let source = CursorTheme::load(&source_path)?;
+    let name = args.dest.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow::anyhow!("Invalid destination: {:?}", args.dest))?;
+    let name = sanitize_theme_name(&name)?;
+    if args.dest.exists() {
+        anyhow::bail!("{:?} already exists; remove it or choose another destination", args.dest);
+    }
+
+    let keep = if args.keep.is_empty() {
+        MINIMAL_CURSORS.iter().map(|name| name.to_string()).collect()
+    } else {
+        args.keep
+    };
+
+    status!("Minimizing {:?} to {:?}...", source.path, args.dest);
+
+    let graph = symlink_graph(&source.cursors_dir)?;
+    let mut kept: BTreeMap<&str, &[String]> = BTreeMap::new();
+    let mut missing = Vec::new();
+    for wanted in &keep {
+        let found = graph.iter()
+            .find(|(cursor, aliases)| *cursor == wanted || aliases.contains(wanted));
+        match found {
+            Some((cursor, aliases)) => {
+                kept.insert(cursor, aliases);
+            }
+            None => {
+                warn!("{} is not in {:?}", wanted, source.path);
+                missing.push(wanted.clone());
+            }
+        }
+    }
+    if kept.is_empty() {
+        anyhow::bail!("None of the cursors to keep are in {:?}", source.path);
+    }
+
+    let theme = CursorTheme::new(name.clone(), args.dest.clone());
+    theme.create_directories()?;
+
+    let mut links = Vec::new();
+    for (cursor, aliases) in &kept {
+        FileUtils::copy_file_with(copy_fs(args.preserve_times), source.cursors_dir.join(cursor), theme.cursors_dir.join(cursor))?;
+        for alias in aliases.iter() {
+            FileUtils::create_symlink(cursor, theme.cursors_dir.join(alias))?;
+            links.push(alias.clone());
+        }
+        status!("  Kept {} ({} aliases)", cursor, aliases.len());
+    }
+    links.sort();
+
+    let index = IndexTheme::read(&source.path)?.unwrap_or_default();
+    create_theme_files(
+        &theme.path,
+        &name,
+        index.name.as_deref(),
+        index.comment.as_deref().unwrap_or_default(),
+        (!index.sizes.is_empty()).then_some(index.sizes.as_slice()),
+        &read_inherits(&source.path)?,
+    )
+    .with_context(|| format!("Failed to write the theme files of {:?}", theme.path))?;
+
+    let source_bytes = theme_disk_usage(&source.path)?;
+    let bytes = theme_disk_usage(&theme.path)?;
+    status!(
+        "Done! Kept {} cursors and {} aliases: {} bytes, down from {} ({}% smaller)",
+        kept.len(),
+        links.len(),
+        bytes,
+        source_bytes,
+        reduction_percent(source_bytes, bytes),
+    );
+
+    Ok(MinimizeReport {
+        theme: name,
+        path: theme.path,
+        source: source.path,
+        cursors: kept.keys().map(|cursor| cursor.to_string()).collect(),
+        links,
+        missing,
+        source_bytes,
+        bytes,
+    })
+}
+
+/// How much smaller `after` is than `before`, in whole percent
+fn reduction_percent(before: u64, after: u64) -> u64 {
+    if before == 0 {
+        return 0;
+    }
+    before.saturating_sub(after) * 100 / before
+}
@@ -1,4 +1,271 @@
 pub mod add_links;
+pub mod check_tools;
+pub mod clean;
+pub mod common;
 pub mod create_animated;
 pub mod create_hyprcursor;
+pub mod css;
+pub mod dedupe;
+pub mod doctor;
+pub mod fingerprint;
+pub mod inspect;
+pub mod list;
+pub mod minimize;
+pub mod relink;
 pub mod rename_cursors;
+pub mod retheme;
+pub mod validate;
+
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{
+    theme_config::{check_theme_comment, IndexTheme}, CursorError, CursorKind, CursorTheme, get_icons_dir, sanitize_display_name, sanitize_theme_name, status,
+    theme_disk_usage,
+};
+
+/// What a [`Warning`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// An alias whose target cursor doesn't exist
+    SkippedAlias,
+    /// A source symlink that doesn't lead to a cursor
+    SkippedSymlink,
+    /// An animation frame that was not extracted
+    MissingFrame,
+    /// Frames scaled up beyond the source size
+    Upscaling,
+    /// An external tool failed and a simpler path was used
+    ToolFallback,
+    /// A size declared for the theme that some cursors lack
+    MissingSize,
+    /// A generated cursor with fewer or more images than its source frames and sizes
+    SuspectCursor,
+    /// A cursor built from its xcursorgen config, without recoloring or shadows
+    Unstyled,
+}
+
+impl WarningKind {
+    /// Heading used when warnings are printed grouped by kind
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::SkippedAlias => "skipped aliases",
+            Self::SkippedSymlink => "skipped symlinks",
+            Self::MissingFrame => "missing frames",
+            Self::Upscaling => "upscaled cursors",
+            Self::ToolFallback => "tool fallbacks",
+            Self::MissingSize => "missing sizes",
+            Self::SuspectCursor => "suspect cursors",
+            Self::Unstyled => "unstyled cursors",
+        }
+    }
+}
+
+/// Something that went wrong without failing the command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// The cursor concerned, if the warning is about one
+    pub cursor: Option<String>,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, cursor: Option<&str>, message: impl Into<String>) -> Self {
+        Self { kind, cursor: cursor.map(str::to_string), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.cursor {
+            Some(cursor) => write!(f, "{}: {}", cursor, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Warnings collected while a command runs, from any thread
+#[derive(Debug, Default)]
+pub struct Warnings(Mutex<Vec<Warning>>);
+
+impl Warnings {
+    /// Record a warning; one identical to an earlier warning is dropped
+    pub fn push(&self, warning: Warning) {
+        let mut warnings = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<Warning> {
+        self.0.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Print warnings at the end of a run, grouped by kind
+pub fn print_warnings<'a>(warnings: impl IntoIterator<Item = &'a Warning>) {
+    let mut by_kind: BTreeMap<WarningKind, Vec<&Warning>> = BTreeMap::new();
+    for warning in warnings {
+        by_kind.entry(warning.kind).or_default().push(warning);
+    }
+    if by_kind.is_empty() {
+        return;
+    }
+
+    status!("Warnings:");
+    for (kind, warnings) in by_kind {
+        status!("  {} ({}):", kind.label(), warnings.len());
+        for warning in warnings {
+            status!("    {}", warning);
+        }
+    }
+}
+
+/// Summary of a built theme, printed as JSON with `--json`
+#[derive(Debug, Serialize)]
+pub struct ThemeReport {
+    pub theme: String,
+    pub output_path: PathBuf,
+    /// First location the theme was installed to
+    pub install_path: PathBuf,
+    /// Every location the theme was installed to
+    pub install_paths: Vec<PathBuf>,
+    /// Real cursor files in the theme
+    pub cursors: usize,
+    /// Alias symlinks in the theme
+    pub links: usize,
+    /// Size of the theme's files, not counting symlinks
+    pub bytes: u64,
+    pub warnings: Vec<Warning>,
+}
+
+impl ThemeReport {
+    /// Count the cursors and links of a finished theme
+    pub fn collect(theme: &CursorTheme, install_paths: Vec<PathBuf>, warnings: Vec<Warning>) -> Result<Self> {
+        let entries = theme.cursors()?;
+        let links = entries.iter()
+            .filter(|entry| matches!(entry.kind, CursorKind::Symlink { .. }))
+            .count();
+
+        Ok(Self {
+            theme: theme.name.clone(),
+            output_path: theme.path.clone(),
+            install_path: install_paths.first().cloned().unwrap_or_else(|| theme.path.clone()),
+            install_paths,
+            cursors: entries.len() - links,
+            links,
+            bytes: theme_disk_usage(&theme.path)?,
+            warnings,
+        })
+    }
+
+    /// One-line summary of the theme's contents, e.g. "120 cursors, 40 aliases, 5242880 bytes"
+    pub fn contents_summary(&self) -> String {
+        format!("{} cursors, {} aliases, {} bytes", self.cursors, self.links, self.bytes)
+    }
+}
+
+/// Which source cursors a command acts on (`--only` / `--exclude`)
+///
+/// Names are matched against source file names, before any renaming.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CursorFilter {
+    #[default]
+    All,
+    Only(Vec<String>),
+    Exclude(Vec<String>),
+}
+
+impl CursorFilter {
+    /// Build a filter from `--only` and `--exclude` lists; giving both is an error
+    pub fn new(only: Vec<String>, exclude: Vec<String>) -> Result<Self> {
+        match (only.is_empty(), exclude.is_empty()) {
+            (true, true) => Ok(Self::All),
+            (false, true) => Ok(Self::Only(only)),
+            (true, false) => Ok(Self::Exclude(exclude)),
+            (false, false) => Err(anyhow::anyhow!("--only and --exclude are mutually exclusive")),
+        }
+    }
+
+    /// Whether the cursor with this source file name should be processed
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(names) => names.iter().any(|n| n == name),
+            Self::Exclude(names) => !names.iter().any(|n| n == name),
+        }
+    }
+}
+
+/// Resolve a theme argument to a path: an existing directory, or a theme name under ~/.icons
+///
+/// An argument that looks like a path, with a separator or a leading `.`, is
+/// never taken as a name: if it isn't a directory it is `CursorError::ThemeNotFound`.
+pub(crate) fn resolve_theme_path(theme: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(theme);
+    if path.is_dir() {
+        return Ok(path);
+    }
+    if theme.starts_with('.') || theme.contains(std::path::is_separator) {
+        return Err(CursorError::ThemeNotFound(path).into());
+    }
+    Ok(get_icons_dir()?.join(sanitize_theme_name(theme)?))
+}
+
+/// The `Name` and `Comment` a build takes from a source theme's index.theme
+///
+/// An index that can't be read is ignored with a warning. So is a `Name`
+/// that isn't a valid display name, while one that only repeats the theme's
+/// directory name (say `Koosh-X11`) is dropped quietly: it names the source,
+/// not the theme being built. A kept `Name` is sanitized, and a `Comment`
+/// that fails [`check_theme_comment`] is ignored with a warning.
+pub(crate) fn read_source_index(theme_path: &Path) -> IndexTheme {
+    let mut index = match IndexTheme::read(theme_path) {
+        Ok(index) => index.unwrap_or_default(),
+        Err(err) => {
+            warn!("Ignoring the index.theme of {:?}: {:#}", theme_path, err);
+            return IndexTheme::default();
+        }
+    };
+    let dir_name = theme_path.file_name().map(|name| name.to_string_lossy());
+    index.name = index.name
+        .filter(|name| Some(name.trim()) != dir_name.as_deref())
+        .and_then(|name| match sanitize_display_name(&name) {
+            Ok(name) => Some(name),
+            Err(err) => {
+                warn!("Ignoring the Name of {:?}: {:#}", theme_path, err);
+                None
+            }
+        });
+    index.comment = index.comment
+        .and_then(|comment| match check_theme_comment(&comment) {
+            Ok(comment) => Some(comment),
+            Err(err) => {
+                warn!("Ignoring the Comment of {:?}: {:#}", theme_path, err);
+                None
+            }
+        });
+    index
+}
+
+/// Lowercase hex of a digest
+pub(crate) fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Describe aliases that were not linked because their target is missing
+pub(crate) fn skipped_alias_warnings(skipped: &[(&str, &str)]) -> Vec<Warning> {
+    skipped.iter()
+        .map(|(target, link)| Warning::new(
+            WarningKind::SkippedAlias,
+            Some(link),
+            format!("target {} does not exist", target),
+        ))
+        .collect()
+}
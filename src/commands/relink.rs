@@ -0,0 +1,54 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::common::create_alias_links;
+use super::{print_warnings, resolve_theme_path, skipped_alias_warnings, Warning};
+use crate::{CursorTheme, LinkMode, status};
+
+/// Arguments for the relink command
+#[derive(Debug)]
+pub struct RelinkArgs {
+    pub theme: String,
+}
+
+/// Summary of a relink run
+#[derive(Debug, Serialize)]
+pub struct RelinkReport {
+    pub theme: String,
+    pub path: PathBuf,
+    /// Aliases that were missing and have been created
+    pub created: Vec<String>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Create the standard aliases an installed theme is missing, without rebuilding cursors
+///
+/// Only adds: existing cursors and links are left alone, and aliases whose
+/// target cursor isn't in the theme are skipped.
+pub fn relink_theme(args: RelinkArgs) -> Result<RelinkReport> {
+    let theme_path = resolve_theme_path(&args.theme)?;
+    let theme = CursorTheme::load(&theme_path)?;
+
+    status!("Relinking {:?}...", theme.cursors_dir);
+
+    let links = create_alias_links(&theme.cursors_dir, false, LinkMode::Symlink)?;
+    for link in &links.created {
+        status!("  Created {}", link);
+    }
+
+    status!("Done! Created {} missing aliases", links.created.len());
+    if !links.skipped.is_empty() {
+        status!("{} aliases skipped due to missing targets", links.skipped.len());
+    }
+
+    let warnings = skipped_alias_warnings(&links.skipped);
+    print_warnings(&warnings);
+
+    Ok(RelinkReport {
+        theme: theme.name,
+        path: theme.path,
+        created: links.created.iter().map(|link| link.to_string()).collect(),
+        warnings,
+    })
+}
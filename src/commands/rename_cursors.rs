@@ -1,33 +1,100 @@
 use anyhow::{Context, Result};
+use log::warn;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
 
+use super::common::{create_alias_links, install_to_icons_dirs};
+use super::{print_warnings, skipped_alias_warnings, CursorFilter, ThemeReport};
 use crate::{
-    cursor_mapping::{get_windows_to_x11_mapping, get_cursor_symlinks},
-    theme_config::create_theme_files,
-    CursorTheme, FileUtils, CommandUtils, get_icons_dir,
+    cursor_mapping::{get_cursor_hotspot, get_windows_to_x11_mapping},
+    theme_config::{check_theme_comment, create_theme_files},
+    windows::parse_inf_scheme,
+    xcursor::{Xcursor, XcursorImage},
+    CursorError, CursorTheme, FileUtils, InstallScope, LinkMode, copy_fs, ensure_within, get_theme_dir, sanitize_display_name,
+    sanitize_theme_name, detail, status, update_icon_caches,
 };
 
+/// Theme comment used when none is given
+pub const DEFAULT_COMMENT: &str = "Koosh cursor theme";
+
+/// PNG file signature
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// What the Windows-named source files contain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceFormat {
+    /// XCursor files, copied verbatim
+    #[default]
+    Xcursor,
+    /// PNG images (`Normal.png` or `Normal`), each wrapped into a static
+    /// single-size cursor at the image's own size
+    Png,
+}
+
 /// Arguments for the rename-cursors command
 #[derive(Debug)]
 pub struct RenameCursorsArgs {
-    pub input_dir: PathBuf,
+    /// Directories of source cursors, merged in order: a later directory's
+    /// source for a cursor replaces an earlier one's
+    pub input_dirs: Vec<PathBuf>,
     pub output_theme: String,
+    /// `Name` written to index.theme and cursor.theme (default: the theme name)
+    pub display_name: Option<String>,
+    /// Exact path to build the theme at (default: `<theme_dir>/<output_theme>`)
+    pub output: Option<PathBuf>,
+    /// Directory the theme is built in (default: the current directory)
+    pub theme_dir: Option<PathBuf>,
+    /// Recreate alias symlinks that already exist
+    pub relink: bool,
+    /// Carry over the source's symlinks (renamed like the cursors) instead of
+    /// adding the standard aliases
+    pub preserve_source_links: bool,
+    /// Write aliases as symlinks or as copies of their target
+    pub link_mode: LinkMode,
+    /// Source (Windows-named) cursors to rename
+    pub filter: CursorFilter,
+    pub source_format: SourceFormat,
+    /// How deep to look for sources below each input directory (default: unlimited)
+    pub max_depth: Option<usize>,
+    /// A cursor pack's `install.inf` naming the role of each source file,
+    /// used instead of the built-in Windows role names
+    pub inf: Option<PathBuf>,
+    /// `Comment` written to index.theme and cursor.theme
+    pub comment: String,
+    /// Themes to inherit from (default: hicolor)
+    pub inherits: Vec<String>,
+    /// Where to install the built theme
+    pub install_scope: InstallScope,
+    pub jobs: Option<usize>,
+    /// Give copied and installed files the modification time of their source
+    pub preserve_times: bool,
+    pub command_timeout: Option<Duration>,
 }
 
 /// Rename cursor files from Windows names to X11 names
-pub fn rename_cursors(args: RenameCursorsArgs) -> Result<()> {
-    println!("Renaming cursor files from Windows to X11 format...");
-    println!("Input directory: {:?}", args.input_dir);
-    println!("Output theme: {}", args.output_theme);
-    
-    // Validate input directory
-    if !args.input_dir.exists() {
-        return Err(anyhow::anyhow!("Input directory does not exist: {:?}", args.input_dir));
+pub fn rename_cursors(mut args: RenameCursorsArgs) -> Result<ThemeReport> {
+    args.output_theme = sanitize_theme_name(&args.output_theme)?;
+    args.display_name = args.display_name.as_deref().map(sanitize_display_name).transpose()?;
+    args.comment = check_theme_comment(&args.comment)?;
+    status!("Renaming cursor files from Windows to X11 format...");
+    for input_dir in &args.input_dirs {
+        status!("Input directory: {:?}", input_dir);
     }
+    status!("Output theme: {}", args.output_theme);
     
-    let script_dir = std::env::current_dir()?;
-    let output_path = script_dir.join(&args.output_theme);
+    // Validate input directories
+    if let Some(missing) = args.input_dirs.iter().find(|dir| !dir.exists()) {
+        return Err(CursorError::ThemeNotFound(missing.clone()).into());
+    }
+    
+    let output_path = match &args.output {
+        Some(output) => output.clone(),
+        None => get_theme_dir(args.theme_dir.as_deref())?.join(&args.output_theme),
+    };
     let theme = CursorTheme::new(args.output_theme.clone(), output_path);
     
     // Create output directory
@@ -37,139 +104,304 @@ pub fn rename_cursors(args: RenameCursorsArgs) -> Result<()> {
     theme.create_directories()?;
     
     // Process cursor files
-    process_cursor_files(&args.input_dir, &theme)?;
+    let mapping = source_mapping(args.inf.as_deref())?;
+    let sources = collect_sources(&args.input_dirs, &mapping, &args.filter, args.max_depth)?;
+    let preserve_links = args.preserve_source_links.then_some(args.link_mode);
+    process_cursor_files(&sources, &theme, &mapping, args.source_format, preserve_links, args.preserve_times)?;
+    
+    // Create symlinks, unless the source's own are all that's wanted
+    let skipped = if args.preserve_source_links {
+        Vec::new()
+    } else {
+        create_alias_links(&theme.cursors_dir, args.relink, args.link_mode)?.skipped
+    };
     
-    // Create symlinks
-    create_compatibility_symlinks(&theme.cursors_dir)?;
+    // Declare the sizes the copied cursors actually hold
+    let sizes = copied_cursor_sizes(&theme.cursors_dir, sources.keys().copied());
+    if !sizes.is_empty() {
+        let size_list: Vec<String> = sizes.iter().map(u32::to_string).collect();
+        status!("Declaring sizes found in the cursors: {}", size_list.join(", "));
+    }
     
     // Create theme files
     create_theme_files(
         &theme.path,
         &args.output_theme,
-        "Koosh cursor theme",
-        None,
+        args.display_name.as_deref(),
+        &args.comment,
+        (!sizes.is_empty()).then_some(sizes.as_slice()),
+        &args.inherits,
     )?;
     
-    // Install to user's .icons directory
-    install_to_user_icons(&theme)?;
+    // Install to the icons directories
+    let install_paths = install_to_icons_dirs(&theme, args.install_scope, args.jobs, args.preserve_times)?;
     
     // Set permissions
     FileUtils::set_permissions_recursive(&theme.path, 0o755)?;
     
     // Update icon cache
-    update_icon_cache(&theme.name)?;
+    for path in &install_paths {
+        update_icon_caches(path, args.command_timeout);
+    }
     
-    println!("Done! Created X11 cursor theme: {}", args.output_theme);
-    println!("Listing files in {:?}:", theme.cursors_dir);
+    status!("Done! Created X11 cursor theme: {}", args.output_theme);
+    if !skipped.is_empty() {
+        status!("{} aliases skipped due to missing targets", skipped.len());
+    }
+    status!("Listing files in {:?}:", theme.cursors_dir);
     list_cursor_files(&theme.cursors_dir)?;
     
-    Ok(())
+    let warnings = skipped_alias_warnings(&skipped);
+    print_warnings(&warnings);
+    ThemeReport::collect(&theme, install_paths, warnings)
 }
 
-/// Process cursor files and rename them
-fn process_cursor_files(input_dir: &Path, theme: &CursorTheme) -> Result<()> {
-    let mapping = get_windows_to_x11_mapping();
-    
-    println!("Processing cursor files...");
+/// X11 names for source cursors, looked up by [`SourceMapping::get`]
+struct SourceMapping {
+    names: HashMap<String, &'static str>,
+    /// Keys are lowercase file stems from an inf, not exact Windows role names
+    from_inf: bool,
+}
+
+impl SourceMapping {
+    /// The X11 name for a source cursor name
+    fn get(&self, source_name: &str) -> Option<&'static str> {
+        if self.from_inf {
+            // Converted cursors usually drop the .ani/.cur extension
+            let stem = Path::new(source_name).file_stem()?.to_string_lossy().to_lowercase();
+            self.names.get(&stem).copied()
+        } else {
+            self.names.get(source_name).copied()
+        }
+    }
+}
+
+/// Map source names to X11 names, from an inf scheme or the Windows role names
+fn source_mapping(inf: Option<&Path>) -> Result<SourceMapping> {
+    let roles = get_windows_to_x11_mapping();
+    let Some(inf) = inf else {
+        let names = roles.into_iter().map(|(role, x11)| (role.to_string(), x11)).collect();
+        return Ok(SourceMapping { names, from_inf: false });
+    };
+
+    status!("Reading cursor scheme from {:?}", inf);
+    let mut names = HashMap::new();
+    for (file, role) in parse_inf_scheme(inf)? {
+        match roles.get(role.as_str()) {
+            Some(&x11) => {
+                let stem = Path::new(&file).file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+                names.insert(stem, x11);
+            }
+            None => warn!("No X11 cursor for the {} role of {}", role, file),
+        }
+    }
+    Ok(SourceMapping { names, from_inf: true })
+}
+
+/// Find the source file for each X11 cursor
+///
+/// Sources are found anywhere under each input directory, up to `max_depth`
+/// levels deep (1 is the directory itself). Two sources in one directory
+/// mapping to the same cursor are an error rather than one silently
+/// replacing the other; across directories, the later one wins.
+fn collect_sources(
+    input_dirs: &[PathBuf],
+    mapping: &SourceMapping,
+    filter: &CursorFilter,
+    max_depth: Option<usize>,
+) -> Result<BTreeMap<&'static str, PathBuf>> {
+    let mut sources: BTreeMap<&'static str, PathBuf> = BTreeMap::new();
     
-    for entry in fs::read_dir(input_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.is_file() {
+    for input_dir in input_dirs {
+        let mut found: HashMap<&str, PathBuf> = HashMap::new();
+        let mut walker = WalkDir::new(input_dir).min_depth(1).sort_by_file_name();
+        if let Some(depth) = max_depth {
+            walker = walker.max_depth(depth);
+        }
+        for entry in walker {
+            let entry = entry.with_context(|| format!("Failed to read {:?}", input_dir))?;
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            
             let file_name = path.file_name()
                 .and_then(|n| n.to_str())
                 .ok_or_else(|| anyhow::anyhow!("Invalid file name"))?;
+            let source_name = match (is_png(path)?, path.extension()) {
+                (true, Some(ext)) if ext.eq_ignore_ascii_case("png") => path.file_stem()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or(file_name),
+                _ => file_name,
+            };
+            if !filter.allows(source_name) {
+                continue;
+            }
             
-            if let Some(&x11_name) = mapping.get(file_name) {
-                println!("  Copying {} to {}", file_name, x11_name);
-                
-                let dest_path = theme.cursors_dir.join(x11_name);
-                fs::copy(&path, &dest_path)
-                    .with_context(|| format!("Failed to copy cursor file: {:?}", path))?;
-                
-                if dest_path.exists() {
-                    println!("    Successfully copied cursor");
-                    println!("    Verified: File exists at destination");
-                } else {
-                    println!("    Error: File does not exist at destination");
-                }
-            } else {
-                println!("  Skipping {} (no mapping defined)", file_name);
+            let Some(x11_name) = mapping.get(source_name) else {
+                status!("  Skipping {} (no mapping defined)", file_name);
+                continue;
+            };
+            if let Some(previous) = found.insert(x11_name, path.to_path_buf()) {
+                return Err(anyhow::anyhow!(
+                    "{:?} and {:?} both map to {}; remove one or narrow --max-depth",
+                    previous,
+                    path,
+                    x11_name
+                ));
             }
         }
-    }
-    
-    Ok(())
-}
-
-/// Create compatibility symlinks
-fn create_compatibility_symlinks(cursors_dir: &Path) -> Result<()> {
-    println!("Creating symlinks...");
-    
-    let symlinks = get_cursor_symlinks();
-    
-    for (target, link_name) in symlinks {
-        let target_path = cursors_dir.join(target);
-        let link_path = cursors_dir.join(link_name);
         
-        if target_path.exists() && !link_path.exists() {
-            FileUtils::create_symlink(target, &link_path)
-                .with_context(|| format!("Failed to create symlink: {} -> {}", link_name, target))?;
-            println!("  Created symlink: {} -> {}", link_name, target);
+        for (x11_name, path) in found {
+            if let Some(previous) = sources.insert(x11_name, path.clone()) {
+                status!("  {:?} overrides {:?} for {}", path, previous, x11_name);
+            }
         }
     }
     
-    Ok(())
+    Ok(sources)
 }
 
-/// Install theme to user's .icons directory
-fn install_to_user_icons(theme: &CursorTheme) -> Result<()> {
-    let user_icons_dir = get_icons_dir()?;
-    let user_theme_dir = user_icons_dir.join(&theme.name);
+/// Copy (or convert) each source to its X11 name
+///
+/// A PNG source is an error in `Xcursor` mode and anything else is an error
+/// in `Png` mode, so a wrong `--source-format` never yields broken cursors.
+/// With `preserve_links`, source links between mapped cursors become aliases
+/// of that link mode, written once every cursor is in place.
+fn process_cursor_files(
+    sources: &BTreeMap<&'static str, PathBuf>,
+    theme: &CursorTheme,
+    mapping: &SourceMapping,
+    format: SourceFormat,
+    preserve_links: Option<LinkMode>,
+    preserve_times: bool,
+) -> Result<()> {
+    status!("Processing cursor files...");
     
-    if theme.path != user_theme_dir {
-        println!("Installing to {:?}", user_theme_dir);
+    let mut links = Vec::new();
+    for (&x11_name, path) in sources {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let dest_path = theme.cursors_dir.join(x11_name);
+        ensure_within(&theme.cursors_dir, &dest_path)?;
         
-        // Remove existing installation
-        if user_theme_dir.exists() {
-            fs::remove_dir_all(&user_theme_dir)?;
-        }
-        fs::create_dir_all(&user_theme_dir)?;
-        
-        // Copy files
-        if theme.cursors_dir.exists() {
-            FileUtils::copy_dir_recursive(&theme.cursors_dir, &user_theme_dir.join("cursors"))?;
+        if let Some(mode) = preserve_links {
+            if let Some(target) = mapped_link_target(path, mapping)? {
+                links.push((mode, x11_name, target, dest_path));
+                continue;
+            }
         }
         
-        let index_theme = theme.path.join("index.theme");
-        if index_theme.exists() {
-            fs::copy(&index_theme, &user_theme_dir.join("index.theme"))?;
+        match (format, is_png(path)?) {
+            (SourceFormat::Xcursor, false) => {
+                status!("  Copying {} to {}", file_name, x11_name);
+                FileUtils::copy_file_with(copy_fs(preserve_times), path, &dest_path)
+                    .with_context(|| format!("Failed to copy cursor file: {:?}", path))?;
+            }
+            (SourceFormat::Xcursor, true) => {
+                return Err(anyhow::anyhow!(
+                    "{:?} is a PNG image, not an XCursor file; use --source-format png to wrap images into cursors",
+                    path
+                ));
+            }
+            (SourceFormat::Png, true) => {
+                status!("  Converting {} to {}", file_name, x11_name);
+                write_png_cursor(path, &dest_path, x11_name)?;
+            }
+            (SourceFormat::Png, false) => {
+                return Err(anyhow::anyhow!(
+                    "{:?} is not a PNG image; use --source-format xcursor to copy cursor files",
+                    path
+                ));
+            }
         }
         
-        let cursor_theme = theme.path.join("cursor.theme");
-        if cursor_theme.exists() {
-            fs::copy(&cursor_theme, &user_theme_dir.join("cursor.theme"))?;
+        if dest_path.exists() {
+            status!("    Successfully copied cursor");
+            status!("    Verified: File exists at destination");
+        } else {
+            status!("    Error: File does not exist at destination");
         }
-        
-        // Set permissions
-        FileUtils::set_permissions_recursive(&user_theme_dir, 0o755)?;
+    }
+    
+    for (mode, x11_name, target, dest_path) in links {
+        status!("  Linking {} to {}", x11_name, target);
+        FileUtils::create_link(mode, target, &dest_path)
+            .with_context(|| format!("Failed to create symlink: {:?} -> {}", dest_path, target))?;
     }
     
     Ok(())
 }
 
-/// Update GTK icon cache
-fn update_icon_cache(theme_name: &str) -> Result<()> {
-    if CommandUtils::command_exists("gtk-update-icon-cache") {
-        let user_theme_dir = get_icons_dir()?.join(theme_name);
-        let _ = CommandUtils::run_command(
-            "gtk-update-icon-cache",
-            &["-f", "-t", user_theme_dir.to_str().unwrap()],
-        );
-        // Ignore errors as this is optional
+/// Nominal sizes of the copied cursors, sorted and without duplicates
+///
+/// Cursors are copied unchanged, so their own sizes are what index.theme must
+/// declare. Links are skipped, and a cursor that doesn't parse is left out
+/// with a warning.
+fn copied_cursor_sizes<'a>(cursors_dir: &Path, names: impl Iterator<Item = &'a str>) -> Vec<u32> {
+    let mut sizes = Vec::new();
+    for name in names {
+        let path = cursors_dir.join(name);
+        if path.is_symlink() {
+            continue;
+        }
+        match Xcursor::read(&path) {
+            Ok(cursor) => {
+                let nominal = cursor.nominal_sizes();
+                detail!("  {} holds sizes {:?}", name, nominal);
+                sizes.extend(nominal);
+            }
+            Err(err) => warn!("Not declaring the sizes of {}: {:#}", name, err),
+        }
     }
-    Ok(())
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+/// The renamed target of a source symlink to a sibling cursor with a mapping
+///
+/// `None` for regular files and for links the mapping doesn't cover, which
+/// are copied like any other cursor.
+fn mapped_link_target(path: &Path, mapping: &SourceMapping) -> Result<Option<&'static str>> {
+    if !path.is_symlink() {
+        return Ok(None);
+    }
+    let target = fs::read_link(path)?;
+    if target.parent().is_some_and(|parent| !parent.as_os_str().is_empty()) {
+        return Ok(None);
+    }
+    let target_name = |name: Option<&std::ffi::OsStr>| name.and_then(|name| name.to_str()).and_then(|name| mapping.get(name));
+    Ok(target_name(target.file_name()).or_else(|| target_name(target.file_stem())))
+}
+
+/// Whether a file starts with the PNG signature
+fn is_png(path: &Path) -> Result<bool> {
+    let mut header = [0u8; 8];
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let read = file.read(&mut header)?;
+    Ok(read == header.len() && &header == PNG_SIGNATURE)
+}
+
+/// Wrap a PNG into a static cursor at the image's size
+///
+/// The nominal size is the larger image dimension; the hotspot comes from
+/// the cursor's built-in ratio.
+fn write_png_cursor(png: &Path, dest: &Path, cursor_name: &str) -> Result<()> {
+    let img = image::open(png)
+        .with_context(|| format!("Failed to read image {:?}", png))?
+        .to_rgba8();
+    let (width, height) = img.dimensions();
+    let (x_ratio, y_ratio) = get_cursor_hotspot(cursor_name);
+    let hotspot = (
+        ((x_ratio * width as f64).round() as u32).min(width.saturating_sub(1)),
+        ((y_ratio * height as f64).round() as u32).min(height.saturating_sub(1)),
+    );
+    
+    let cursor = Xcursor {
+        images: vec![XcursorImage::from_rgba(&img, width.max(height), hotspot, 0)],
+    };
+    cursor.write(dest)
 }
 
 /// List cursor files in the directory
@@ -188,7 +420,7 @@ fn list_cursor_files(cursors_dir: &Path) -> Result<()> {
             "directory".to_string()
         };
         
-        println!("  {:?} ({})", path.file_name().unwrap(), file_type);
+        status!("  {:?} ({})", path.file_name().unwrap(), file_type);
     }
     
     Ok(())
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::{print_warnings, resolve_theme_path, Warning, WarningKind};
+use crate::theme_config::{IndexTheme, DEFAULT_INHERITS};
+use crate::xcursor::Xcursor;
+use crate::{CursorKind, CursorTheme, status};
+
+/// Arguments for the retheme command
+#[derive(Debug)]
+pub struct RethemeArgs {
+    pub theme: String,
+    /// Sizes to declare in index.theme
+    pub sizes: Vec<u32>,
+}
+
+/// Summary of a retheme run
+#[derive(Debug, Serialize)]
+pub struct RethemeReport {
+    pub theme: String,
+    pub path: PathBuf,
+    pub sizes: Vec<u32>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Rewrite the `[cursors/N]` sections of a theme's index.theme without rebuilding cursors
+///
+/// Every other field of the index is kept. Sizes that some cursors don't
+/// contain are still declared, with a warning.
+pub fn retheme(args: RethemeArgs) -> Result<RethemeReport> {
+    let theme_path = resolve_theme_path(&args.theme)?;
+    let theme = CursorTheme::load(&theme_path)?;
+
+    let mut sizes = args.sizes;
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    status!("Declaring sizes {:?} for {:?}...", sizes, theme.path);
+
+    let warnings = missing_size_warnings(&theme, &sizes)?;
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+
+    let mut index = IndexTheme::read(&theme.path)?.unwrap_or_else(|| IndexTheme {
+        name: Some(theme.name.clone()),
+        inherits: DEFAULT_INHERITS.iter().map(|theme| theme.to_string()).collect(),
+        ..Default::default()
+    });
+    index.sizes = sizes.clone();
+    index.write(&theme.path)?;
+
+    status!("Done! Updated {:?}", theme.path.join("index.theme"));
+    print_warnings(&warnings);
+
+    Ok(RethemeReport {
+        theme: theme.name,
+        path: theme.path,
+        sizes,
+        warnings,
+    })
+}
+
+/// One warning per requested size that at least one cursor lacks
+fn missing_size_warnings(theme: &CursorTheme, sizes: &[u32]) -> Result<Vec<Warning>> {
+    let mut lacking: Vec<Vec<String>> = vec![Vec::new(); sizes.len()];
+
+    for entry in theme.cursors()? {
+        if !matches!(entry.kind, CursorKind::File { .. }) {
+            continue;
+        }
+        // Unreadable cursors contain no sizes at all
+        let embedded = Xcursor::read(theme.cursors_dir.join(&entry.name))
+            .map(|cursor| cursor.nominal_sizes())
+            .unwrap_or_default();
+        for (index, size) in sizes.iter().enumerate() {
+            if !embedded.contains(size) {
+                lacking[index].push(entry.name.clone());
+            }
+        }
+    }
+
+    Ok(sizes.iter()
+        .zip(lacking)
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(size, names)| Warning::new(
+            WarningKind::MissingSize,
+            None,
+            format!("size {} is missing from {} cursor(s): {}", size, names.len(), names.join(", ")),
+        ))
+        .collect())
+}
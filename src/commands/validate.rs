@@ -0,0 +1,170 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::common::render_table;
+use super::resolve_theme_path;
+use crate::theme_config::{find_inherits_cycle, read_inherits, IndexTheme};
+use crate::xcursor::Xcursor;
+use crate::{CursorKind, CursorTheme, get_icons_dir, get_system_icons_dir, detail, status};
+
+/// Arguments for the validate command
+#[derive(Debug)]
+pub struct ValidateArgs {
+    pub theme: String,
+    /// Print an aligned table of the cursors instead of the problem list
+    pub pretty: bool,
+}
+
+/// Result of validating a theme
+#[derive(Debug, Serialize)]
+pub struct ValidateReport {
+    pub theme: String,
+    pub path: PathBuf,
+    /// Sizes declared by `[cursors/N]` sections in index.theme
+    pub declared_sizes: Vec<u32>,
+    /// Real cursor files that were parsed
+    pub cursors: usize,
+    /// Cursors with more than one frame at some size
+    pub animated: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+/// Audit a theme's cursors against its index.theme
+pub fn validate_theme(args: ValidateArgs) -> Result<ValidateReport> {
+    let theme_path = resolve_theme_path(&args.theme)?;
+    let theme = CursorTheme::load(&theme_path)?;
+
+    status!("Validating {:?}...", theme.path);
+
+    let declared_sizes = IndexTheme::read(&theme.path)?
+        .map(|index| index.sizes)
+        .unwrap_or_default();
+    let files: Vec<_> = theme.cursors()?.into_iter()
+        .filter(|entry| matches!(entry.kind, CursorKind::File { .. }))
+        .collect();
+    let cursors = files.len();
+
+    let mut animated = Vec::new();
+    let mut rows = Vec::new();
+    for entry in &files {
+        // Unreadable cursors are reported by check_index_sizes
+        match Xcursor::read(theme.cursors_dir.join(&entry.name)) {
+            Ok(cursor) => {
+                detail!("  {}: {}", entry.name, cursor.animation_label());
+                if cursor.is_animated() {
+                    animated.push(entry.name.clone());
+                }
+                let sizes: Vec<String> = cursor.nominal_sizes().iter().map(u32::to_string).collect();
+                rows.push(vec![entry.name.clone(), sizes.join(", "), cursor.frame_count().to_string()]);
+            }
+            Err(_) => rows.push(vec![entry.name.clone(), "-".to_string(), "-".to_string()]),
+        }
+    }
+    status!("  {} of {} cursors are animated", animated.len(), cursors);
+
+    let mut problems = check_index_sizes(&theme)?;
+    let inheritance = check_inheritance(&theme)?;
+
+    if args.pretty {
+        // Problems are prefixed with the cursor name; show the rest in its row
+        for row in &mut rows {
+            let prefix = format!("{}: ", row[0]);
+            let state = problems.iter()
+                .find_map(|problem| problem.strip_prefix(&prefix))
+                .unwrap_or("ok")
+                .to_string();
+            row.push(state);
+        }
+        status!("{}", render_table(&["CURSOR", "SIZES", "FRAMES", "STATUS"], &rows));
+    } else {
+        for problem in &problems {
+            status!("  {}", problem);
+        }
+    }
+    for problem in &inheritance {
+        status!("  {}", problem);
+    }
+    problems.extend(inheritance);
+    if problems.is_empty() {
+        status!("OK: {} cursors match the declared sizes {:?}", cursors, declared_sizes);
+    }
+
+    Ok(ValidateReport {
+        theme: theme.name,
+        path: theme.path,
+        declared_sizes,
+        cursors,
+        animated,
+        problems,
+    })
+}
+
+/// Look for `Inherits` loops starting at this theme, across the installed themes
+///
+/// Inherited themes are looked up by directory name in the user icons
+/// directory, then the system one. Older versions of this tool wrote
+/// `Inherits=<own name>` into cursor.theme, which this reports.
+pub fn check_inheritance(theme: &CursorTheme) -> Result<Vec<String>> {
+    let Some(start) = theme.path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+        return Ok(Vec::new());
+    };
+    let search_dirs = [get_icons_dir()?, get_system_icons_dir()];
+
+    let inherits_of = |name: &str| -> Result<Vec<String>> {
+        if name == start {
+            return read_inherits(&theme.path);
+        }
+        match search_dirs.iter().map(|dir| dir.join(name)).find(|path| path.is_dir()) {
+            Some(path) => read_inherits(path),
+            None => Ok(Vec::new()),
+        }
+    };
+
+    Ok(find_inherits_cycle(&start, inherits_of)?
+        .map(|cycle| format!("inheritance cycle: {}", cycle.join(" -> ")))
+        .into_iter()
+        .collect())
+}
+
+/// Compare the sizes embedded in each cursor with the `[cursors/N]` sections of index.theme
+///
+/// Returns one message per cursor that can't be parsed or lacks a declared
+/// size. A theme without index.theme, or without size sections, declares
+/// nothing and always passes.
+pub fn check_index_sizes(theme: &CursorTheme) -> Result<Vec<String>> {
+    let declared = IndexTheme::read(&theme.path)?
+        .map(|index| index.sizes)
+        .unwrap_or_default();
+
+    let mut problems = Vec::new();
+    for entry in theme.cursors()? {
+        if !matches!(entry.kind, CursorKind::File { .. }) {
+            continue;
+        }
+
+        let cursor = match Xcursor::read(theme.cursors_dir.join(&entry.name)) {
+            Ok(cursor) => cursor,
+            Err(err) => {
+                problems.push(format!("{}: {:#}", entry.name, err));
+                continue;
+            }
+        };
+
+        let sizes = cursor.nominal_sizes();
+        let missing: Vec<String> = declared.iter()
+            .filter(|size| !sizes.contains(size))
+            .map(u32::to_string)
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "{}: missing declared size(s) {} (has {})",
+                entry.name,
+                missing.join(", "),
+                sizes.iter().map(u32::to_string).collect::<Vec<_>>().join(", "),
+            ));
+        }
+    }
+
+    Ok(problems)
+}
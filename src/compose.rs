@@ -0,0 +1,190 @@
+use anyhow::{Context, Result};
+use image::{imageops, Rgba, RgbaImage};
+
+/// Largest shadow offset, in pixels along either axis, a spec may ask for
+pub const MAX_SHADOW_OFFSET: i32 = 64;
+
+/// Largest shadow blur sigma, in pixels, a spec may ask for
+pub const MAX_SHADOW_BLUR: f32 = 16.0;
+
+/// Furthest, in pixels, [`apply_shadow`] ever grows a canvas on one side
+///
+/// Scaled parameters may exceed the spec limits; this keeps them bounded.
+const MAX_SHADOW_REACH: i64 = 512;
+
+/// Parameters for a soft drop shadow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowParams {
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// Gaussian blur sigma in pixels
+    pub blur: f32,
+    /// Shadow opacity from 0.0 to 1.0
+    pub opacity: f32,
+}
+
+impl Default for ShadowParams {
+    fn default() -> Self {
+        Self {
+            offset_x: 2,
+            offset_y: 2,
+            blur: 3.0,
+            opacity: 0.4,
+        }
+    }
+}
+
+impl ShadowParams {
+    /// Parse a spec like `offset=2,2;blur=3;opacity=0.4`
+    ///
+    /// Keys may be given in any order; missing keys keep their defaults.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut params = Self::default();
+
+        for part in spec.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid shadow parameter '{}', expected key=value", part))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "offset" => {
+                    let (x, y) = value.split_once(',')
+                        .ok_or_else(|| anyhow::anyhow!("Invalid shadow offset '{}', expected X,Y", value))?;
+                    params.offset_x = x.trim().parse().context("Invalid shadow offset")?;
+                    params.offset_y = y.trim().parse().context("Invalid shadow offset")?;
+                }
+                "blur" => params.blur = value.parse().context("Invalid shadow blur")?,
+                "opacity" => params.opacity = value.parse().context("Invalid shadow opacity")?,
+                other => return Err(anyhow::anyhow!("Unknown shadow parameter '{}'", other)),
+            }
+        }
+
+        if params.offset_x.unsigned_abs() > MAX_SHADOW_OFFSET as u32 || params.offset_y.unsigned_abs() > MAX_SHADOW_OFFSET as u32 {
+            return Err(anyhow::anyhow!("Shadow offset must be within {} pixels", MAX_SHADOW_OFFSET));
+        }
+        if !(0.0..=MAX_SHADOW_BLUR).contains(&params.blur) {
+            return Err(anyhow::anyhow!("Shadow blur must be between 0 and {}", MAX_SHADOW_BLUR));
+        }
+        if !(0.0..=1.0).contains(&params.opacity) {
+            return Err(anyhow::anyhow!("Shadow opacity must be between 0 and 1"));
+        }
+
+        Ok(params)
+    }
+
+    /// Scale offset and blur, e.g. when a frame is resized
+    pub fn scaled(&self, factor: f32) -> Self {
+        Self {
+            offset_x: (self.offset_x as f32 * factor).round() as i32,
+            offset_y: (self.offset_y as f32 * factor).round() as i32,
+            blur: self.blur * factor,
+            opacity: self.opacity,
+        }
+    }
+}
+
+/// How far the original image moved inside the expanded canvas
+///
+/// Add this to the hotspot so the pointer tip stays on the same pixel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HotspotShift {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Composite a blurred, offset, darkened copy of the image beneath it
+///
+/// The canvas grows on every side the shadow can reach, so shadows on
+/// frames that touch the edge are never clipped. Out-of-range parameters are
+/// clamped: NaN blur or opacity counts as none, and the canvas never grows by
+/// more than a bounded amount.
+pub fn apply_shadow(img: &RgbaImage, params: ShadowParams) -> (RgbaImage, HotspotShift) {
+    let finite_or_zero = |value: f32| if value.is_finite() { value } else { 0.0 };
+    let params = ShadowParams {
+        offset_x: params.offset_x.clamp(-MAX_SHADOW_REACH as i32, MAX_SHADOW_REACH as i32),
+        offset_y: params.offset_y.clamp(-MAX_SHADOW_REACH as i32, MAX_SHADOW_REACH as i32),
+        blur: finite_or_zero(params.blur).clamp(0.0, MAX_SHADOW_REACH as f32 / 3.0),
+        opacity: finite_or_zero(params.opacity).clamp(0.0, 1.0),
+    };
+    let reach = (params.blur * 3.0).ceil() as i64;
+    let (dx, dy) = (params.offset_x as i64, params.offset_y as i64);
+
+    let pad_left = (reach - dx).max(0);
+    let pad_right = (reach + dx).max(0);
+    let pad_top = (reach - dy).max(0);
+    let pad_bottom = (reach + dy).max(0);
+
+    let width = img.width() + (pad_left + pad_right) as u32;
+    let height = img.height() + (pad_top + pad_bottom) as u32;
+
+    let mut shadow = RgbaImage::new(width, height);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let alpha = (pixel.0[3] as f32 * params.opacity).round() as u8;
+        let sx = (x as i64 + pad_left + dx) as u32;
+        let sy = (y as i64 + pad_top + dy) as u32;
+        shadow.put_pixel(sx, sy, Rgba([0, 0, 0, alpha]));
+    }
+
+    let mut canvas = if params.blur > 0.0 {
+        imageops::blur(&shadow, params.blur)
+    } else {
+        shadow
+    };
+    imageops::overlay(&mut canvas, img, pad_left, pad_top);
+
+    let shift = HotspotShift {
+        x: pad_left as u32,
+        y: pad_top as u32,
+    };
+    (canvas, shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_shadow_spec() {
+        let params = ShadowParams::parse("offset=2,-1;blur=3;opacity=0.4").unwrap();
+        assert_eq!(params, ShadowParams { offset_x: 2, offset_y: -1, blur: 3.0, opacity: 0.4 });
+
+        assert!(ShadowParams::parse("opacity=2").is_err());
+        assert!(ShadowParams::parse("glow=1").is_err());
+        for spec in ["blur=NaN", "blur=inf", "blur=1e9", "opacity=NaN", "offset=100000,0", "offset=0,-2147483648"] {
+            assert!(ShadowParams::parse(spec).is_err(), "{} should be rejected", spec);
+        }
+    }
+
+    #[test]
+    fn shadow_expands_canvas_and_shifts_hotspot() {
+        let img = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+        let params = ShadowParams { offset_x: 2, offset_y: 2, blur: 1.0, opacity: 0.5 };
+
+        let (out, shift) = apply_shadow(&img, params);
+
+        // reach = 3: left/top pad 1, right/bottom pad 5
+        assert_eq!(shift, HotspotShift { x: 1, y: 1 });
+        assert_eq!((out.width(), out.height()), (10, 10));
+
+        // The original sits untouched at the shifted position
+        assert_eq!(out.get_pixel(1, 1).0, [255, 255, 255, 255]);
+        // The shadow shows past the original's bottom-right corner
+        let shadow = out.get_pixel(6, 6).0;
+        assert!(shadow[3] > 0 && shadow[0] == 0);
+        // and fades out before the canvas edge
+        assert_eq!(out.get_pixel(9, 9).0[3], 0);
+    }
+
+    #[test]
+    fn shadow_clamps_out_of_range_params() {
+        let img = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+
+        let nan = ShadowParams { offset_x: 0, offset_y: 0, blur: f32::NAN, opacity: f32::NAN };
+        let (out, shift) = apply_shadow(&img, nan);
+        assert_eq!((out.width(), shift), (4, HotspotShift::default()));
+
+        let huge = ShadowParams { offset_x: i32::MAX, offset_y: i32::MIN, blur: f32::INFINITY, opacity: 1.0 };
+        let (out, _) = apply_shadow(&img, huge);
+        assert!(out.width() <= 4 + 4 * MAX_SHADOW_REACH as u32);
+    }
+}
@@ -1,4 +1,8 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 
 /// Mapping from Windows cursor names to X11 cursor names
 pub fn get_windows_to_x11_mapping() -> HashMap<&'static str, &'static str> {
@@ -128,15 +132,181 @@ pub fn get_cursor_symlinks() -> Vec<(&'static str, &'static str)> {
     ]
 }
 
+/// Hotspot ratio for cursors without a table entry: the center
+const CENTER: (f64, f64) = (0.5, 0.5);
+
+/// Hotspot ratios (x, y) by cursor name
+///
+/// Arrows point at their tip, edge and corner cursors at the edge or corner
+/// they name, and everything symmetric (text, move, crosshairs) at the center.
+static HOTSPOT_RATIOS: &[(&str, (f64, f64))] = &[
+    // Top-left arrow tip
+    ("left_ptr", (0.125, 0.125)),
+    ("arrow", (0.125, 0.125)),
+    ("default", (0.125, 0.125)),
+    ("top_left_arrow", (0.125, 0.125)),
+    ("left_ptr_watch", (0.125, 0.125)),
+    ("progress", (0.125, 0.125)),
+    ("left_ptr_help", (0.125, 0.125)),
+    ("question_arrow", (0.125, 0.125)),
+    ("help", (0.125, 0.125)),
+    ("whats_this", (0.125, 0.125)),
+    ("context-menu", (0.125, 0.125)),
+    ("copy", (0.125, 0.125)),
+    ("link", (0.125, 0.125)),
+    ("alias", (0.125, 0.125)),
+    ("dnd-link", (0.125, 0.125)),
+    ("not-allowed", (0.125, 0.125)),
+    ("unavailable", (0.125, 0.125)),
+    ("pencil", (0.125, 0.125)),
+    ("draft", (0.125, 0.125)),
+    // Top-right arrow tip
+    ("right_ptr", (0.875, 0.125)),
+    // Pipette tip
+    ("color-picker", (0.125, 0.875)),
+    // Corners
+    ("top_left_corner", (0.125, 0.125)),
+    ("top_right_corner", (0.875, 0.125)),
+    ("bottom_left_corner", (0.125, 0.875)),
+    ("bottom_right_corner", (0.875, 0.875)),
+    ("nw-resize", (0.125, 0.125)),
+    ("ne-resize", (0.875, 0.125)),
+    ("sw-resize", (0.125, 0.875)),
+    ("se-resize", (0.875, 0.875)),
+    // Edges
+    ("top_side", (0.5, 0.125)),
+    ("bottom_side", (0.5, 0.875)),
+    ("left_side", (0.125, 0.5)),
+    ("right_side", (0.875, 0.5)),
+    ("n-resize", (0.5, 0.125)),
+    ("s-resize", (0.5, 0.875)),
+    ("w-resize", (0.125, 0.5)),
+    ("e-resize", (0.875, 0.5)),
+    // Single-headed arrows point at their head
+    ("sb_up_arrow", (0.5, 0.125)),
+    ("sb_down_arrow", (0.5, 0.875)),
+    ("sb_left_arrow", (0.125, 0.5)),
+    ("sb_right_arrow", (0.875, 0.5)),
+    ("up-arrow", (0.5, 0.125)),
+    ("down-arrow", (0.5, 0.875)),
+    ("left-arrow", (0.125, 0.5)),
+    ("right-arrow", (0.875, 0.5)),
+    // Center
+    ("text", CENTER),
+    ("xterm", CENTER),
+    ("ibeam", CENTER),
+    ("move", CENTER),
+    ("fleur", CENTER),
+    ("crosshair", CENTER),
+    ("cross", CENTER),
+    ("tcross", CENTER),
+    ("grab", CENTER),
+    ("grabbing", CENTER),
+    ("closedhand", CENTER),
+    ("openhand", CENTER),
+    ("wait", CENTER),
+    ("watch", CENTER),
+    // Lens of the magnifier
+    ("zoom-in", (0.4, 0.4)),
+    ("zoom-out", (0.4, 0.4)),
+];
+
+/// Hotspot ratios by name prefix, checked in order when there is no exact entry
+static HOTSPOT_PREFIXES: &[(&str, (f64, f64))] = &[
+    ("pointer", (0.3, 0.125)), // Fingertip
+    ("hand", (0.3, 0.125)), // Fingertip
+    ("size_", CENTER), // Double-headed resize arrows
+];
+
 /// Get hotspot ratios for different cursor types
 pub fn get_cursor_hotspot(cursor_name: &str) -> (f64, f64) {
-    match cursor_name {
-        "left_ptr" | "not-allowed" | "unavailable" => (0.125, 0.125), // Top-left tip
-        "text" | "xterm" | "ibeam" => (0.5, 0.5), // Center
-        name if name.starts_with("pointer") || name.starts_with("hand") => (0.3, 0.125), // Fingertip
-        "pencil" => (0.125, 0.125), // Tip
-        "move" => (0.5, 0.5), // Center
-        name if name.starts_with("size_") => (0.5, 0.5), // Center for resize cursors
-        _ => (0.5, 0.5), // Default: center
+    HOTSPOT_RATIOS.iter()
+        .find(|(name, _)| *name == cursor_name)
+        .or_else(|| HOTSPOT_PREFIXES.iter().find(|(prefix, _)| cursor_name.starts_with(prefix)))
+        .map(|&(_, ratio)| ratio)
+        .unwrap_or(CENTER)
+}
+
+/// An absolute hotspot in pixels at a cursor's native size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct HotspotOverride {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Absolute hotspots by cursor name, taking precedence over [`get_cursor_hotspot`]
+pub type HotspotOverrides = HashMap<String, HotspotOverride>;
+
+/// Read hotspot overrides from a TOML file of `pointer = { x = 9, y = 3 }` entries
+pub fn load_hotspot_overrides<P: AsRef<Path>>(path: P) -> Result<HotspotOverrides> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read hotspot overrides: {:?}", path))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Invalid hotspot overrides: {:?}", path))
+}
+
+/// Hotspot in pixels for a cursor scaled from `orig_size` to `size`
+///
+/// An override is scaled proportionally and clamped inside the image;
+/// otherwise the ratio from [`get_cursor_hotspot`] is applied to `size`.
+pub fn resolve_hotspot(cursor: &str, size: u32, orig_size: u32, overrides: &HotspotOverrides) -> (u32, u32) {
+    if let Some(hotspot) = overrides.get(cursor) {
+        let scale = size as f64 / orig_size.max(1) as f64;
+        let scaled = |v: u32| ((v as f64 * scale).round() as u32).min(size.saturating_sub(1));
+        return (scaled(hotspot.x), scaled(hotspot.y));
+    }
+
+    let (x_ratio, y_ratio) = get_cursor_hotspot(cursor);
+    (((size as f64 * x_ratio) as u32).max(1), ((size as f64 * y_ratio) as u32).max(1))
+}
+
+/// Hotspot of a cursor as ratios of its size, for reporting
+pub fn hotspot_ratio(cursor: &str, orig_size: u32, overrides: &HotspotOverrides) -> (f64, f64) {
+    match overrides.get(cursor) {
+        Some(hotspot) => {
+            let size = orig_size.max(1) as f64;
+            (hotspot.x as f64 / size, hotspot.y as f64 / size)
+        }
+        None => get_cursor_hotspot(cursor),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_and_corner_cursors_are_not_centered() {
+        assert_eq!(get_cursor_hotspot("left_ptr"), (0.125, 0.125));
+        assert_eq!(get_cursor_hotspot("top_left_corner"), (0.125, 0.125));
+        assert_eq!(get_cursor_hotspot("bottom_right_corner"), (0.875, 0.875));
+        assert_eq!(get_cursor_hotspot("right_side"), (0.875, 0.5));
+        assert_eq!(get_cursor_hotspot("sb_up_arrow"), (0.5, 0.125));
+        assert_eq!(get_cursor_hotspot("hand2"), (0.3, 0.125));
+        assert_eq!(get_cursor_hotspot("crosshair"), CENTER);
+        assert_eq!(get_cursor_hotspot("size_ver"), CENTER);
+        assert_eq!(get_cursor_hotspot("something-else"), CENTER);
+    }
+
+    #[test]
+    fn hotspot_table_has_no_duplicates() {
+        let mut names: Vec<_> = HOTSPOT_RATIOS.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        let count = names.len();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn hotspot_overrides_are_scaled_and_win_over_ratios() {
+        let overrides: HotspotOverrides = toml::from_str("pointer = { x = 9, y = 3 }").unwrap();
+
+        assert_eq!(resolve_hotspot("pointer", 24, 24, &overrides), (9, 3));
+        assert_eq!(resolve_hotspot("pointer", 48, 24, &overrides), (18, 6));
+        assert_eq!(resolve_hotspot("pointer", 32, 24, &overrides), (12, 4));
+        // Without an override the built-in ratio applies
+        assert_eq!(resolve_hotspot("pointer", 48, 24, &HashMap::new()), (14, 6));
+        assert_eq!(hotspot_ratio("pointer", 24, &overrides), (0.375, 0.125));
     }
 }
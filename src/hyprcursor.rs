@@ -0,0 +1,550 @@
+use anyhow::{Context, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::theme_config::{render_cursor_meta, HyprManifest, MetaEntry, ResizeAlgorithm};
+use crate::xcursor::Xcursor;
+
+/// Extension of a compiled hyprcursor shape
+pub const HLC_EXTENSION: &str = "hlc";
+
+/// Cursor directory used when the manifest names none
+pub const DEFAULT_CURSORS_DIRECTORY: &str = "hyprcursors";
+
+/// Per-shape metadata file inside a shape directory
+pub const META_FILE: &str = "meta.hl";
+
+/// Size declared for SVG images, which hyprcursor renders at any size
+pub const SVG_SIZE: u32 = 0;
+
+/// Keys [`write_shape`] writes itself; any other line of an old `meta.hl` is kept
+const GENERATED_META_KEYS: &[&str] = &["define_size", "hotspot_x", "hotspot_y", "resize_algorithm"];
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+/// Zip 2.0, the first version with deflate
+const ZIP_VERSION: u16 = 20;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+/// 1980-01-01, the earliest DOS date, so archives are reproducible
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Compress one file for a hyprcursor shape archive
+///
+/// A compiled shape (`<shape>.hlc`) is a zip archive of the shape's
+/// directory; this is the raw deflate stream stored for each entry.
+pub fn compress_payload(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).expect("writing to a Vec cannot fail");
+    encoder.finish().expect("writing to a Vec cannot fail")
+}
+
+/// Inverse of [`compress_payload`]
+pub fn decompress_payload(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    DeflateDecoder::new(data)
+        .read_to_end(&mut out)
+        .context("Invalid deflate data")?;
+    Ok(out)
+}
+
+/// One raster image of a hyprcursor shape
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub path: PathBuf,
+    /// Nominal size the image is declared at
+    pub size: u32,
+    /// Animation delay, for shapes with several frames at one size
+    pub delay_ms: Option<u32>,
+}
+
+/// The images a hyprcursor shape is built from
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceAsset {
+    Raster(Vec<Frame>),
+    /// A vector image, declared at [`SVG_SIZE`] and scaled by hyprcursor
+    Svg(PathBuf),
+}
+
+/// The manifest's `cursors_directory`, where the shape directories live
+pub fn cursors_directory(manifest: &HyprManifest) -> &str {
+    manifest.get("cursors_directory").unwrap_or(DEFAULT_CURSORS_DIRECTORY)
+}
+
+/// Write a shape directory: its images and a `meta.hl` declaring them
+///
+/// `hotspot` is the (x, y) ratio of the image size. Lines of an existing
+/// `meta.hl` other than sizes and hotspots (such as `define_override`
+/// aliases) are kept, and its old images are removed. Raster shapes keep a
+/// valid existing resize algorithm and get `bilinear` otherwise.
+pub fn write_shape(shape_dir: &Path, hotspot: (f64, f64), asset: &SourceAsset) -> Result<()> {
+    let meta_path = shape_dir.join(META_FILE);
+    let old_meta = fs::read_to_string(&meta_path).unwrap_or_default();
+    let kept: Vec<String> = old_meta.lines()
+        .filter(|line| {
+            let key = line.split_once('=').map_or("", |(key, _)| key.trim());
+            !GENERATED_META_KEYS.contains(&key)
+        })
+        .map(str::to_string)
+        .collect();
+    let resize_algorithm: ResizeAlgorithm = HyprManifest::parse(&old_meta)
+        .get("resize_algorithm")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default();
+    // Read the images first, as they may live in the directory being replaced
+    let images: Vec<(&Path, u32, Option<u32>)> = match asset {
+        SourceAsset::Raster(frames) => frames.iter()
+            .map(|frame| (frame.path.as_path(), frame.size, frame.delay_ms))
+            .collect(),
+        SourceAsset::Svg(path) => vec![(path.as_path(), SVG_SIZE, None)],
+    };
+    let mut files = Vec::new();
+    for (path, size, delay) in images {
+        let file_name = path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid image path: {:?}", path))?;
+        let data = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        files.push((file_name, data, size, delay));
+    }
+
+    let mut head = Vec::new();
+    if matches!(asset, SourceAsset::Raster(_)) {
+        head.push(MetaEntry::ResizeAlgorithm(resize_algorithm));
+    }
+    head.push(MetaEntry::Hotspot { x: hotspot.0, y: hotspot.1 });
+    let sizes: Vec<MetaEntry> = files.iter()
+        .map(|(file_name, _, size, delay)| MetaEntry::Size {
+            size: *size,
+            file: file_name.to_string(),
+            delay_ms: *delay,
+        })
+        .collect();
+
+    let mut meta = render_cursor_meta(&head);
+    for line in kept.iter().filter(|line| !line.trim().is_empty()) {
+        meta.push_str(line);
+        meta.push('\n');
+    }
+    meta.push_str(&render_cursor_meta(&sizes));
+
+    if shape_dir.exists() {
+        fs::remove_dir_all(shape_dir).with_context(|| format!("Failed to clear {:?}", shape_dir))?;
+    }
+    fs::create_dir_all(shape_dir)?;
+    for (file_name, data, _, _) in &files {
+        fs::write(shape_dir.join(file_name), data)
+            .with_context(|| format!("Failed to write {:?}", shape_dir.join(file_name)))?;
+    }
+    fs::write(&meta_path, meta).with_context(|| format!("Failed to write {:?}", meta_path))
+}
+
+/// Set the resize algorithm of every shape under `shapes_dir`
+///
+/// Other lines of each `meta.hl` are kept. Returns how many shapes were updated.
+pub fn set_resize_algorithm(shapes_dir: &Path, algorithm: ResizeAlgorithm) -> Result<usize> {
+    let mut updated = 0;
+    for entry in fs::read_dir(shapes_dir).with_context(|| format!("Failed to read {:?}", shapes_dir))? {
+        let meta_path = entry?.path().join(META_FILE);
+        if !meta_path.is_file() {
+            continue;
+        }
+        let mut meta = HyprManifest::read(&meta_path)?;
+        meta.set("resize_algorithm", algorithm.as_str());
+        meta.write(&meta_path)?;
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+/// Write the shapes of a hyprcursor working theme from an XCursor theme
+///
+/// Each cursor in `cursors_dir` becomes a shape under `shapes_dir` with one
+/// PNG per image, and each symlink a `define_override` alias of the shape it
+/// leads to. The cursors are read in process, so no tool extracts their
+/// frames again. Returns the cursors that are not valid XCursor files.
+pub fn write_shapes_from_xcursors(cursors_dir: &Path, shapes_dir: &Path) -> Result<Vec<String>> {
+    let mut entries = fs::read_dir(cursors_dir)
+        .with_context(|| format!("Failed to read {:?}", cursors_dir))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    let cursors_dir = fs::canonicalize(cursors_dir)?;
+
+    let mut unparsed = Vec::new();
+    let mut aliases: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for path in entries {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if path.is_symlink() {
+            // Aliases only count when they end at a cursor of this theme
+            let target = fs::canonicalize(&path).ok()
+                .filter(|target| target.parent() == Some(cursors_dir.as_path()));
+            if let Some(target) = target {
+                let shape = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                aliases.entry(shape).or_default().push(name);
+            }
+            continue;
+        }
+        if !path.is_file() {
+            continue;
+        }
+        match Xcursor::read(&path) {
+            Ok(cursor) => write_xcursor_shape(&shapes_dir.join(&name), &name, &cursor)?,
+            Err(_) => unparsed.push(name),
+        }
+    }
+
+    for (shape, names) in aliases {
+        let meta_path = shapes_dir.join(&shape).join(META_FILE);
+        if !meta_path.is_file() {
+            continue;
+        }
+        let mut meta = fs::read_to_string(&meta_path)?;
+        meta.push_str(&render_cursor_meta(&[MetaEntry::Override(names)]));
+        fs::write(&meta_path, meta).with_context(|| format!("Failed to write {:?}", meta_path))?;
+    }
+
+    Ok(unparsed)
+}
+
+/// Write one shape from the images of an XCursor
+///
+/// The hotspot ratio comes from the largest image; delays are only declared
+/// for animated cursors.
+fn write_xcursor_shape(shape_dir: &Path, name: &str, cursor: &Xcursor) -> Result<()> {
+    let largest = cursor.images.iter()
+        .max_by_key(|image| image.width)
+        .ok_or_else(|| anyhow::anyhow!("{} has no images", name))?;
+    let hotspot = (
+        largest.xhot as f64 / largest.width.max(1) as f64,
+        largest.yhot as f64 / largest.height.max(1) as f64,
+    );
+
+    fs::create_dir_all(shape_dir)?;
+    let animated = cursor.is_animated();
+    let mut frames = Vec::new();
+    for size in cursor.nominal_sizes() {
+        for (i, image) in cursor.frames(size).into_iter().enumerate() {
+            let path = shape_dir.join(format!("{}_{}_{}.png", name, size, i + 1));
+            image.to_rgba().save(&path)
+                .with_context(|| format!("Failed to write {:?}", path))?;
+            frames.push(Frame { path, size, delay_ms: animated.then_some(image.delay) });
+        }
+    }
+    write_shape(shape_dir, hotspot, &SourceAsset::Raster(frames))
+}
+
+/// Pack the files of a shape directory (`meta.hl` and its images) into a `.hlc` archive
+///
+/// Entries are deflate-compressed and sorted by name, with fixed timestamps.
+pub fn write_hlc(shape_dir: &Path, out: &Path) -> Result<()> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(shape_dir).with_context(|| format!("Failed to read {:?}", shape_dir))? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid file name: {:?}", path))?
+            .to_string();
+        let data = fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        files.push((name, data));
+    }
+    files.sort();
+
+    fs::write(out, zip_archive(&files)).with_context(|| format!("Failed to write {:?}", out))
+}
+
+/// Read the `(name, contents)` entries of a `.hlc` archive, in archive order
+pub fn read_hlc(path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    unzip_archive(&data).with_context(|| format!("Invalid hyprcursor archive: {:?}", path))
+}
+
+/// Compile a hyprcursor working theme the way `hyprcursor-util --create` does
+///
+/// `theme_dir` holds `manifest.hl` and one directory per shape under the
+/// manifest's `cursors_directory`. The manifest and any other top-level files
+/// are copied to `out_dir`, and each shape directory becomes `<shape>.hlc`.
+pub fn compile_theme(theme_dir: &Path, out_dir: &Path) -> Result<()> {
+    let manifest = HyprManifest::read(theme_dir.join("manifest.hl"))?;
+    let cursors_directory = cursors_directory(&manifest);
+    let shapes_dir = theme_dir.join(cursors_directory);
+    let out_shapes = out_dir.join(cursors_directory);
+    fs::create_dir_all(&out_shapes)?;
+
+    for entry in fs::read_dir(theme_dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            fs::copy(&path, out_dir.join(path.file_name().unwrap()))
+                .with_context(|| format!("Failed to copy {:?}", path))?;
+        }
+    }
+
+    for entry in fs::read_dir(&shapes_dir).with_context(|| format!("Failed to read {:?}", shapes_dir))? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let shape = path.file_name().unwrap().to_string_lossy();
+        write_hlc(&path, &out_shapes.join(format!("{}.{}", shape, HLC_EXTENSION)))?;
+    }
+
+    Ok(())
+}
+
+fn zip_archive(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in files {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let compressed = compress_payload(data);
+        let offset = archive.len() as u32;
+
+        // Fields shared by the local and central headers, from "version needed" on
+        let mut common = Vec::new();
+        put_u16(&mut common, ZIP_VERSION);
+        put_u16(&mut common, 0);
+        put_u16(&mut common, METHOD_DEFLATE);
+        put_u16(&mut common, 0);
+        put_u16(&mut common, DOS_DATE);
+        put_u32(&mut common, crc.sum());
+        put_u32(&mut common, compressed.len() as u32);
+        put_u32(&mut common, data.len() as u32);
+        put_u16(&mut common, name.len() as u16);
+        put_u16(&mut common, 0);
+
+        put_u32(&mut archive, LOCAL_HEADER_SIGNATURE);
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&compressed);
+
+        put_u32(&mut central, CENTRAL_HEADER_SIGNATURE);
+        put_u16(&mut central, ZIP_VERSION);
+        central.extend_from_slice(&common);
+        put_u16(&mut central, 0); // comment length
+        put_u16(&mut central, 0); // disk number
+        put_u16(&mut central, 0); // internal attributes
+        put_u32(&mut central, 0); // external attributes
+        put_u32(&mut central, offset);
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = archive.len() as u32;
+    archive.extend_from_slice(&central);
+    put_u32(&mut archive, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut archive, 0);
+    put_u16(&mut archive, 0);
+    put_u16(&mut archive, files.len() as u16);
+    put_u16(&mut archive, files.len() as u16);
+    put_u32(&mut archive, central.len() as u32);
+    put_u32(&mut archive, central_offset);
+    put_u16(&mut archive, 0);
+    archive
+}
+
+fn unzip_archive(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let end = (0..=data.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+        .rev()
+        .find(|&at| read_u32(data, at).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| anyhow::anyhow!("Missing end of central directory"))?;
+    let count = read_u16(data, end + 10)? as usize;
+    let mut at = read_u32(data, end + 16)? as usize;
+
+    let mut files = Vec::with_capacity(count);
+    for _ in 0..count {
+        if read_u32(data, at)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(anyhow::anyhow!("Bad central directory entry at offset {}", at));
+        }
+        let method = read_u16(data, at + 10)?;
+        let crc = read_u32(data, at + 16)?;
+        let compressed_size = read_u32(data, at + 20)? as usize;
+        let name_len = read_u16(data, at + 28)? as usize;
+        let extra_len = read_u16(data, at + 30)? as usize;
+        let comment_len = read_u16(data, at + 32)? as usize;
+        let local = read_u32(data, at + 42)? as usize;
+        let name = String::from_utf8_lossy(slice(data, at + 46, name_len)?).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+
+        if read_u32(data, local)? != LOCAL_HEADER_SIGNATURE {
+            return Err(anyhow::anyhow!("Bad local header for {}", name));
+        }
+        let start = local + 30 + read_u16(data, local + 26)? as usize + read_u16(data, local + 28)? as usize;
+        let payload = slice(data, start, compressed_size)?;
+        let contents = match method {
+            METHOD_STORED => payload.to_vec(),
+            METHOD_DEFLATE => decompress_payload(payload)?,
+            other => return Err(anyhow::anyhow!("Unsupported compression method {} for {}", other, name)),
+        };
+
+        let mut check = Crc::new();
+        check.update(&contents);
+        if check.sum() != crc {
+            return Err(anyhow::anyhow!("CRC mismatch for {}", name));
+        }
+        files.push((name, contents));
+    }
+
+    Ok(files)
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    data.get(offset..offset + len)
+        .ok_or_else(|| anyhow::anyhow!("Truncated archive at offset {}", offset))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    slice(data, offset, 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    slice(data, offset, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_round_trip() {
+        let data = b"resize_algorithm = bilinear\n".repeat(20);
+        let compressed = compress_payload(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress_payload(&compressed).unwrap(), data);
+        assert!(decompress_payload(b"\xff\xff not deflate").is_err());
+    }
+
+    #[test]
+    fn svg_shapes_replace_raster_images_and_keep_aliases() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shape = dir.path().join("left_ptr");
+        fs::create_dir_all(&shape).unwrap();
+        fs::write(shape.join("left_ptr_24.png"), b"png").unwrap();
+        fs::write(
+            shape.join(META_FILE),
+            "resize_algorithm = bilinear\nhotspot_x = 0.5\nhotspot_y = 0.5\ndefine_override = arrow;default\ndefine_size = 24, left_ptr_24.png\n",
+        ).unwrap();
+        let svg = dir.path().join("left_ptr.svg");
+        fs::write(&svg, "<svg/>").unwrap();
+
+        write_shape(&shape, (0.125, 0.125), &SourceAsset::Svg(svg)).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(shape.join(META_FILE)).unwrap(),
+            "hotspot_x = 0.125\nhotspot_y = 0.125\ndefine_override = arrow;default\ndefine_size = 0, left_ptr.svg\n",
+        );
+        assert!(shape.join("left_ptr.svg").is_file());
+        assert!(!shape.join("left_ptr_24.png").exists());
+    }
+
+    #[test]
+    fn resize_algorithms_survive_rewrites_until_set() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let image = dir.path().join("pixel_24.png");
+        fs::write(&image, b"png").unwrap();
+        let frames = vec![Frame { path: image, size: 24, delay_ms: None }];
+
+        let shapes = dir.path().join("hyprcursors");
+        let shape = shapes.join("pixel");
+        fs::create_dir_all(&shape).unwrap();
+        fs::write(shape.join(META_FILE), "resize_algorithm = nearest\nhotspot_x = 0\nhotspot_y = 0\n").unwrap();
+        write_shape(&shape, (0.0, 0.0), &SourceAsset::Raster(frames)).unwrap();
+        assert!(fs::read_to_string(shape.join(META_FILE)).unwrap().starts_with("resize_algorithm = nearest\n"));
+
+        assert_eq!(set_resize_algorithm(&shapes, ResizeAlgorithm::None).unwrap(), 1);
+        let meta = fs::read_to_string(shape.join(META_FILE)).unwrap();
+        assert!(meta.starts_with("resize_algorithm = none\n"));
+        assert!(meta.ends_with("define_size = 24, pixel_24.png\n"));
+    }
+
+    #[test]
+    fn raster_shapes_declare_each_frame() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let frames: Vec<Frame> = (1..=2)
+            .map(|i| {
+                let path = dir.path().join(format!("wait_{}.png", i));
+                fs::write(&path, b"png").unwrap();
+                Frame { path, size: 32, delay_ms: Some(50) }
+            })
+            .collect();
+
+        let shape = dir.path().join("hyprcursors/wait");
+        write_shape(&shape, (0.5, 0.5), &SourceAsset::Raster(frames)).unwrap();
+
+        let meta = fs::read_to_string(shape.join(META_FILE)).unwrap();
+        assert!(meta.starts_with("resize_algorithm = bilinear\n"));
+        assert!(meta.ends_with("define_size = 32, wait_1.png, 50\ndefine_size = 32, wait_2.png, 50\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn xcursor_themes_become_shapes_with_aliases() {
+        use crate::xcursor::XcursorImage;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let cursors = dir.path().join("cursors");
+        fs::create_dir_all(&cursors).unwrap();
+        let images = [24, 48]
+            .map(|size| XcursorImage::from_rgba(&image::RgbaImage::new(size, size), size, (size / 4, size / 2), 50))
+            .to_vec();
+        Xcursor { images }.write(cursors.join("left_ptr")).unwrap();
+        std::os::unix::fs::symlink("left_ptr", cursors.join("arrow")).unwrap();
+        std::os::unix::fs::symlink("arrow", cursors.join("default")).unwrap();
+        fs::write(cursors.join("broken"), b"not a cursor").unwrap();
+
+        let shapes = dir.path().join("hyprcursors");
+        let unparsed = write_shapes_from_xcursors(&cursors, &shapes).unwrap();
+
+        assert_eq!(unparsed, ["broken"]);
+        assert!(!shapes.join("arrow").exists());
+        assert_eq!(
+            fs::read_to_string(shapes.join("left_ptr").join(META_FILE)).unwrap(),
+            "resize_algorithm = bilinear\nhotspot_x = 0.250\nhotspot_y = 0.500\n\
+             define_size = 24, left_ptr_24_1.png\ndefine_size = 48, left_ptr_48_1.png\n\
+             define_override = arrow;default\n",
+        );
+        assert!(shapes.join("left_ptr/left_ptr_48_1.png").is_file());
+    }
+
+    #[test]
+    fn compiled_theme_reads_back() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let working = dir.path().join("working");
+        let shape = working.join("hyprcursors/left_ptr");
+        fs::create_dir_all(&shape).unwrap();
+        fs::write(working.join("manifest.hl"), "name = Test\ncursors_directory = hyprcursors\n").unwrap();
+        fs::write(shape.join("meta.hl"), "hotspot_x = 0.1\nhotspot_y = 0.1\ndefine_size = 24, left_ptr_24.png\n").unwrap();
+        let mut png = Vec::new();
+        image::RgbaImage::new(24, 24)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        fs::write(shape.join("left_ptr_24.png"), &png).unwrap();
+
+        let out = dir.path().join("out");
+        compile_theme(&working, &out).unwrap();
+
+        assert!(out.join("manifest.hl").is_file());
+        let entries = read_hlc(&out.join("hyprcursors/left_ptr.hlc")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["left_ptr_24.png", "meta.hl"]);
+        assert_eq!(entries[0].1, png);
+        assert_eq!(entries[1].1, fs::read(shape.join("meta.hl")).unwrap());
+    }
+}
@@ -1,11 +1,23 @@
 use anyhow::{Context, Result};
+use log::{debug, log, warn, Level};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 pub mod commands;
+pub mod compose;
 pub mod cursor_mapping;
+pub mod hyprcursor;
+pub mod quantize;
+pub mod recolor;
 pub mod theme_config;
+pub mod windows;
+pub mod xcursor;
 
 pub use walkdir;
 
@@ -16,12 +28,57 @@ pub enum CursorError {
     ThemeNotFound(PathBuf),
     #[error("Cursor file not found: {0}")]
     CursorNotFound(PathBuf),
+    #[error("Manifest file not found: {0}")]
+    ManifestNotFound(PathBuf),
+    #[error("Required tool not found: {0}")]
+    MissingDependency(String),
+    #[error("Invalid theme name {name:?}: {reason}")]
+    InvalidThemeName { name: String, reason: String },
+    #[error("Path {path:?} is outside of {base:?}")]
+    PathOutsideBase { base: PathBuf, path: PathBuf },
     #[error("Command failed: {command} - {error}")]
     CommandFailed { command: String, error: String },
+    #[error("Command timed out after {}s: {command}", .timeout.as_secs_f64())]
+    CommandTimeout { command: String, timeout: Duration },
+    #[error("{} cursor(s) failed: {}", .0.len(), .0.join(", "))]
+    CursorsFailed(Vec<String>),
+    #[error("Symlink cycle: {}", .0.join(" -> "))]
+    SymlinkCycle(Vec<String>),
+    #[error("{theme} has {problems} problem(s)")]
+    ValidationFailed { theme: String, problems: usize },
+    #[error("Installed theme {path:?} differs from the build: {}", .differences.join("; "))]
+    InstallMismatch { path: PathBuf, differences: Vec<String> },
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
+/// How alias cursors (e.g. `arrow` for `left_ptr`) are written into a theme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkMode {
+    /// A relative symlink to the target cursor
+    #[default]
+    Symlink,
+    /// A full copy of the target cursor, for apps that don't follow symlinks
+    Copy,
+    /// A hard link to the target cursor; falls back to a copy across filesystems
+    Hardlink,
+}
+
+/// Where built themes are installed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstallScope {
+    /// The user's icons directory (see [`get_icons_dir`])
+    #[default]
+    User,
+    /// The system icons directory (see [`get_system_icons_dir`])
+    System,
+    /// The user directory, then the system one when it is writable
+    Both,
+}
+
+/// Name of a theme's cursors directory unless the theme says otherwise
+pub const DEFAULT_CURSORS_DIR: &str = "cursors";
+
 /// Represents a cursor theme
 #[derive(Debug, Clone)]
 pub struct CursorTheme {
@@ -32,7 +89,12 @@ pub struct CursorTheme {
 
 impl CursorTheme {
     pub fn new(name: String, path: PathBuf) -> Self {
-        let cursors_dir = path.join("cursors");
+        Self::with_cursors_dir(name, path, None)
+    }
+
+    /// A theme whose cursors live in `<path>/<cursors_dir>` (default: `cursors`)
+    pub fn with_cursors_dir(name: String, path: PathBuf, cursors_dir: Option<&str>) -> Self {
+        let cursors_dir = path.join(cursors_dir.unwrap_or(DEFAULT_CURSORS_DIR));
         Self {
             name,
             path,
@@ -49,9 +111,441 @@ impl CursorTheme {
             .with_context(|| format!("Failed to create cursors directory: {:?}", self.cursors_dir))?;
         Ok(())
     }
+
+    /// Load an existing theme from disk
+    ///
+    /// The name comes from `index.theme` when present, otherwise from the
+    /// directory name. Cursors are read from `cursors/`, or when a theme
+    /// without one has a `manifest.hl`, from its `cursors_directory`.
+    pub fn load(path: &Path) -> Result<CursorTheme> {
+        if !path.is_dir() {
+            return Err(CursorError::ThemeNotFound(path.to_path_buf()).into());
+        }
+
+        let index_name = theme_config::IndexTheme::read(path)?
+            .and_then(|index| index.name)
+            .filter(|name| !name.is_empty());
+        let name = match index_name {
+            Some(name) => name,
+            None => path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .ok_or_else(|| anyhow::anyhow!("Invalid theme path: {:?}", path))?,
+        };
+
+        let cursors_dir = Self::manifest_cursors_dir(path)?;
+        Ok(Self::with_cursors_dir(name, path.to_path_buf(), cursors_dir.as_deref()))
+    }
+
+    /// The cursors directory [`load`](Self::load) would use for the theme at `path`
+    ///
+    /// Unlike `load` this doesn't read `index.theme`, so a theme whose index
+    /// can't be parsed still has one.
+    pub fn find_cursors_dir(path: &Path) -> Result<PathBuf> {
+        let cursors_dir = Self::manifest_cursors_dir(path)?;
+        Ok(path.join(cursors_dir.as_deref().unwrap_or(DEFAULT_CURSORS_DIR)))
+    }
+
+    /// The `cursors_directory` of a hyprcursor theme lacking `cursors/`
+    fn manifest_cursors_dir(path: &Path) -> Result<Option<String>> {
+        let manifest_path = path.join("manifest.hl");
+        if path.join(DEFAULT_CURSORS_DIR).is_dir() || !manifest_path.is_file() {
+            return Ok(None);
+        }
+        let manifest = theme_config::HyprManifest::read(&manifest_path)?;
+        let dir = hyprcursor::cursors_directory(&manifest).to_string();
+        ensure_within(path, &path.join(&dir))
+            .with_context(|| format!("Invalid cursors_directory in {:?}: {}", manifest_path, dir))?;
+        Ok(Some(dir))
+    }
+
+    /// List the cursors in this theme, sorted by name
+    pub fn cursors(&self) -> Result<Vec<CursorEntry>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&self.cursors_dir)
+            .with_context(|| format!("Failed to read cursors directory: {:?}", self.cursors_dir))?
+        {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            let kind = if path.is_symlink() {
+                CursorKind::Symlink { target: fs::read_link(&path)? }
+            } else if path.is_file() {
+                CursorKind::File { size: entry.metadata()?.len() }
+            } else {
+                continue;
+            };
+
+            entries.push(CursorEntry { name, kind });
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
+
+/// A single entry in a theme's cursors directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorEntry {
+    pub name: String,
+    pub kind: CursorKind,
+}
+
+/// Whether a cursor entry is a real file or an alias
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CursorKind {
+    File { size: u64 },
+    Symlink { target: PathBuf },
+}
+
+/// Map each real cursor in `cursors_dir` to the aliases that resolve to it
+///
+/// Chains such as `hand1 -> hand2 -> pointer` are followed, so every alias is
+/// listed under the real cursor at the end of its chain. Alias lists are
+/// sorted, and cursors without aliases map to an empty list. Aliases that
+/// leave the directory or end at a missing cursor are left out; a chain that
+/// loops is reported as `CursorError::SymlinkCycle`.
+pub fn symlink_graph(cursors_dir: &Path) -> Result<HashMap<String, Vec<String>>> {
+    let mut graph = HashMap::new();
+    let mut links = HashMap::new();
+    for entry in fs::read_dir(cursors_dir)
+        .with_context(|| format!("Failed to read cursors directory: {:?}", cursors_dir))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_symlink() {
+            links.insert(name, link_target_name(cursors_dir, &fs::read_link(&path)?));
+        } else if path.is_file() {
+            graph.insert(name, Vec::new());
+        }
+    }
+
+    for alias in links.keys() {
+        let mut chain = vec![alias.clone()];
+        let mut next = links[alias].clone();
+        while let Some(name) = next {
+            if let Some(aliases) = graph.get_mut(&name) {
+                aliases.push(alias.clone());
+                break;
+            }
+            if chain.contains(&name) {
+                chain.push(name);
+                return Err(CursorError::SymlinkCycle(chain).into());
+            }
+            next = links.get(&name).cloned().flatten();
+            chain.push(name);
+        }
+    }
+
+    for aliases in graph.values_mut() {
+        aliases.sort();
+    }
+    Ok(graph)
+}
+
+/// Total size in bytes of the files under `path`
+///
+/// Symlinks are not followed and count for nothing, so aliases don't add
+/// their target's size again.
+pub fn theme_disk_usage(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry.with_context(|| format!("Failed to read theme: {:?}", path))?;
+        if entry.file_type().is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// The cursor name a link target refers to, if it stays inside `cursors_dir`
+fn link_target_name(cursors_dir: &Path, target: &Path) -> Option<String> {
+    let target = if target.is_absolute() {
+        target.strip_prefix(cursors_dir).ok()?
+    } else {
+        target.strip_prefix(".").unwrap_or(target)
+    };
+    let mut components = target.components();
+    match (components.next(), components.next()) {
+        (Some(std::path::Component::Normal(name)), None) => Some(name.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+/// Filesystem operations used by the cursor tools
+///
+/// `FileUtils` goes through this trait so the same copy/link logic can run
+/// against the real filesystem, a logging no-op filesystem for dry runs, or
+/// an in-memory fake in tests.
+pub trait Fs {
+    /// Copy a file's contents, following symlinks in the source
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64>;
+    /// Create a directory and all missing parents
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()>;
+    /// Remove a file or symlink
+    fn remove_file(&self, path: &Path) -> std::io::Result<()>;
+    /// Remove a directory and everything below it
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()>;
+    /// Create a symbolic link at `link` pointing to `target`
+    fn symlink(&self, target: &Path, link: &Path) -> std::io::Result<()>;
+    /// The target of a symbolic link, as written
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf>;
+    /// Create a hard link at `link` to the existing file `original`
+    fn hard_link(&self, original: &Path, link: &Path) -> std::io::Result<()>;
+    /// List the entries of a directory
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>>;
+    /// Set the permission bits of a path (no-op where unsupported)
+    fn set_permissions(&self, path: &Path, mode: u32) -> std::io::Result<()>;
+    /// Whether the path exists, following symlinks
+    fn exists(&self, path: &Path) -> bool;
+    /// Whether the path is a directory, following symlinks
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Whether the path itself is a symlink
+    fn is_symlink(&self, path: &Path) -> bool;
+}
+
+/// The real filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFs;
+
+impl Fs for RealFs {
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64> {
+        fs::copy(from, to)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> std::io::Result<()> {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(target, link);
+
+        #[cfg(windows)]
+        return std::os::windows::fs::symlink_file(target, link);
+    }
+
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> std::io::Result<()> {
+        fs::hard_link(original, link)
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    fn set_permissions(&self, path: &Path, mode: u32) -> std::io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(not(unix))]
+    fn set_permissions(&self, _path: &Path, _mode: u32) -> std::io::Result<()> {
+        // No-op on non-Unix systems
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
+}
+
+/// The real filesystem, giving copies the modification time of their source
+///
+/// Used for `--preserve-times`; everything but [`Fs::copy`] goes to [`RealFs`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreserveTimesFs;
+
+impl Fs for PreserveTimesFs {
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64> {
+        let copied = fs::copy(from, to)?;
+        copy_mtime(from, to)?;
+        Ok(copied)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        RealFs.create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        RealFs.remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        RealFs.remove_dir_all(path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> std::io::Result<()> {
+        RealFs.symlink(target, link)
+    }
+
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        RealFs.read_link(path)
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> std::io::Result<()> {
+        RealFs.hard_link(original, link)
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        RealFs.read_dir(path)
+    }
+
+    fn set_permissions(&self, path: &Path, mode: u32) -> std::io::Result<()> {
+        RealFs.set_permissions(path, mode)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        RealFs.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        RealFs.is_dir(path)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        RealFs.is_symlink(path)
+    }
+}
+
+/// The real filesystem copies go through: [`PreserveTimesFs`] with `preserve_times`, else [`RealFs`]
+pub fn copy_fs(preserve_times: bool) -> &'static (dyn Fs + Sync) {
+    if preserve_times {
+        &PreserveTimesFs
+    } else {
+        &RealFs
+    }
+}
+
+/// A filesystem that reads from disk but only logs mutations
+///
+/// Used for dry runs: every write is printed instead of performed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopFs;
+
+impl Fs for NoopFs {
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64> {
+        status!("[dry-run] copy {:?} -> {:?}", from, to);
+        Ok(0)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        status!("[dry-run] create directory {:?}", path);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        status!("[dry-run] remove {:?}", path);
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        status!("[dry-run] remove directory {:?}", path);
+        Ok(())
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> std::io::Result<()> {
+        status!("[dry-run] symlink {:?} -> {:?}", link, target);
+        Ok(())
+    }
+
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        RealFs.read_link(path)
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> std::io::Result<()> {
+        status!("[dry-run] hard link {:?} -> {:?}", link, original);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        RealFs.read_dir(path)
+    }
+
+    fn set_permissions(&self, path: &Path, mode: u32) -> std::io::Result<()> {
+        status!("[dry-run] chmod {:o} {:?}", mode, path);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        RealFs.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        RealFs.is_dir(path)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        RealFs.is_symlink(path)
+    }
+}
+
+/// Give `to` the modification time of `from`
+fn copy_mtime(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(from)?);
+    filetime::set_file_mtime(to, mtime)
+}
+
+/// How a symlink met while copying a tree is reproduced
+enum LinkCopy {
+    /// A symlink with this target
+    Link(PathBuf),
+    /// A copy of the file the link leads to
+    Contents,
+}
+
+impl LinkCopy {
+    /// Decide how to copy the symlink `link` found below `root`
+    ///
+    /// See [`FileUtils::copy_dir_recursive`] for the rules.
+    fn classify(fs: &dyn Fs, root: &Path, link: &Path) -> Result<Self> {
+        let target = fs.read_link(link)
+            .with_context(|| format!("Failed to read symlink: {:?}", link))?;
+        let resolved = normalize_lexically(&link.parent().unwrap_or(Path::new("")).join(&target));
+        if target.is_relative() && resolved.starts_with(normalize_lexically(root)) {
+            return Ok(Self::Link(target));
+        }
+        if !fs.exists(link) {
+            warn!("Keeping dangling symlink {:?} -> {:?}", link, target);
+            return Ok(Self::Link(target));
+        }
+        if fs.is_dir(link) {
+            let absolute = std::path::absolute(&resolved)
+                .with_context(|| format!("Failed to resolve symlink: {:?}", link))?;
+            return Ok(Self::Link(absolute));
+        }
+        Ok(Self::Contents)
+    }
 }
 
 /// Utility functions for file operations
+///
+/// The plain methods operate on the real filesystem; the `*_with` variants
+/// take an explicit [`Fs`].
 pub struct FileUtils;
 
 impl FileUtils {
@@ -59,64 +553,203 @@ impl FileUtils {
     pub fn create_symlink<P: AsRef<Path>, Q: AsRef<Path>>(
         target: P,
         link: Q,
+    ) -> Result<()> {
+        Self::create_symlink_with(&RealFs, target, link)
+    }
+
+    /// Create a symbolic link on the given filesystem
+    pub fn create_symlink_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        target: P,
+        link: Q,
     ) -> Result<()> {
         let target = target.as_ref();
         let link = link.as_ref();
 
         // Remove existing link if it exists
-        if link.exists() || link.is_symlink() {
-            fs::remove_file(link)
+        if fs.exists(link) || fs.is_symlink(link) {
+            fs.remove_file(link)
                 .with_context(|| format!("Failed to remove existing link: {:?}", link))?;
         }
 
-        #[cfg(unix)]
-        std::os::unix::fs::symlink(target, link)
+        fs.symlink(target, link)
             .with_context(|| format!("Failed to create symlink: {:?} -> {:?}", link, target))?;
 
-        #[cfg(windows)]
-        std::os::windows::fs::symlink_file(target, link)
-            .with_context(|| format!("Failed to create symlink: {:?} -> {:?}", link, target))?;
+        Ok(())
+    }
+
+    /// Create a hard link, replacing any existing file or link at `link`
+    pub fn create_hardlink<P: AsRef<Path>, Q: AsRef<Path>>(
+        target: P,
+        link: Q,
+    ) -> Result<()> {
+        Self::create_hardlink_with(&RealFs, target, link)
+    }
+
+    /// Create a hard link on the given filesystem
+    pub fn create_hardlink_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        target: P,
+        link: Q,
+    ) -> Result<()> {
+        let target = target.as_ref();
+        let link = link.as_ref();
+
+        if fs.exists(link) || fs.is_symlink(link) {
+            fs.remove_file(link)
+                .with_context(|| format!("Failed to remove existing link: {:?}", link))?;
+        }
+
+        fs.hard_link(target, link)
+            .with_context(|| format!("Failed to create hard link: {:?} -> {:?}", link, target))?;
 
         Ok(())
     }
 
+    /// Create an alias `link` for `target`, a path relative to the link's directory
+    pub fn create_link<P: AsRef<Path>, Q: AsRef<Path>>(
+        mode: LinkMode,
+        target: P,
+        link: Q,
+    ) -> Result<()> {
+        Self::create_link_with(&RealFs, mode, target, link)
+    }
+
+    /// Create an alias on the given filesystem, replacing whatever is at `link`
+    pub fn create_link_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        mode: LinkMode,
+        target: P,
+        link: Q,
+    ) -> Result<()> {
+        let target = target.as_ref();
+        let link = link.as_ref();
+
+        if mode == LinkMode::Symlink {
+            return Self::create_symlink_with(fs, target, link);
+        }
+
+        let source = match link.parent() {
+            Some(dir) => dir.join(target),
+            None => target.to_path_buf(),
+        };
+
+        if mode == LinkMode::Hardlink {
+            match Self::create_hardlink_with(fs, &source, link) {
+                Ok(()) => return Ok(()),
+                Err(err) => warn!("{:#}; copying instead", err),
+            }
+        }
+
+        // Never copy through an old symlink onto its own target
+        if fs.exists(link) || fs.is_symlink(link) {
+            fs.remove_file(link)
+                .with_context(|| format!("Failed to remove existing link: {:?}", link))?;
+        }
+
+        Self::copy_file_with(fs, &source, link)
+    }
+
     /// Copy a file
     pub fn copy_file<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+        Self::copy_file_with(&RealFs, from, to)
+    }
+
+    /// Copy a file on the given filesystem
+    pub fn copy_file_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
         let from = from.as_ref();
         let to = to.as_ref();
 
-        fs::copy(from, to)
+        fs.copy(from, to)
             .with_context(|| format!("Failed to copy file: {:?} -> {:?}", from, to))?;
         Ok(())
     }
 
+    /// Copy a file and give the copy the source's modification time
+    pub fn copy_file_preserve_times<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+        Self::copy_file_with(&PreserveTimesFs, from, to)
+    }
+
     /// Copy a directory recursively
+    ///
+    /// Relative symlinks that stay inside `from` are recreated with the same
+    /// target rather than copied as the files they point to, so a theme's
+    /// aliases stay aliases. A link leading out of `from` would dangle in the
+    /// copy, so one to a file is copied as the file and one to a directory
+    /// gets an absolute target. Dangling links are kept, with a warning.
+    /// Symlinked directories are never descended into, so a link cycle can't
+    /// recurse forever.
     pub fn copy_dir_recursive<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
-        let from = from.as_ref();
-        let to = to.as_ref();
+        Self::copy_dir_recursive_with(&RealFs, from, to)
+    }
 
-        if !from.exists() {
+    /// Copy a directory recursively on the given filesystem
+    pub fn copy_dir_recursive_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
+        Self::copy_dir_recursive_filtered_with(fs, from, to, &|_| true)
+    }
+
+    /// Copy a directory recursively, skipping entries for which `keep` returns false
+    ///
+    /// `keep` sees each source path below `from`; rejecting a directory skips
+    /// everything inside it.
+    pub fn copy_dir_recursive_filtered<P: AsRef<Path>, Q: AsRef<Path>, F: Fn(&Path) -> bool>(
+        from: P,
+        to: Q,
+        keep: F,
+    ) -> Result<()> {
+        Self::copy_dir_recursive_filtered_with(&RealFs, from, to, &keep)
+    }
+
+    /// Copy a directory recursively on the given filesystem, keeping only entries accepted by `keep`
+    pub fn copy_dir_recursive_filtered_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        from: P,
+        to: Q,
+        keep: &dyn Fn(&Path) -> bool,
+    ) -> Result<()> {
+        Self::copy_tree_with(fs, from.as_ref(), from.as_ref(), to.as_ref(), keep)
+    }
+
+    /// Copy `from`, a directory at or below `root`, to `to`
+    fn copy_tree_with(fs: &dyn Fs, root: &Path, from: &Path, to: &Path, keep: &dyn Fn(&Path) -> bool) -> Result<()> {
+        if !fs.exists(from) {
             return Err(anyhow::anyhow!("Source directory does not exist: {:?}", from));
         }
 
-        fs::create_dir_all(to)
+        fs.create_dir_all(to)
             .with_context(|| format!("Failed to create destination directory: {:?}", to))?;
 
-        for entry in walkdir::WalkDir::new(from) {
-            let entry = entry?;
-            let path = entry.path();
-            let relative_path = path.strip_prefix(from)?;
-            let dest_path = to.join(relative_path);
+        for path in fs.read_dir(from)
+            .with_context(|| format!("Failed to read directory: {:?}", from))?
+        {
+            if !keep(&path) {
+                continue;
+            }
+            let file_name = path.file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid file name: {:?}", path))?;
+            let dest_path = to.join(file_name);
 
-            if path.is_dir() {
-                fs::create_dir_all(&dest_path)
-                    .with_context(|| format!("Failed to create directory: {:?}", dest_path))?;
-            } else {
-                if let Some(parent) = dest_path.parent() {
-                    fs::create_dir_all(parent)
-                        .with_context(|| format!("Failed to create parent directory: {:?}", parent))?;
+            if fs.is_symlink(&path) {
+                match LinkCopy::classify(fs, root, &path)? {
+                    LinkCopy::Link(target) => fs.symlink(&target, &dest_path)
+                        .with_context(|| format!("Failed to create symlink: {:?} -> {:?}", dest_path, target))?,
+                    LinkCopy::Contents => {
+                        fs.copy(&path, &dest_path)
+                            .with_context(|| format!("Failed to copy file: {:?} -> {:?}", path, dest_path))?;
+                    }
                 }
-                fs::copy(path, &dest_path)
+            } else if fs.is_dir(&path) {
+                Self::copy_tree_with(fs, root, &path, &dest_path, keep)?;
+            } else {
+                fs.copy(&path, &dest_path)
                     .with_context(|| format!("Failed to copy file: {:?} -> {:?}", path, dest_path))?;
             }
         }
@@ -124,48 +757,507 @@ impl FileUtils {
         Ok(())
     }
 
-    /// Set file permissions (Unix only)
-    #[cfg(unix)]
-    pub fn set_permissions_recursive<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
-        use std::os::unix::fs::PermissionsExt;
+    /// Mirror a directory: copy what changed and remove what is gone
+    ///
+    /// Files whose destination already has the same size and SHA-256 are
+    /// left alone, so their modification times don't change. Entries of `to`
+    /// with no counterpart in `from` are removed. Symlinks are handled as by
+    /// `copy_dir_recursive`, so aliases stay aliases and dangling links are kept.
+    pub fn copy_dir_recursive_sync<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+        Self::copy_dir_recursive_sync_filtered(from, to, |_| true)
+    }
 
-        let path = path.as_ref();
-        for entry in walkdir::WalkDir::new(path) {
-            let entry = entry?;
-            let permissions = fs::Permissions::from_mode(mode);
-            fs::set_permissions(entry.path(), permissions)
-                .with_context(|| format!("Failed to set permissions for: {:?}", entry.path()))?;
-        }
-        Ok(())
+    /// [`copy_dir_recursive_sync`](Self::copy_dir_recursive_sync), skipping entries rejected by `keep`
+    ///
+    /// Rejected entries count as gone, so they are removed from `to`.
+    pub fn copy_dir_recursive_sync_filtered<P: AsRef<Path>, Q: AsRef<Path>, F: Fn(&Path) -> bool>(
+        from: P,
+        to: Q,
+        keep: F,
+    ) -> Result<()> {
+        Self::copy_dir_recursive_sync_filtered_with(&RealFs, from, to, &keep)
     }
 
-    #[cfg(not(unix))]
-    pub fn set_permissions_recursive<P: AsRef<Path>>(_path: P, _mode: u32) -> Result<()> {
-        // No-op on non-Unix systems
-        Ok(())
+    /// [`copy_dir_recursive_sync_filtered`](Self::copy_dir_recursive_sync_filtered), copying and linking through `fs`
+    ///
+    /// The directories are still read and compared on disk.
+    pub fn copy_dir_recursive_sync_filtered_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        fs: &dyn Fs,
+        from: P,
+        to: Q,
+        keep: &dyn Fn(&Path) -> bool,
+    ) -> Result<()> {
+        Self::sync_dir(fs, from.as_ref(), from.as_ref(), to.as_ref(), keep)
     }
-}
 
-/// Utility functions for running external commands
-pub struct CommandUtils;
+    /// Mirror `from`, a directory at or below `root`, to `to`
+    fn sync_dir(copier: &dyn Fs, root: &Path, from: &Path, to: &Path, keep: &dyn Fn(&Path) -> bool) -> Result<()> {
+        if !from.exists() {
+            return Err(anyhow::anyhow!("Source directory does not exist: {:?}", from));
+        }
+        if to.is_symlink() || (to.exists() && !to.is_dir()) {
+            fs::remove_file(to)
+                .with_context(|| format!("Failed to remove {:?}", to))?;
+        }
+        fs::create_dir_all(to)
+            .with_context(|| format!("Failed to create destination directory: {:?}", to))?;
+
+        let mut wanted = std::collections::HashSet::new();
+        for entry in fs::read_dir(from)
+            .with_context(|| format!("Failed to read directory: {:?}", from))?
+        {
+            let path = entry?.path();
+            if !keep(&path) {
+                continue;
+            }
+            let file_name = path.file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid file name: {:?}", path))?;
+            wanted.insert(file_name.to_os_string());
+            let dest_path = to.join(file_name);
+
+            if path.is_symlink() {
+                Self::sync_link(copier, root, &path, &dest_path)?;
+            } else if path.is_dir() {
+                Self::sync_dir(copier, root, &path, &dest_path, keep)?;
+            } else {
+                Self::sync_file_with(copier, &path, &dest_path)?;
+            }
+        }
+
+        for entry in fs::read_dir(to)
+            .with_context(|| format!("Failed to read directory: {:?}", to))?
+        {
+            let path = entry?.path();
+            if path.file_name().is_some_and(|name| wanted.contains(name)) {
+                continue;
+            }
+            debug!("removed: {:?}", path);
+            Self::remove_path(&path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copy a file unless `to` already has the same contents
+    ///
+    /// Returns whether the file was copied.
+    pub fn sync_file<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<bool> {
+        Self::sync_file_with(&RealFs, from, to)
+    }
+
+    /// [`sync_file`](Self::sync_file), copying through `fs`
+    pub fn sync_file_with<P: AsRef<Path>, Q: AsRef<Path>>(fs: &dyn Fs, from: P, to: Q) -> Result<bool> {
+        let from = from.as_ref();
+        let to = to.as_ref();
+
+        if to.is_file() && !to.is_symlink() && Self::same_contents(from, to)? {
+            debug!("unchanged: {:?}", to);
+            return Ok(false);
+        }
+        if to.is_symlink() || to.exists() {
+            Self::remove_path(to)?;
+        }
+        fs.copy(from, to)
+            .with_context(|| format!("Failed to copy file: {:?} -> {:?}", from, to))?;
+        Ok(true)
+    }
+
+    /// Mirror the symlink `from` found below `root` at `to`
+    ///
+    /// A link recreated as a link is left alone when `to` already has its
+    /// target; one copied as contents goes through [`sync_file`](Self::sync_file).
+    fn sync_link(copier: &dyn Fs, root: &Path, from: &Path, to: &Path) -> Result<()> {
+        let target = match LinkCopy::classify(&RealFs, root, from)? {
+            LinkCopy::Link(target) => target,
+            LinkCopy::Contents => return Self::sync_file_with(copier, from, to).map(|_| ()),
+        };
+        if to.is_symlink() && fs::read_link(to)? == target {
+            debug!("unchanged: {:?}", to);
+            return Ok(());
+        }
+        if to.is_symlink() || to.exists() {
+            Self::remove_path(to)?;
+        }
+        copier.symlink(&target, to)
+            .with_context(|| format!("Failed to create symlink: {:?} -> {:?}", to, target))
+    }
+
+    /// Whether two files have the same size and SHA-256
+    fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+        use sha2::{Digest, Sha256};
+
+        if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+            return Ok(false);
+        }
+        let hash = |path: &Path| -> Result<_> {
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+            Ok(hasher.finalize())
+        };
+        Ok(hash(a)? == hash(b)?)
+    }
+
+    /// Remove a file, symlink or directory tree
+    pub(crate) fn remove_path(path: &Path) -> Result<()> {
+        let removed = if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        removed.with_context(|| format!("Failed to remove {:?}", path))
+    }
+
+    /// Copy a directory recursively, copying files on up to `jobs` threads
+    ///
+    /// The directory tree is created up front so worker threads never race
+    /// on directory creation. Symlinks are handled as by `copy_dir_recursive`.
+    pub fn copy_dir_recursive_parallel<P: AsRef<Path>, Q: AsRef<Path>>(
+        from: P,
+        to: Q,
+        jobs: usize,
+    ) -> Result<()> {
+        Self::copy_dir_recursive_parallel_filtered(from, to, jobs, |_| true)
+    }
+
+    /// [`copy_dir_recursive_parallel`](Self::copy_dir_recursive_parallel), skipping entries rejected by `keep`
+    pub fn copy_dir_recursive_parallel_filtered<P: AsRef<Path>, Q: AsRef<Path>, F: Fn(&Path) -> bool>(
+        from: P,
+        to: Q,
+        jobs: usize,
+        keep: F,
+    ) -> Result<()> {
+        Self::copy_dir_recursive_parallel_filtered_with(&RealFs, from, to, jobs, keep)
+    }
+
+    /// [`copy_dir_recursive_parallel_filtered`](Self::copy_dir_recursive_parallel_filtered), writing through `fs`
+    ///
+    /// The source tree is still walked on disk.
+    pub fn copy_dir_recursive_parallel_filtered_with<P: AsRef<Path>, Q: AsRef<Path>, F: Fn(&Path) -> bool>(
+        fs: &(dyn Fs + Sync),
+        from: P,
+        to: Q,
+        jobs: usize,
+        keep: F,
+    ) -> Result<()> {
+        use rayon::prelude::*;
+
+        let from = from.as_ref();
+        let to = to.as_ref();
+
+        if !from.exists() {
+            return Err(anyhow::anyhow!("Source directory does not exist: {:?}", from));
+        }
+
+        let mut files = Vec::new();
+        let walker = walkdir::WalkDir::new(from)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || keep(entry.path()));
+        for entry in walker {
+            let entry = entry?;
+            let path = entry.path();
+            let dest_path = to.join(path.strip_prefix(from)?);
+
+            if entry.path_is_symlink() && entry.depth() > 0 {
+                match LinkCopy::classify(&RealFs, from, path)? {
+                    LinkCopy::Link(target) => fs.symlink(&target, &dest_path)
+                        .with_context(|| format!("Failed to create symlink: {:?} -> {:?}", dest_path, target))?,
+                    LinkCopy::Contents => files.push((path.to_path_buf(), dest_path)),
+                }
+            } else if path.is_dir() {
+                fs.create_dir_all(&dest_path)
+                    .with_context(|| format!("Failed to create directory: {:?}", dest_path))?;
+            } else {
+                files.push((path.to_path_buf(), dest_path));
+            }
+        }
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.max(1))
+            .build()
+            .context("Failed to create copy thread pool")?;
+
+        pool.install(|| {
+            files.par_iter().try_for_each(|(src, dest)| {
+                fs.copy(src, dest)
+                    .map(|_| ())
+                    .with_context(|| format!("Failed to copy file: {:?} -> {:?}", src, dest))
+            })
+        })
+    }
+
+    /// Copy a directory recursively, in parallel when more than one job is requested
+    pub fn copy_dir_with_jobs<P: AsRef<Path>, Q: AsRef<Path>>(
+        from: P,
+        to: Q,
+        jobs: Option<usize>,
+    ) -> Result<()> {
+        Self::copy_dir_with_jobs_filtered(from, to, jobs, |_| true)
+    }
+
+    /// Copy a directory like [`copy_dir_with_jobs`](Self::copy_dir_with_jobs), keeping only entries accepted by `keep`
+    pub fn copy_dir_with_jobs_filtered<P: AsRef<Path>, Q: AsRef<Path>, F: Fn(&Path) -> bool>(
+        from: P,
+        to: Q,
+        jobs: Option<usize>,
+        keep: F,
+    ) -> Result<()> {
+        Self::copy_dir_with_jobs_filtered_with(&RealFs, from, to, jobs, keep)
+    }
+
+    /// [`copy_dir_with_jobs_filtered`](Self::copy_dir_with_jobs_filtered), writing through `fs`
+    pub fn copy_dir_with_jobs_filtered_with<P: AsRef<Path>, Q: AsRef<Path>, F: Fn(&Path) -> bool>(
+        fs: &(dyn Fs + Sync),
+        from: P,
+        to: Q,
+        jobs: Option<usize>,
+        keep: F,
+    ) -> Result<()> {
+        match jobs {
+            Some(jobs) if jobs > 1 => Self::copy_dir_recursive_parallel_filtered_with(fs, from, to, jobs, keep),
+            _ => Self::copy_dir_recursive_filtered_with(fs, from, to, &keep),
+        }
+    }
+
+    /// Set file permissions (Unix only)
+    pub fn set_permissions_recursive<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
+        Self::set_permissions_recursive_with(&RealFs, path, mode)
+    }
+
+    /// Set file permissions recursively on the given filesystem
+    pub fn set_permissions_recursive_with<P: AsRef<Path>>(
+        fs: &dyn Fs,
+        path: P,
+        mode: u32,
+    ) -> Result<()> {
+        let path = path.as_ref();
+
+        fs.set_permissions(path, mode)
+            .with_context(|| format!("Failed to set permissions for: {:?}", path))?;
+
+        if fs.is_dir(path) && !fs.is_symlink(path) {
+            for child in fs.read_dir(path)? {
+                Self::set_permissions_recursive_with(fs, &child, mode)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wait before the first retry of a failed command
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Most characters of a command's stderr logged after it exits
+const STDERR_SNIPPET_LEN: usize = 200;
+
+/// A command and its arguments as one shell-like line
+///
+/// Arguments that are empty or contain whitespace are quoted.
+pub fn command_line(command: &str, args: &[&str]) -> String {
+    let mut line = command.to_string();
+    for arg in args {
+        line.push(' ');
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            line.push_str(&format!("{:?}", arg));
+        } else {
+            line.push_str(arg);
+        }
+    }
+    line
+}
+
+/// The start of a command's stderr, on one line
+fn stderr_snippet(stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let snippet: String = stderr.split_whitespace().collect::<Vec<_>>().join(" ");
+    match snippet.char_indices().nth(STDERR_SNIPPET_LEN) {
+        Some((end, _)) => format!("{}...", &snippet[..end]),
+        None => snippet,
+    }
+}
+
+/// Utility functions for running external commands
+pub struct CommandUtils;
 
 impl CommandUtils {
     /// Run a command and return success/failure
     pub fn run_command(command: &str, args: &[&str]) -> Result<()> {
-        let output = Command::new(command)
-            .args(args)
-            .output()
-            .with_context(|| format!("Failed to execute command: {}", command))?;
+        Self::run_command_output(command, args).map(|_| ())
+    }
+
+    /// Run a command and return its trimmed stdout
+    ///
+    /// A non-zero exit is reported as `CursorError::CommandFailed` with the
+    /// command's stderr.
+    pub fn run_command_output(command: &str, args: &[&str]) -> Result<String> {
+        Self::execute(command, args, None, None)
+    }
+
+    /// Run a command, killing it if it runs longer than `dur`
+    ///
+    /// An expired deadline is reported as `CursorError::CommandTimeout`.
+    pub fn run_command_timeout(command: &str, args: &[&str], dur: Duration) -> Result<()> {
+        Self::execute(command, args, None, Some(dur)).map(|_| ())
+    }
+
+    /// Run a command with an optional timeout and return its trimmed stdout
+    pub fn run_command_output_timeout(
+        command: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+    ) -> Result<String> {
+        Self::execute(command, args, None, timeout)
+    }
+
+    /// Run a command in the given working directory with an optional timeout
+    pub fn run_command_in_dir(
+        dir: &Path,
+        command: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+    ) -> Result<String> {
+        Self::execute(command, args, Some(dir), timeout)
+    }
+
+    /// Run a command up to `attempts` times until it exits successfully
+    ///
+    /// See [`CommandUtils::retry`].
+    pub fn run_command_retry(command: &str, args: &[&str], attempts: u32) -> Result<()> {
+        Self::retry(command, attempts, || Self::run_command(command, args))
+    }
+
+    /// Call `run` up to `attempts` times while it fails with a non-zero exit
+    ///
+    /// Waits 100ms before the first retry and doubles the wait each time.
+    /// Other errors (a missing command, a timeout) are returned at once, as is
+    /// the last failure once the attempts are used up. `attempts` of 0 or 1
+    /// runs once.
+    pub fn retry<T>(command: &str, attempts: u32, mut run: impl FnMut() -> Result<T>) -> Result<T> {
+        let attempts = attempts.max(1);
+        let mut backoff = RETRY_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let err = match run() {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            let failed = matches!(err.downcast_ref::<CursorError>(), Some(CursorError::CommandFailed { .. }));
+            if !failed || attempt >= attempts {
+                return Err(err);
+            }
+            warn!(
+                "{} failed (attempt {}/{}), retrying in {}ms",
+                command,
+                attempt,
+                attempts,
+                backoff.as_millis()
+            );
+            std::thread::sleep(backoff);
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    fn execute(
+        command: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+        timeout: Option<Duration>,
+    ) -> Result<String> {
+        let line = command_line(command, args);
+        let level = if print_commands() { Level::Info } else { Level::Debug };
+        match dir {
+            Some(dir) => log!(level, "Running: {} (in {:?})", line, dir),
+            None => log!(level, "Running: {}", line),
+        }
+
+        let mut cmd = Command::new(command);
+        cmd.args(args);
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
+        }
+
+        // A command that isn't installed is a missing dependency, not a failure;
+        // a missing working directory fails the spawn the same way
+        let io_error = |err: std::io::Error| -> anyhow::Error {
+            if err.kind() == std::io::ErrorKind::NotFound && dir.is_none_or(Path::is_dir) {
+                CursorError::MissingDependency(command.to_string()).into()
+            } else {
+                anyhow::Error::new(err).context(format!("Failed to execute command: {}", command))
+            }
+        };
+        let output = match timeout {
+            None => cmd.output().map_err(io_error)?,
+            Some(timeout) => Self::output_with_deadline(cmd, timeout)
+                .map_err(|err| match err {
+                    DeadlineError::Io(err) => io_error(err),
+                    DeadlineError::Expired => CursorError::CommandTimeout {
+                        command: line.clone(),
+                        timeout,
+                    }.into(),
+                })?,
+        };
+
+        let snippet = stderr_snippet(&output.stderr);
+        if snippet.is_empty() {
+            debug!("{} exited with {}", command, output.status);
+        } else {
+            debug!("{} exited with {}: {}", command, output.status, snippet);
+        }
 
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(CursorError::CommandFailed {
-                command: format!("{} {}", command, args.join(" ")),
+                command: line,
                 error: error.to_string(),
             }.into());
         }
 
-        Ok(())
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Spawn a command and collect its output, killing it once the deadline passes
+    fn output_with_deadline(mut cmd: Command, timeout: Duration) -> Result<Output, DeadlineError> {
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(DeadlineError::Io)?;
+
+        // Drain the pipes on separate threads so a chatty child can't block on a full pipe
+        let stdout = child.stdout.take().map(Self::drain);
+        let stderr = child.stderr.take().map(Self::drain);
+
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait().map_err(DeadlineError::Io)? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(DeadlineError::Expired);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        let collect = |handle: Option<std::thread::JoinHandle<Vec<u8>>>| {
+            handle.and_then(|h| h.join().ok()).unwrap_or_default()
+        };
+
+        Ok(Output {
+            status,
+            stdout: collect(stdout),
+            stderr: collect(stderr),
+        })
+    }
+
+    fn drain<R: Read + Send + 'static>(mut reader: R) -> std::thread::JoinHandle<Vec<u8>> {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = reader.read_to_end(&mut buf);
+            buf
+        })
     }
 
     /// Check if a command is available
@@ -178,13 +1270,926 @@ impl CommandUtils {
     }
 }
 
+enum DeadlineError {
+    Io(std::io::Error),
+    Expired,
+}
+
+/// Validate a theme name before it is used in paths or theme files
+///
+/// Rejects empty names, path separators, `..`, control characters (which
+/// could inject lines into `index.theme`) and leading dots. Surrounding
+/// whitespace is trimmed.
+pub fn sanitize_theme_name(name: &str) -> Result<String> {
+    let invalid = |reason: String| -> anyhow::Error {
+        CursorError::InvalidThemeName { name: name.to_string(), reason }.into()
+    };
+
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err(invalid("name is empty".to_string()));
+    }
+    if let Some(c) = trimmed.chars().find(|c| c.is_control()) {
+        return Err(invalid(format!("contains control character {:?}", c)));
+    }
+    if let Some(c) = trimmed.chars().find(|c| matches!(c, '/' | '\\')) {
+        return Err(invalid(format!("contains path separator '{}'", c)));
+    }
+    if trimmed.contains("..") {
+        return Err(invalid("contains '..'".to_string()));
+    }
+    if trimmed.starts_with('.') {
+        return Err(invalid("starts with '.'".to_string()));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+/// Validate a display name written as `Name=` and the manifest `name`
+///
+/// Unlike [`sanitize_theme_name`] the name never becomes a path, so spaces,
+/// slashes and dots are fine; it must not be empty or contain control
+/// characters. Surrounding whitespace is trimmed.
+pub fn sanitize_display_name(name: &str) -> Result<String> {
+    let invalid = |reason: String| -> anyhow::Error {
+        CursorError::InvalidThemeName { name: name.to_string(), reason }.into()
+    };
+
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err(invalid("display name is empty".to_string()));
+    }
+    if let Some(c) = trimmed.chars().find(|c| c.is_control()) {
+        return Err(invalid(format!("contains control character {:?}", c)));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+/// Resolve `.` and `..` components without touching the filesystem
+///
+/// `..` at the root stays at the root; leading `..` of a relative path are kept.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::ParentDir => match normalized.components().next_back() {
+                Some(std::path::Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(std::path::Component::RootDir | std::path::Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            std::path::Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Ensure `candidate` resolves to a path strictly inside `base`
+///
+/// The candidate does not need to exist yet: its deepest existing ancestor
+/// is canonicalized (resolving symlinks) and the remaining components are
+/// applied lexically, so `..` segments and symlinked parents can't escape.
+pub fn ensure_within(base: &Path, candidate: &Path) -> Result<()> {
+    let canonical_base = base.canonicalize()
+        .with_context(|| format!("Failed to resolve directory: {:?}", base))?;
+
+    let mut existing = candidate;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            // `..` or root components: resolve what we have lexically
+            _ => break,
+        }
+    }
+
+    let mut resolved = if existing.as_os_str().is_empty() {
+        std::env::current_dir()?
+    } else if existing.exists() {
+        existing.canonicalize()?
+    } else {
+        existing.to_path_buf()
+    };
+
+    for component in rest.iter().rev() {
+        resolved.push(component);
+    }
+
+    let normalized = normalize_lexically(&resolved);
+    if normalized == canonical_base || !normalized.starts_with(&canonical_base) {
+        return Err(CursorError::PathOutsideBase {
+            base: base.to_path_buf(),
+            path: candidate.to_path_buf(),
+        }.into());
+    }
+
+    Ok(())
+}
+
 /// Get the user's home directory
 pub fn get_home_dir() -> Result<PathBuf> {
     home::home_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))
 }
 
+/// Environment variable that redirects the icons directory
+pub const ICONS_DIR_ENV: &str = "KOOSH_ICONS_DIR";
+
+static ICONS_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Override the icons directory for the rest of the process (the `--icons-dir` flag)
+///
+/// Only the first call takes effect.
+pub fn set_icons_dir_override(path: PathBuf) {
+    let _ = ICONS_DIR_OVERRIDE.set(path);
+}
+
 /// Get the user's .icons directory
+///
+/// Resolution order:
+/// 1. the `KOOSH_ICONS_DIR` environment variable, when set and non-empty
+/// 2. the `--icons-dir` override from [`set_icons_dir_override`]
+/// 3. `~/.icons`
 pub fn get_icons_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(ICONS_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = ICONS_DIR_OVERRIDE.get() {
+        return Ok(dir.clone());
+    }
     Ok(get_home_dir()?.join(".icons"))
 }
+
+/// Environment variable that redirects the system icons directory
+pub const SYSTEM_ICONS_DIR_ENV: &str = "KOOSH_SYSTEM_ICONS_DIR";
+
+/// Get the system-wide icons directory
+///
+/// `KOOSH_SYSTEM_ICONS_DIR` when set and non-empty, otherwise `/usr/share/icons`.
+pub fn get_system_icons_dir() -> PathBuf {
+    std::env::var_os(SYSTEM_ICONS_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/usr/share/icons"))
+}
+
+/// Get the base directory for temporary working files
+///
+/// Uses the given override when present, otherwise the system temp
+/// directory (which honors `$TMPDIR`).
+pub fn get_temp_dir(override_dir: Option<&Path>) -> PathBuf {
+    override_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Create one run's scratch directory under the temp base
+///
+/// The directory is `<base>/<name>.<random>`, so concurrent runs never share
+/// one; it is removed when the returned guard is dropped, on error paths too.
+pub fn create_scratch_dir(override_dir: Option<&Path>, name: &str) -> Result<tempfile::TempDir> {
+    let base = get_temp_dir(override_dir);
+    fs::create_dir_all(&base)
+        .with_context(|| format!("Failed to create temp directory: {:?}", base))?;
+    tempfile::Builder::new()
+        .prefix(&format!("{}.", name))
+        .tempdir_in(&base)
+        .with_context(|| format!("Failed to create scratch directory in {:?}", base))
+}
+
+/// Get the base directory project-local themes are built in
+///
+/// Uses the given override (the `--theme-dir` flag) when present, otherwise
+/// the current directory.
+pub fn get_theme_dir(override_dir: Option<&Path>) -> Result<PathBuf> {
+    match override_dir {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => std::env::current_dir().context("Failed to get current directory"),
+    }
+}
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Switch the process to machine-readable output (the `--json` flag)
+///
+/// Human progress messages printed through [`status!`] are suppressed so
+/// stdout carries only the final JSON report.
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--json` output is active
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+static VERBOSE_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Show per-cursor and per-frame detail (the `-v` flag)
+pub fn set_verbose_output(enabled: bool) {
+    VERBOSE_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `-v` detail output is active
+pub fn verbose_output() -> bool {
+    VERBOSE_OUTPUT.load(Ordering::Relaxed)
+}
+
+static PRINT_COMMANDS: AtomicBool = AtomicBool::new(false);
+
+/// Log every external command at info level (the `--print-commands` flag)
+///
+/// They are logged at debug level otherwise.
+pub fn set_print_commands(enabled: bool) {
+    PRINT_COMMANDS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--print-commands` is active
+pub fn print_commands() -> bool {
+    PRINT_COMMANDS.load(Ordering::Relaxed)
+}
+
+static SYNC_INSTALLS: AtomicBool = AtomicBool::new(false);
+
+/// Update installed themes in place, skipping unchanged files (the `--sync` flag)
+pub fn set_sync_installs(enabled: bool) {
+    SYNC_INSTALLS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--sync` is active
+pub fn sync_installs() -> bool {
+    SYNC_INSTALLS.load(Ordering::Relaxed)
+}
+
+static VERIFY_INSTALLS: AtomicBool = AtomicBool::new(false);
+
+/// Re-read installed themes and compare them with the build (the `--verify-install` flag)
+pub fn set_verify_installs(enabled: bool) {
+    VERIFY_INSTALLS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--verify-install` is active
+pub fn verify_installs() -> bool {
+    VERIFY_INSTALLS.load(Ordering::Relaxed)
+}
+
+static ACTIVE_PROGRESS: Mutex<Option<indicatif::MultiProgress>> = Mutex::new(None);
+
+/// Keeps [`status!`] lines from tearing progress bars while it is alive
+pub struct ProgressGuard(());
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_PROGRESS.lock() {
+            *active = None;
+        }
+    }
+}
+
+/// Print status lines with `progress`'s bars cleared, redrawing them after
+pub fn show_progress(progress: &indicatif::MultiProgress) -> ProgressGuard {
+    if let Ok(mut active) = ACTIVE_PROGRESS.lock() {
+        *active = Some(progress.clone());
+    }
+    ProgressGuard(())
+}
+
+/// Print one status line, around the progress bars being drawn if any
+#[doc(hidden)]
+pub fn print_status(args: std::fmt::Arguments) {
+    let progress = ACTIVE_PROGRESS.lock().ok().and_then(|active| active.clone());
+    match progress {
+        Some(progress) => progress.suspend(|| println!("{}", args)),
+        None => println!("{}", args),
+    }
+}
+
+/// `println!` for human progress messages; silent in `--json` mode
+#[macro_export]
+macro_rules! status {
+    () => {
+        $crate::status!("")
+    };
+    ($($arg:tt)*) => {
+        if !$crate::json_output() {
+            $crate::print_status(format_args!($($arg)*));
+        }
+    };
+}
+
+/// [`status!`] for detail lines only shown with `-v`
+#[macro_export]
+macro_rules! detail {
+    ($($arg:tt)*) => {
+        if $crate::verbose_output() {
+            $crate::status!($($arg)*);
+        }
+    };
+}
+
+/// Placeholder in [`ICON_CACHE_TOOLS`] arguments replaced by the theme directory
+const THEME_DIR_ARG: &str = "{theme_dir}";
+
+/// Icon cache refresh tools, tried in order when installed
+///
+/// The GTK tools rebuild the theme's own cache; the xdg and KDE tools ask the
+/// desktop to reload its icon/cursor caches globally.
+const ICON_CACHE_TOOLS: &[(&str, &[&str])] = &[
+    ("gtk-update-icon-cache", &["-f", "-t", THEME_DIR_ARG]),
+    ("gtk4-update-icon-cache", &["-f", "-t", THEME_DIR_ARG]),
+    ("xdg-icon-resource", &["forceupdate"]),
+    ("kbuildsycoca6", &[]),
+    ("kbuildsycoca5", &[]),
+];
+
+/// Refresh desktop icon caches for an installed theme
+///
+/// Runs every available tool from [`ICON_CACHE_TOOLS`] and logs the ones that
+/// succeeded. This is optional, so missing tools, failures and tools killed
+/// after `timeout` are ignored.
+pub fn update_icon_caches(theme_dir: &Path, timeout: Option<Duration>) {
+    let theme_dir = theme_dir.to_string_lossy();
+    for (tool, args) in ICON_CACHE_TOOLS {
+        if !CommandUtils::command_exists(tool) {
+            continue;
+        }
+        let args: Vec<&str> = args
+            .iter()
+            .map(|arg| if *arg == THEME_DIR_ARG { theme_dir.as_ref() } else { *arg })
+            .collect();
+        match CommandUtils::run_command_output_timeout(tool, &args, timeout) {
+            Ok(_) => status!("Updated icon cache with {}", tool),
+            Err(e) => detail!("{} failed (ignored): {:#}", tool, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Node {
+        Dir,
+        File(Vec<u8>),
+        Link(PathBuf),
+    }
+
+    /// In-memory filesystem for exercising `FileUtils` without touching disk
+    #[derive(Default)]
+    struct MemFs {
+        nodes: RefCell<BTreeMap<PathBuf, Node>>,
+    }
+
+    impl MemFs {
+        fn add_file(&self, path: &str, contents: &[u8]) {
+            self.nodes.borrow_mut().insert(PathBuf::from(path), Node::File(contents.to_vec()));
+        }
+
+        fn add_dir(&self, path: &str) {
+            self.nodes.borrow_mut().insert(PathBuf::from(path), Node::Dir);
+        }
+
+        fn node(&self, path: &str) -> Option<Node> {
+            self.nodes.borrow().get(Path::new(path)).cloned()
+        }
+
+        fn resolve(&self, path: &Path) -> Option<Node> {
+            match self.nodes.borrow().get(path).cloned() {
+                Some(Node::Link(target)) => {
+                    let target = normalize_lexically(&path.parent().unwrap_or(Path::new("")).join(target));
+                    self.resolve(&target)
+                }
+                other => other,
+            }
+        }
+    }
+
+    fn not_found(path: &Path) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::NotFound, format!("{:?}", path))
+    }
+
+    impl Fs for MemFs {
+        fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64> {
+            match self.resolve(from) {
+                Some(Node::File(data)) => {
+                    let len = data.len() as u64;
+                    self.nodes.borrow_mut().insert(to.to_path_buf(), Node::File(data));
+                    Ok(len)
+                }
+                _ => Err(not_found(from)),
+            }
+        }
+
+        fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+            for ancestor in path.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+                self.nodes.borrow_mut().entry(ancestor.to_path_buf()).or_insert(Node::Dir);
+            }
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            self.nodes.borrow_mut().remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+            self.nodes.borrow_mut().retain(|p, _| !p.starts_with(path));
+            Ok(())
+        }
+
+        fn symlink(&self, target: &Path, link: &Path) -> std::io::Result<()> {
+            self.nodes.borrow_mut().insert(link.to_path_buf(), Node::Link(target.to_path_buf()));
+            Ok(())
+        }
+
+        fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+            match self.nodes.borrow().get(path) {
+                Some(Node::Link(target)) => Ok(target.clone()),
+                _ => Err(not_found(path)),
+            }
+        }
+
+        fn hard_link(&self, _original: &Path, _link: &Path) -> std::io::Result<()> {
+            Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "MemFs has no inodes"))
+        }
+
+        fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+            Ok(self.nodes.borrow().keys()
+                .filter(|p| p.parent() == Some(path))
+                .cloned()
+                .collect())
+        }
+
+        fn set_permissions(&self, _path: &Path, _mode: u32) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.resolve(path).is_some()
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.resolve(path) == Some(Node::Dir)
+        }
+
+        fn is_symlink(&self, path: &Path) -> bool {
+            matches!(self.nodes.borrow().get(path), Some(Node::Link(_)))
+        }
+    }
+
+    #[test]
+    fn copy_file_preserve_times_keeps_the_source_mtime() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let from = dir.path().join("left_ptr");
+        let to = dir.path().join("copy");
+        fs::write(&from, b"cursor").unwrap();
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&from, old).unwrap();
+
+        FileUtils::copy_file_preserve_times(&from, &to).unwrap();
+
+        assert_eq!(fs::read(&to).unwrap(), b"cursor");
+        assert_eq!(filetime::FileTime::from_last_modification_time(&fs::metadata(&to).unwrap()), old);
+    }
+
+    #[test]
+    fn load_reads_name_and_lists_cursors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme_path = dir.path().join("on-disk-name");
+        fs::create_dir_all(theme_path.join("cursors")).unwrap();
+        fs::write(theme_path.join("index.theme"), "[Icon Theme]\nName=Display Name\n").unwrap();
+        fs::write(theme_path.join("cursors/left_ptr"), b"1234").unwrap();
+        FileUtils::create_symlink("left_ptr", theme_path.join("cursors/arrow")).unwrap();
+
+        let theme = CursorTheme::load(&theme_path).unwrap();
+        assert_eq!(theme.name, "Display Name");
+        assert_eq!(theme.cursors().unwrap(), vec![
+            CursorEntry { name: "arrow".into(), kind: CursorKind::Symlink { target: "left_ptr".into() } },
+            CursorEntry { name: "left_ptr".into(), kind: CursorKind::File { size: 4 } },
+        ]);
+
+        fs::remove_file(theme_path.join("index.theme")).unwrap();
+        assert_eq!(CursorTheme::load(&theme_path).unwrap().name, "on-disk-name");
+        assert!(CursorTheme::load(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn load_reads_the_cursors_directory_from_the_manifest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme_path = dir.path().join("Hypr");
+        fs::create_dir_all(theme_path.join("shapes")).unwrap();
+        fs::write(theme_path.join("manifest.hl"), "name = Hypr\ncursors_directory = shapes\n").unwrap();
+        assert_eq!(CursorTheme::load(&theme_path).unwrap().cursors_dir, theme_path.join("shapes"));
+
+        // X11 cursors next to the hyprcursor shapes win
+        fs::create_dir_all(theme_path.join("cursors")).unwrap();
+        assert_eq!(CursorTheme::load(&theme_path).unwrap().cursors_dir, theme_path.join("cursors"));
+
+        fs::remove_dir(theme_path.join("cursors")).unwrap();
+        fs::write(theme_path.join("manifest.hl"), "cursors_directory = ../elsewhere\n").unwrap();
+        assert!(CursorTheme::load(&theme_path).is_err());
+    }
+
+    #[test]
+    fn copy_dir_recursive_copies_nested_files() {
+        let fs = MemFs::default();
+        fs.add_dir("src");
+        fs.add_dir("src/cursors");
+        fs.add_file("src/index.theme", b"[Icon Theme]");
+        fs.add_file("src/cursors/left_ptr", b"xcursor");
+
+        FileUtils::copy_dir_recursive_with(&fs, "src", "dst").unwrap();
+
+        assert_eq!(fs.node("dst/index.theme"), Some(Node::File(b"[Icon Theme]".to_vec())));
+        assert_eq!(fs.node("dst/cursors/left_ptr"), Some(Node::File(b"xcursor".to_vec())));
+    }
+
+    #[test]
+    fn copy_dir_recursive_keeps_symlinks() {
+        let fs = MemFs::default();
+        fs.add_dir("src");
+        fs.add_file("src/left_ptr", b"xcursor");
+        fs.symlink(Path::new("left_ptr"), Path::new("src/default")).unwrap();
+
+        FileUtils::copy_dir_recursive_with(&fs, "src", "dst").unwrap();
+
+        assert_eq!(fs.node("dst/default"), Some(Node::Link(PathBuf::from("left_ptr"))));
+    }
+
+    #[test]
+    fn copy_dir_recursive_does_not_follow_directory_link_cycles() {
+        let fs = MemFs::default();
+        fs.add_dir("src");
+        fs.add_dir("src/cursors");
+        fs.add_file("src/cursors/left_ptr", b"xcursor");
+        fs.symlink(Path::new(".."), Path::new("src/cursors/loop")).unwrap();
+
+        FileUtils::copy_dir_recursive_with(&fs, "src", "dst").unwrap();
+
+        assert_eq!(fs.node("dst/cursors/loop"), Some(Node::Link(PathBuf::from(".."))));
+        assert_eq!(fs.node("dst/cursors/loop/cursors"), None);
+    }
+
+    #[test]
+    fn copy_dir_recursive_copies_files_linked_from_outside() {
+        let fs = MemFs::default();
+        fs.add_dir("shared");
+        fs.add_file("shared/arrow", b"xcursor");
+        fs.add_dir("src");
+        fs.symlink(Path::new("../shared/arrow"), Path::new("src/default")).unwrap();
+
+        FileUtils::copy_dir_recursive_with(&fs, "src", "dst").unwrap();
+
+        assert_eq!(fs.node("dst/default"), Some(Node::File(b"xcursor".to_vec())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_keep_only_links_that_resolve_inside_the_tree() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shared = dir.path().join("shared");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("arrow"), b"arrow").unwrap();
+        let src = dir.path().join("build/src");
+        fs::create_dir_all(src.join("cursors")).unwrap();
+        fs::write(src.join("cursors/left_ptr"), b"left_ptr").unwrap();
+        std::os::unix::fs::symlink("left_ptr", src.join("cursors/default")).unwrap();
+        std::os::unix::fs::symlink("../cursors/left_ptr", src.join("cursors/arrow")).unwrap();
+        std::os::unix::fs::symlink("../../../shared/arrow", src.join("cursors/outside")).unwrap();
+        std::os::unix::fs::symlink("../../shared", src.join("shared")).unwrap();
+        std::os::unix::fs::symlink("../missing", src.join("cursors/dangling")).unwrap();
+
+        for name in ["serial", "parallel", "sync"] {
+            let dst = dir.path().join(name);
+            match name {
+                "serial" => FileUtils::copy_dir_recursive(&src, &dst),
+                "parallel" => FileUtils::copy_dir_recursive_parallel(&src, &dst, 4),
+                _ => FileUtils::copy_dir_recursive_sync(&src, &dst),
+            }
+            .unwrap();
+
+            assert_eq!(fs::read_link(dst.join("cursors/default")).unwrap(), Path::new("left_ptr"), "{}", name);
+            assert_eq!(fs::read_link(dst.join("cursors/arrow")).unwrap(), Path::new("../cursors/left_ptr"), "{}", name);
+            assert!(!dst.join("cursors/outside").is_symlink(), "{}", name);
+            assert_eq!(fs::read(dst.join("cursors/outside")).unwrap(), b"arrow", "{}", name);
+            assert_eq!(fs::read_link(dst.join("shared")).unwrap(), shared, "{}", name);
+            assert_eq!(fs::read_link(dst.join("cursors/dangling")).unwrap(), Path::new("../missing"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn copy_dir_recursive_filtered_skips_rejected_subtrees() {
+        let fs = MemFs::default();
+        fs.add_dir("src");
+        fs.add_dir("src/.git");
+        fs.add_dir("src/.git/objects");
+        fs.add_file("src/.git/objects/pack", b"git");
+        fs.add_file("src/left_ptr", b"xcursor");
+        fs.add_file("src/left_ptr.bak", b"old");
+
+        let keep = |path: &Path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            !name.starts_with('.') && !name.ends_with(".bak")
+        };
+        FileUtils::copy_dir_recursive_filtered_with(&fs, "src", "dst", &keep).unwrap();
+
+        assert_eq!(fs.node("dst/left_ptr"), Some(Node::File(b"xcursor".to_vec())));
+        assert_eq!(fs.node("dst/left_ptr.bak"), None);
+        assert_eq!(fs.node("dst/.git"), None);
+        assert_eq!(fs.node("dst/.git/objects/pack"), None);
+    }
+
+    #[test]
+    fn copy_dir_recursive_requires_source() {
+        let fs = MemFs::default();
+        assert!(FileUtils::copy_dir_recursive_with(&fs, "missing", "dst").is_err());
+    }
+
+    #[test]
+    fn copy_dir_recursive_sync_keeps_unchanged_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("cursors")).unwrap();
+        fs::write(src.join("cursors/left_ptr"), b"arrow").unwrap();
+        fs::write(src.join("cursors/text"), b"ibeam").unwrap();
+        fs::write(src.join("index.theme"), b"[Icon Theme]").unwrap();
+        FileUtils::copy_dir_recursive(&src, &dst).unwrap();
+
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(dst.join("cursors/left_ptr"), old).unwrap();
+        filetime::set_file_mtime(dst.join("cursors/text"), old).unwrap();
+        fs::write(src.join("cursors/text"), b"IBEAM").unwrap();
+        fs::remove_file(src.join("index.theme")).unwrap();
+        fs::write(dst.join("cursors/stale"), b"old").unwrap();
+
+        FileUtils::copy_dir_recursive_sync(&src, &dst).unwrap();
+
+        let mtime = |path: &str| filetime::FileTime::from_last_modification_time(&fs::metadata(dst.join(path)).unwrap());
+        assert_eq!(mtime("cursors/left_ptr"), old);
+        assert_ne!(mtime("cursors/text"), old);
+        assert_eq!(fs::read(dst.join("cursors/text")).unwrap(), b"IBEAM");
+        assert!(!dst.join("cursors/stale").exists());
+        assert!(!dst.join("index.theme").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_dir_recursive_sync_keeps_links_as_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("left_ptr"), b"arrow").unwrap();
+        std::os::unix::fs::symlink("left_ptr", src.join("arrow")).unwrap();
+        std::os::unix::fs::symlink("missing", src.join("xterm")).unwrap();
+
+        // A copy left behind by an earlier install becomes a link again
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(dst.join("arrow"), b"arrow").unwrap();
+
+        for _ in 0..2 {
+            FileUtils::copy_dir_recursive_sync(&src, &dst).unwrap();
+            assert_eq!(fs::read_link(dst.join("arrow")).unwrap(), Path::new("left_ptr"));
+            assert_eq!(fs::read_link(dst.join("xterm")).unwrap(), Path::new("missing"));
+            assert_eq!(fs::read(dst.join("left_ptr")).unwrap(), b"arrow");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_dir_recursive_parallel_matches_serial_copy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("cursors/nested")).unwrap();
+        for i in 0..16 {
+            fs::write(src.join("cursors").join(format!("cursor_{}", i)), vec![i as u8; 64]).unwrap();
+        }
+        fs::write(src.join("cursors/nested/deep"), b"deep").unwrap();
+        fs::write(src.join("index.theme"), b"[Icon Theme]").unwrap();
+        std::os::unix::fs::symlink("cursor_0", src.join("cursors/alias")).unwrap();
+
+        FileUtils::copy_dir_recursive_parallel(&src, dir.path().join("dst"), 4).unwrap();
+
+        let dst = dir.path().join("dst");
+        for i in 0..16 {
+            assert_eq!(fs::read(dst.join("cursors").join(format!("cursor_{}", i))).unwrap(), vec![i as u8; 64]);
+        }
+        assert_eq!(fs::read(dst.join("cursors/nested/deep")).unwrap(), b"deep");
+        assert_eq!(fs::read(dst.join("index.theme")).unwrap(), b"[Icon Theme]");
+        assert_eq!(fs::read_link(dst.join("cursors/alias")).unwrap(), Path::new("cursor_0"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command_output_returns_trimmed_stdout() {
+        assert_eq!(CommandUtils::run_command_output("echo", &["  24  "]).unwrap(), "24");
+
+        let err = CommandUtils::run_command_output("false", &[]).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CursorError>(), Some(CursorError::CommandFailed { .. })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn missing_commands_are_missing_dependencies() {
+        for timeout in [None, Some(Duration::from_secs(5))] {
+            let err = CommandUtils::run_command_output_timeout("koosh-no-such-tool", &[], timeout).unwrap_err();
+            assert!(matches!(err.downcast_ref::<CursorError>(), Some(CursorError::MissingDependency(tool)) if tool == "koosh-no-such-tool"));
+        }
+    }
+
+    #[test]
+    fn command_lines_quote_arguments_with_spaces() {
+        assert_eq!(command_line("xcursorgen", &["cursor.config", "cursor"]), "xcursorgen cursor.config cursor");
+        assert_eq!(command_line("magick", &["My Frame.png", ""]), "magick \"My Frame.png\" \"\"");
+        assert_eq!(stderr_snippet(b"  bad\n  input \n"), "bad input");
+        assert!(stderr_snippet("x ".repeat(300).as_bytes()).ends_with("..."));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command_timeout_kills_hung_commands() {
+        let err = CommandUtils::run_command_timeout("sleep", &["5"], Duration::from_millis(100)).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CursorError>(), Some(CursorError::CommandTimeout { .. })));
+
+        CommandUtils::run_command_timeout("true", &[], Duration::from_secs(5)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command_retry_retries_failed_commands() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("ran");
+        // Fails the first time, succeeds once the marker exists
+        let script = format!("[ -e '{0}' ] || {{ touch '{0}'; exit 1; }}", marker.display());
+
+        CommandUtils::run_command_retry("sh", &["-c", &script], 2).unwrap();
+
+        let _ = fs::remove_file(&marker);
+        let err = CommandUtils::run_command_retry("sh", &["-c", &script], 1).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CursorError>(), Some(CursorError::CommandFailed { .. })));
+    }
+
+    #[test]
+    fn sanitize_theme_name_accepts_plain_names() {
+        assert_eq!(sanitize_theme_name("Koosh-Animated").unwrap(), "Koosh-Animated");
+        assert_eq!(sanitize_theme_name("  Koosh X11 ").unwrap(), "Koosh X11");
+    }
+
+    #[test]
+    fn sanitize_theme_name_rejects_traversal() {
+        for name in ["../evil", "a/b", "a\\b", "..", "foo..bar", ".hidden", ""] {
+            let err = sanitize_theme_name(name).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref::<CursorError>(), Some(CursorError::InvalidThemeName { .. })),
+                "{:?} should be rejected",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn sanitize_theme_name_rejects_newline_injection() {
+        let err = sanitize_theme_name("Koosh\nInherits=evil").unwrap_err();
+        assert!(err.to_string().contains("control character '\\n'"), "{}", err);
+    }
+
+    #[test]
+    fn display_names_allow_spaces_but_not_newlines() {
+        assert_eq!(sanitize_display_name(" Koosh Animated (HiDPI) ").unwrap(), "Koosh Animated (HiDPI)");
+        assert!(sanitize_display_name("Koosh\nInherits=evil").is_err());
+        assert!(sanitize_display_name("  ").is_err());
+    }
+
+    #[test]
+    fn ensure_within_rejects_escaping_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cursors = dir.path().join("theme/cursors");
+        fs::create_dir_all(&cursors).unwrap();
+
+        ensure_within(&cursors, &cursors.join("left_ptr")).unwrap();
+        ensure_within(&cursors, &cursors.join("nested/../left_ptr")).unwrap();
+
+        for escaping in ["../../foo", "../index.theme", "..", "."] {
+            let err = ensure_within(&cursors, &cursors.join(escaping)).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref::<CursorError>(), Some(CursorError::PathOutsideBase { .. })),
+                "{:?} should be rejected",
+                escaping
+            );
+        }
+        assert!(ensure_within(&cursors, Path::new("/etc/passwd")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ensure_within_follows_symlinked_parents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cursors = dir.path().join("cursors");
+        fs::create_dir_all(&cursors).unwrap();
+        std::os::unix::fs::symlink(dir.path(), cursors.join("escape")).unwrap();
+
+        assert!(ensure_within(&cursors, &cursors.join("escape/outside")).is_err());
+    }
+
+    #[test]
+    fn create_symlink_replaces_existing_link() {
+        let fs = MemFs::default();
+        fs.add_dir("cursors");
+        fs.add_file("cursors/left_ptr", b"a");
+        fs.add_file("cursors/pointer", b"b");
+        fs.symlink(Path::new("pointer"), Path::new("cursors/arrow")).unwrap();
+
+        FileUtils::create_symlink_with(&fs, "left_ptr", "cursors/arrow").unwrap();
+
+        assert_eq!(fs.node("cursors/arrow"), Some(Node::Link(PathBuf::from("left_ptr"))));
+    }
+
+    #[test]
+    fn create_link_copy_materializes_target() {
+        let fs = MemFs::default();
+        fs.add_dir("cursors");
+        fs.add_file("cursors/left_ptr", b"a");
+        fs.add_file("cursors/pointer", b"b");
+        fs.symlink(Path::new("pointer"), Path::new("cursors/arrow")).unwrap();
+
+        FileUtils::create_link_with(&fs, LinkMode::Copy, "left_ptr", "cursors/arrow").unwrap();
+
+        assert_eq!(fs.node("cursors/arrow"), Some(Node::File(b"a".to_vec())));
+        assert_eq!(fs.node("cursors/pointer"), Some(Node::File(b"b".to_vec())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_hardlink_shares_the_inode() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("left_ptr"), b"a").unwrap();
+        std::os::unix::fs::symlink("pointer", dir.path().join("arrow")).unwrap();
+
+        FileUtils::create_link(LinkMode::Hardlink, "left_ptr", dir.path().join("arrow")).unwrap();
+
+        let link = fs::symlink_metadata(dir.path().join("arrow")).unwrap();
+        assert!(link.is_file());
+        assert_eq!(link.ino(), fs::metadata(dir.path().join("left_ptr")).unwrap().ino());
+    }
+
+    #[test]
+    fn create_link_hardlink_falls_back_to_copy() {
+        let fs = MemFs::default();
+        fs.add_dir("cursors");
+        fs.add_file("cursors/left_ptr", b"a");
+
+        FileUtils::create_link_with(&fs, LinkMode::Hardlink, "left_ptr", "cursors/arrow").unwrap();
+
+        assert_eq!(fs.node("cursors/arrow"), Some(Node::File(b"a".to_vec())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_graph_resolves_chains() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let cursors = dir.path();
+        fs::write(cursors.join("pointer"), b"p").unwrap();
+        fs::write(cursors.join("text"), b"t").unwrap();
+        symlink("pointer", cursors.join("hand2")).unwrap();
+        symlink("hand2", cursors.join("hand1")).unwrap();
+        symlink(cursors.join("hand1"), cursors.join("pointing_hand")).unwrap();
+        symlink("missing", cursors.join("dangling")).unwrap();
+        symlink("../elsewhere", cursors.join("outside")).unwrap();
+
+        let graph = symlink_graph(cursors).unwrap();
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph["pointer"], ["hand1", "hand2", "pointing_hand"]);
+        assert!(graph["text"].is_empty());
+
+        symlink("loop_b", cursors.join("loop_a")).unwrap();
+        symlink("loop_a", cursors.join("loop_b")).unwrap();
+        let err = symlink_graph(cursors).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CursorError>(), Some(CursorError::SymlinkCycle(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_skips_symlinks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let theme = dir.path();
+        fs::create_dir_all(theme.join("cursors")).unwrap();
+        fs::write(theme.join("index.theme"), b"12345").unwrap();
+        fs::write(theme.join("cursors/left_ptr"), b"1234567890").unwrap();
+        std::os::unix::fs::symlink("left_ptr", theme.join("cursors/default")).unwrap();
+
+        assert_eq!(theme_disk_usage(theme).unwrap(), 15);
+    }
+}
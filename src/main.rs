@@ -1,19 +1,105 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use serde::Serialize;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use koosh_cursor_tools::compose::ShadowParams;
+use koosh_cursor_tools::quantize::{BitDepth, FrameDepth};
+use koosh_cursor_tools::recolor::{parse_tint, RecolorOp};
+use koosh_cursor_tools::cursor_mapping::load_hotspot_overrides;
+use koosh_cursor_tools::theme_config::{check_theme_version, ResizeAlgorithm, DEFAULT_THEME_VERSION};
+use koosh_cursor_tools::{
+    json_output, CursorError, InstallScope, LinkMode, set_icons_dir_override, set_json_output, set_print_commands, set_sync_installs, set_verbose_output, set_verify_installs, status,
+};
 use koosh_cursor_tools::commands::{
-    add_links::{add_missing_links, AddLinksArgs},
-    create_animated::{create_animated_theme, CreateAnimatedArgs},
+    CursorFilter,
+    add_links::{self, add_missing_links, AddLinksArgs},
+    check_tools::check_tools,
+    clean::{clean_scratch_dirs, CleanArgs},
+    create_animated::{self, create_animated_theme, CreateAnimatedArgs, DelayUnit, OutFormat, SourceKind, VariantSpec},
     create_hyprcursor::{create_hyprcursor_theme, CreateHyprcursorArgs},
-    rename_cursors::{rename_cursors, RenameCursorsArgs},
+    css::{export_css, CssArgs},
+    dedupe::{dedupe_theme, DedupeArgs},
+    doctor::doctor,
+    fingerprint::{fingerprint_theme, FingerprintArgs},
+    inspect::{inspect_cursor, InspectArgs},
+    list::{list_cursors, list_themes, ListArgs, ListThemesArgs},
+    minimize::{minimize_theme, MinimizeArgs},
+    rename_cursors::{self, rename_cursors, RenameCursorsArgs, SourceFormat},
+    relink::{relink_theme, RelinkArgs},
+    retheme::{retheme, RethemeArgs},
+    validate::{validate_theme, ValidateArgs},
 };
 
 #[derive(Parser)]
 #[command(name = "koosh-cursor-tools")]
 #[command(about = "Rust implementation of Koosh cursor theme management tools")]
 #[command(version = "0.1.0")]
+#[command(after_help = EXIT_CODES_HELP)]
 struct Cli {
+    /// Base directory for temporary working files (default: $TMPDIR or the system temp dir)
+    #[arg(long, global = true)]
+    temp_dir: Option<PathBuf>,
+
+    /// Install themes here instead of ~/.icons (KOOSH_ICONS_DIR takes precedence)
+    #[arg(long, global = true)]
+    icons_dir: Option<PathBuf>,
+
+    /// Install built themes to the user icons dir, the system one
+    /// (/usr/share/icons), or both (the system install is skipped with a
+    /// warning when not writable)
+    #[arg(long, global = true, value_name = "SCOPE", default_value = "user", value_parser = parse_install_scope_arg)]
+    install_scope: InstallScope,
+
+    /// Build project-local themes in this directory instead of the current one
+    /// (add-links --output-dir and --output take precedence)
+    #[arg(long, global = true)]
+    theme_dir: Option<PathBuf>,
+
+    /// Copy installed files and scale frames on this many threads
+    /// (default: copy serially, scale on every core)
+    #[arg(short, long, global = true)]
+    jobs: Option<usize>,
+
+    /// Give copied and installed files the modification time of their source
+    #[arg(long, global = true)]
+    preserve_times: bool,
+
+    /// Update installed themes in place, copying only files that changed
+    /// (faster reinstalls; unchanged files keep their modification time)
+    #[arg(long, global = true)]
+    sync: bool,
+
+    /// After installing, re-read the installed theme and fail unless its cursors
+    /// and aliases match the build
+    #[arg(long, global = true)]
+    verify_install: bool,
+
+    /// Kill external tools that run longer than this many seconds (default: no timeout)
+    #[arg(long, global = true, value_name = "SECS")]
+    command_timeout: Option<u64>,
+
+    /// Print a single JSON report on stdout instead of progress messages
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Hide progress bars (they are also hidden when stdout is not a terminal)
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// Print per-cursor and per-frame detail
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Log every external command before it runs (to stderr; RUST_LOG=debug
+    /// also logs exit statuses)
+    #[arg(long, global = true)]
+    print_commands: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -29,87 +115,884 @@ enum Commands {
         /// Source directory containing cursor files
         #[arg(short, long)]
         source_dir: Option<PathBuf>,
+        
+        /// Directory to build the theme in; the theme is created at <OUTPUT_DIR>/<THEME_NAME> (default: current directory)
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+        
+        /// Exact path to build the theme at, independent of the theme name (overrides --output-dir)
+        #[arg(long)]
+        output: Option<PathBuf>,
+        
+        /// Cursor size to use in the printed Hyprland config
+        #[arg(long, default_value_t = 24)]
+        size: u32,
+        
+        /// Name shown in theme pickers (Name= in index.theme and cursor.theme); may contain
+        /// spaces (default: the theme name)
+        #[arg(long, alias = "name-in-index", value_name = "NAME")]
+        display_name: Option<String>,
+        
+        /// Comment written to index.theme and cursor.theme; {name}, {sizes} and {date}
+        /// are filled in
+        #[arg(long, alias = "comment-template", default_value = add_links::DEFAULT_COMMENT)]
+        comment: String,
+        
+        /// Theme to inherit missing cursors from; repeat for several (default: hicolor)
+        #[arg(long = "inherit", value_name = "THEME")]
+        inherits: Vec<String>,
+        
+        /// Recreate alias symlinks even when they already exist
+        #[arg(long, alias = "overwrite-existing-links")]
+        relink: bool,
+        
+        /// Write aliases as full copies of their target instead of symlinks,
+        /// for apps that don't follow symlinks (each alias costs a full cursor file)
+        #[arg(long)]
+        materialize_links: bool,
+        
+        /// Write aliases as hard links to their target (copies where that fails)
+        #[arg(long, conflicts_with = "materialize_links")]
+        hardlinks: bool,
+        
+        /// Lowercase cursor file names (and alias targets) so mixed-case sources
+        /// like `Left_Ptr` still get their aliases
+        #[arg(long)]
+        normalize_names: bool,
+        
+        /// Only process these source cursors (comma-separated; conflicts with --exclude)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES", conflicts_with = "exclude")]
+        only: Vec<String>,
+        
+        /// Skip these source cursors (comma-separated; conflicts with --only)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+        exclude: Vec<String>,
+    },
+    
+    /// Show which external tools are installed and which commands need them
+    CheckTools,
+    
+    /// Diagnose the setup: tools, the icons directory, cursor variables and
+    /// installed themes with dangling aliases
+    ///
+    /// Exits with 5 when something is clearly broken.
+    Doctor,
+    
+    /// Remove stale koosh_* scratch directories left by interrupted runs
+    Clean {
+        /// Directory to clean (default: the current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    
+    /// Print a shell completion script to stdout
+    ///
+    /// e.g. `koosh-cursor-tools completions bash > ~/.local/share/bash-completion/completions/koosh-cursor-tools`,
+    /// `completions zsh > ~/.zfunc/_koosh-cursor-tools` or
+    /// `completions fish > ~/.config/fish/completions/koosh-cursor-tools.fish`
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
     },
     
     /// Create animated cursor theme with multi-size support
     CreateAnimated {
-        /// Input theme directory (default: Koosh-X11)
+        /// Input theme directory or .tar.gz release archive (default: Koosh-X11); repeat to
+        /// merge several themes in order, a later theme's cursors replacing earlier ones
         #[arg(short, long, default_value = "Koosh-X11")]
-        input_theme: String,
+        input_theme: Vec<String>,
         
         /// Output theme name (default: Koosh-Animated)
         #[arg(short, long, default_value = "Koosh-Animated")]
         output_theme: String,
+        
+        /// Name shown in theme pickers (Name= in index.theme and cursor.theme); may contain
+        /// spaces (default: the input theme's Name, else the theme name)
+        #[arg(long, alias = "name-in-index", value_name = "NAME")]
+        display_name: Option<String>,
+        
+        /// Comment written to index.theme and cursor.theme; {name}, {sizes} and {date}
+        /// are filled in (default: the input theme's Comment, else a Koosh description)
+        #[arg(long, alias = "comment-template")]
+        comment: Option<String>,
+        
+        /// Theme to inherit missing cursors from; repeat for several (default: hicolor)
+        #[arg(long = "inherit", value_name = "THEME")]
+        inherits: Vec<String>,
+        
+        /// Rotate the hue of every frame by this many degrees
+        #[arg(long, value_name = "DEG", allow_hyphen_values = true, conflicts_with = "tint")]
+        hue_shift: Option<f32>,
+        
+        /// Colorize every frame with this color, keeping brightness and alpha
+        #[arg(long, value_name = "#RRGGBB", value_parser = parse_tint_arg)]
+        tint: Option<[u8; 3]>,
+        
+        /// Also build a <OUTPUT_THEME>-Dark variant from the same frames
+        #[arg(long)]
+        variants: bool,
+        
+        /// Tint for the dark variant (default: invert colors)
+        #[arg(long, value_name = "#RRGGBB", value_parser = parse_tint_arg, requires = "variants")]
+        dark_tint: Option<[u8; 3]>,
+        
+        /// Add a drop shadow, e.g. "offset=2,2;blur=3;opacity=0.4" (pixels at the source size)
+        #[arg(long, value_name = "SPEC", value_parser = parse_shadow_arg)]
+        shadow: Option<ShadowParams>,
+        
+        /// Write the build parameters (delay, sizes, filter, hotspots) to <THEME>/.koosh-build.json
+        #[arg(long)]
+        emit_build_manifest: bool,
+        
+        /// Delay for frames whose source gives none (PNG frames, or cursors without delays)
+        #[arg(long, value_name = "N", default_value_t = create_animated::DEFAULT_FRAME_DELAY_MS, value_parser = clap::value_parser!(u32).range(1..))]
+        frame_delay: u32,
+        
+        /// Unit of --frame-delay: ms, or cs for centiseconds (--frame-delay 6 --delay-unit cs is 60ms)
+        #[arg(long, value_name = "UNIT", default_value = "ms", value_parser = parse_delay_unit_arg)]
+        delay_unit: DelayUnit,
+        
+        /// Multiply every frame delay by this factor (0.5 = twice as fast); applied after
+        /// the base delay is chosen and never below 1ms
+        #[arg(long, value_name = "FLOAT", default_value_t = 1.0, value_parser = parse_delay_scale_arg)]
+        delay_scale: f32,
+        
+        /// Stop at the first cursor that fails instead of building the rest and reporting failures
+        #[arg(long)]
+        fail_fast: bool,
+        
+        /// Run xcursorgen up to N more times when it fails (default: 0)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: u32,
+        
+        /// Skip re-extracting each generated cursor to check its images (faster for large batches)
+        #[arg(long)]
+        no_verify: bool,
+        
+        /// Themes to produce: xcursor, hyprcursor (installed as <OUTPUT_THEME>-Hyprcursor,
+        /// with the X11 cursors inside) or both
+        #[arg(long, value_name = "FORMAT", default_value = "xcursor", value_parser = parse_out_format_arg)]
+        out_format: OutFormat,
+        
+        /// How hyprcursor scales the shapes of --out-format hyprcursor/both to other
+        /// sizes: none, nearest (for pixel art) or bilinear (default: bilinear)
+        #[arg(long, value_name = "ALGORITHM")]
+        resize_algorithm: Option<ResizeAlgorithm>,
+        
+        /// Version written to the manifest of the --out-format hyprcursor/both theme
+        #[arg(long, value_name = "VERSION", default_value = DEFAULT_THEME_VERSION, value_parser = parse_theme_version_arg)]
+        theme_version: String,
+        
+        /// Sizes to generate (comma-separated; default: $KOOSH_SIZES, then 24,32,48,64,72,96)
+        #[arg(long, value_delimiter = ',', value_name = "SIZES")]
+        sizes: Option<Vec<u32>>,
+        
+        /// Sizes to leave out of the cursors and index.theme (comma-separated,
+        /// e.g. 72,96 for a 32px source)
+        #[arg(long, value_delimiter = ',', value_name = "SIZES")]
+        exclude_sizes: Vec<u32>,
+        
+        /// Treat the source frames as N px instead of their detected width; N is the
+        /// size that gets the unscaled frames and the base hotspots are scaled from
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        source_size: Option<u32>,
+        
+        /// Directory of hand-written xcursorgen configs named <CURSOR>.config; a cursor
+        /// with one is built from its lines (images relative to the config) as they are,
+        /// instead of from computed hotspots and delays, and is not recolored or shadowed
+        #[arg(long, value_name = "DIR")]
+        xcursorgen_config: Option<PathBuf>,
+        
+        /// Floyd–Steinberg dither natively scaled frames instead of rounding their colors.
+        /// Only the native image backend (used without ImageMagick, or for --hue-shift,
+        /// --tint and --shadow) dithers; ImageMagick scales frames its own way
+        #[arg(long)]
+        dither: bool,
+        
+        /// Depth of the natively scaled frames handed to xcursorgen: 8 (bits per RGBA
+        /// channel) or indexed (a palette of up to 256 colors); alpha is kept either way.
+        /// xcursorgen still writes 32-bit cursors, so indexed limits their colors without
+        /// making them smaller. Like --dither, this only applies to the native image backend
+        #[arg(long, value_name = "DEPTH", default_value = "8", value_parser = parse_bit_depth_arg)]
+        bit_depth: BitDepth,
+        
+        /// Rebuild every cursor instead of reusing those whose source is unchanged
+        /// since the last build (recorded in <THEME>/.koosh-cache.json)
+        #[arg(long)]
+        force: bool,
+        
+        /// Build from a directory of PNG frame folders (one per cursor, with an optional
+        /// `hotspot` file holding "x y") instead of an XCursor theme; replaces --input-theme.
+        /// Repeat to merge several directories in order, like --input-theme
+        #[arg(long, value_name = "DIR")]
+        from_pngs: Vec<PathBuf>,
+        
+        /// TOML file of absolute hotspots at the source size, e.g. `pointer = { x = 9, y = 3 }`;
+        /// these are scaled to every output size and win over the built-in ratios
+        #[arg(long, value_name = "FILE")]
+        hotspots: Option<PathBuf>,
+        
+        /// Recreate alias symlinks even when they already exist
+        #[arg(long, alias = "overwrite-existing-links")]
+        relink: bool,
+        
+        /// Keep the source theme's own symlinks as they are and add none of the standard aliases
+        #[arg(long, conflicts_with = "relink")]
+        preserve_source_links: bool,
+        
+        /// Write aliases as full copies of their target instead of symlinks,
+        /// for apps that don't follow symlinks (each alias costs a full cursor file)
+        #[arg(long)]
+        materialize_links: bool,
+        
+        /// Write aliases as hard links to their target (copies where that fails)
+        #[arg(long, conflicts_with = "materialize_links")]
+        hardlinks: bool,
+        
+        /// Lowercase cursor file names (and alias targets) so mixed-case sources
+        /// like `Left_Ptr` still get their aliases
+        #[arg(long)]
+        normalize_names: bool,
+        
+        /// Only process these source cursors (comma-separated; conflicts with --exclude)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES", conflicts_with = "exclude")]
+        only: Vec<String>,
+        
+        /// Skip these source cursors (comma-separated; conflicts with --only)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+        exclude: Vec<String>,
     },
     
     /// Create hyprcursor theme from an existing animated theme
     CreateHyprcursor {
-        /// Source theme name (default: Koosh-Animated)
-        #[arg(short, long, default_value = "Koosh-Animated")]
-        source_theme: String,
+        /// Source theme directory, or the name of a theme in ~/.icons (default: Koosh-Animated)
+        #[arg(short, long, visible_alias = "source-theme", default_value = "Koosh-Animated")]
+        source: PathBuf,
         
         /// Destination theme name (default: Koosh-Hyprcursor2)
         #[arg(short, long, default_value = "Koosh-Hyprcursor2")]
         dest_theme: String,
+        
+        /// Name shown in theme pickers (Name= in index.theme and cursor.theme, and the
+        /// manifest name); may contain spaces (default: the source theme's Name, else the
+        /// theme name)
+        #[arg(long, alias = "name-in-index", value_name = "NAME")]
+        display_name: Option<String>,
+        
+        /// Comment written to index.theme and cursor.theme, and the manifest description;
+        /// {name}, {sizes} and {date} are filled in (default: the source theme's Comment,
+        /// else a Koosh description)
+        #[arg(long, alias = "comment-template")]
+        comment: Option<String>,
+        
+        /// Theme to inherit missing cursors from; repeat for several (default: hicolor)
+        #[arg(long = "inherit", value_name = "THEME")]
+        inherits: Vec<String>,
+        
+        /// Directory of <shape>.svg files embedded as scalable cursors; shapes
+        /// without an SVG keep the source theme's raster images
+        #[arg(long, value_name = "DIR")]
+        svg_dir: Option<PathBuf>,
+        
+        /// Run hyprcursor-util up to N more times when it fails (default: 0)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: u32,
+        
+        /// Leave this run's koosh_extract.* and koosh_hyprcursor.* temporary
+        /// directories behind, even when the build fails
+        #[arg(long)]
+        keep_temp: bool,
+        
+        /// How hyprcursor scales shapes to other sizes: none, nearest (for pixel
+        /// art) or bilinear (default: keep the source theme's, else bilinear)
+        #[arg(long, value_name = "ALGORITHM")]
+        resize_algorithm: Option<ResizeAlgorithm>,
+        
+        /// Version written to the theme's manifest, e.g. for versioned releases
+        #[arg(long, value_name = "VERSION", default_value = DEFAULT_THEME_VERSION, value_parser = parse_theme_version_arg)]
+        theme_version: String,
+    },
+    
+    /// Export a theme as a CSS stylesheet with one PNG per cursor keyword
+    Css {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: String,
+        
+        /// Stylesheet to write; the PNGs go next to it
+        #[arg(short, long, default_value = "cursors.css")]
+        out: PathBuf,
+    },
+    
+    /// Add the standard aliases an installed theme is missing, without rebuilding cursors
+    ///
+    /// Useful after adding a cursor by hand. Existing links are left alone.
+    Relink {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: String,
+    },
+    
+    /// Replace byte-identical cursor files with symlinks to one copy
+    Dedupe {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: String,
+    },
+    
+    /// Copy a theme with only the essential cursors and their aliases, for minimal systems
+    Minimize {
+        /// Theme directory, or the name of a theme in ~/.icons
+        source: String,
+        
+        /// Directory to create the minimal theme at; its name is the theme name
+        dest: PathBuf,
+        
+        /// Cursors to keep, by name or alias (comma-separated;
+        /// default: left_ptr,text,pointer,wait,watch)
+        #[arg(long, value_delimiter = ',', value_name = "CURSORS")]
+        keep: Vec<String>,
+    },
+    
+    /// Print a hash of a theme's cursors, links and index.theme for change detection
+    ///
+    /// With --json the report also lists the hash of every cursor.
+    Fingerprint {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: String,
+    },
+    
+    /// Print the image chunks (sizes, hotspots, delays) of a cursor file
+    Inspect {
+        /// XCursor file to inspect
+        cursor: PathBuf,
+    },
+    
+    /// List a theme's cursors, their aliases, and which are animated
+    ///
+    /// Without a theme, lists every cursor theme in the icons directory.
+    List {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: Option<String>,
+        
+        /// Print an aligned table with headers instead of plain lines
+        #[arg(long)]
+        pretty: bool,
+    },
+    
+    /// Write man pages for every command into a directory
+    #[command(hide = true)]
+    Manpage {
+        /// Directory to write the .1 files to
+        out_dir: PathBuf,
     },
     
     /// Rename cursor files from Windows names to X11 names
     RenameCursors {
-        /// Input directory containing Windows-named cursor files
+        /// Input directory containing Windows-named cursor files; repeat to merge several
+        /// directories in order, a later directory's cursors replacing earlier ones
         #[arg(short, long, default_value = "output")]
-        input_dir: PathBuf,
+        input_dir: Vec<PathBuf>,
+        
+        /// Cursor pack install.inf whose scheme says which file is which cursor
+        /// (sources then match by file name, with or without extension)
+        #[arg(long, value_name = "FILE")]
+        inf: Option<PathBuf>,
         
         /// Output theme name (default: Koosh-X11)
         #[arg(short, long, default_value = "Koosh-X11")]
         output_theme: String,
+        
+        /// Exact path to build the theme at (default: <THEME_DIR>/<OUTPUT_THEME>)
+        #[arg(long)]
+        output: Option<PathBuf>,
+        
+        /// Name shown in theme pickers (Name= in index.theme and cursor.theme); may contain
+        /// spaces (default: the theme name)
+        #[arg(long, alias = "name-in-index", value_name = "NAME")]
+        display_name: Option<String>,
+        
+        /// Comment written to index.theme and cursor.theme; {name}, {sizes} and {date}
+        /// are filled in
+        #[arg(long, alias = "comment-template", default_value = rename_cursors::DEFAULT_COMMENT)]
+        comment: String,
+        
+        /// Theme to inherit missing cursors from; repeat for several (default: hicolor)
+        #[arg(long = "inherit", value_name = "THEME")]
+        inherits: Vec<String>,
+        
+        /// Recreate alias symlinks even when they already exist
+        #[arg(long, alias = "overwrite-existing-links")]
+        relink: bool,
+        
+        /// Carry over the source's symlinks (renamed like the cursors) and add none of
+        /// the standard aliases
+        #[arg(long, conflicts_with = "relink")]
+        preserve_source_links: bool,
+        
+        /// Write aliases as full copies of their target instead of symlinks,
+        /// for apps that don't follow symlinks (each alias costs a full cursor file)
+        #[arg(long)]
+        materialize_links: bool,
+        
+        /// Write aliases as hard links to their target (copies where that fails)
+        #[arg(long, conflicts_with = "materialize_links")]
+        hardlinks: bool,
+        
+        /// Only process these source cursors (comma-separated; conflicts with --exclude)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES", conflicts_with = "exclude")]
+        only: Vec<String>,
+        
+        /// Skip these source cursors (comma-separated; conflicts with --only)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+        exclude: Vec<String>,
+        
+        /// What the source files are: xcursor (copied as-is) or png (each image
+        /// becomes a static cursor at its own size)
+        #[arg(long, value_name = "FORMAT", default_value = "xcursor", value_parser = parse_source_format_arg)]
+        source_format: SourceFormat,
+        
+        /// Look for source files at most this many levels deep (1 = only the
+        /// input directory itself; default: the whole tree)
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+    },
+    
+    /// Rewrite the sizes declared in a theme's index.theme without rebuilding cursors
+    Retheme {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: String,
+        
+        /// Sizes to declare (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "SIZES", required = true)]
+        sizes: Vec<u32>,
+    },
+    
+    /// Check that every cursor contains the sizes declared in index.theme
+    Validate {
+        /// Theme directory, or the name of a theme in ~/.icons
+        theme: String,
+        
+        /// Print an aligned table of the cursors instead of plain lines
+        #[arg(long)]
+        pretty: bool,
     },
 }
 
-fn main() -> Result<()> {
+fn parse_tint_arg(value: &str) -> Result<[u8; 3], String> {
+    parse_tint(value).map_err(|e| e.to_string())
+}
+
+fn parse_delay_scale_arg(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        Ok(_) => Err("must be a positive number".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_delay_unit_arg(value: &str) -> Result<DelayUnit, String> {
+    match value {
+        "ms" => Ok(DelayUnit::Ms),
+        "cs" => Ok(DelayUnit::Cs),
+        _ => Err("expected ms or cs".to_string()),
+    }
+}
+
+fn parse_theme_version_arg(value: &str) -> Result<String, String> {
+    check_theme_version(value).map_err(|e| e.to_string())
+}
+
+fn parse_shadow_arg(value: &str) -> Result<ShadowParams, String> {
+    ShadowParams::parse(value).map_err(|e| format!("{:#}", e))
+}
+
+fn parse_install_scope_arg(value: &str) -> Result<InstallScope, String> {
+    match value {
+        "user" => Ok(InstallScope::User),
+        "system" => Ok(InstallScope::System),
+        "both" => Ok(InstallScope::Both),
+        _ => Err("expected user, system or both".to_string()),
+    }
+}
+
+fn parse_source_format_arg(value: &str) -> Result<SourceFormat, String> {
+    match value {
+        "xcursor" => Ok(SourceFormat::Xcursor),
+        "png" => Ok(SourceFormat::Png),
+        _ => Err("expected xcursor or png".to_string()),
+    }
+}
+
+fn parse_bit_depth_arg(value: &str) -> Result<BitDepth, String> {
+    match value {
+        "8" => Ok(BitDepth::Eight),
+        "indexed" => Ok(BitDepth::Indexed),
+        _ => Err("expected 8 or indexed".to_string()),
+    }
+}
+
+fn parse_out_format_arg(value: &str) -> Result<OutFormat, String> {
+    match value {
+        "xcursor" => Ok(OutFormat::Xcursor),
+        "hyprcursor" => Ok(OutFormat::Hyprcursor),
+        "both" => Ok(OutFormat::Both),
+        _ => Err("expected xcursor, hyprcursor or both".to_string()),
+    }
+}
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  other errors
+  2  invalid command line
+  3  a required external tool is missing
+  4  an input theme, cursor or manifest was not found
+  5  validate or doctor found problems
+  6  some cursors failed to build
+  7  an external tool failed or timed out";
+
+/// Exit code for errors without a more specific category
+const EXIT_FAILURE: i32 = 1;
+/// Exit code when a required external tool is not installed
+const EXIT_MISSING_DEPENDENCY: i32 = 3;
+/// Exit code when an input theme, cursor or manifest does not exist
+const EXIT_NOT_FOUND: i32 = 4;
+/// Exit code when `validate` finds problems in a theme, or `doctor` in the setup
+const EXIT_VALIDATION_FAILED: i32 = 5;
+/// Exit code when a build finished but some cursors failed
+const EXIT_PARTIAL_BUILD: i32 = 6;
+/// Exit code when an external tool failed or timed out
+const EXIT_TOOL_FAILED: i32 = 7;
+
+/// Process exit code for an error, by its `CursorError` category
+///
+/// Usage errors exit with clap's code 2 before any command runs.
+fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.chain().find_map(|cause| cause.downcast_ref::<CursorError>()) {
+        Some(CursorError::MissingDependency(_)) => EXIT_MISSING_DEPENDENCY,
+        Some(
+            CursorError::ThemeNotFound(_)
+            | CursorError::CursorNotFound(_)
+            | CursorError::ManifestNotFound(_),
+        ) => EXIT_NOT_FOUND,
+        Some(CursorError::ValidationFailed { .. }) => EXIT_VALIDATION_FAILED,
+        Some(CursorError::CursorsFailed(_)) => EXIT_PARTIAL_BUILD,
+        Some(CursorError::CommandFailed { .. } | CursorError::CommandTimeout { .. }) => EXIT_TOOL_FAILED,
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn main() {
     let cli = Cli::parse();
+    let default_filter = if cli.print_commands { "warn,koosh_cursor_tools=info" } else { "warn" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter)).init();
+    set_print_commands(cli.print_commands);
+    set_json_output(cli.json);
+    set_verbose_output(cli.verbose);
+    set_sync_installs(cli.sync);
+    set_verify_installs(cli.verify_install);
+
+    if let Err(err) = run(cli) {
+        if json_output() {
+            eprintln!("{}", serde_json::json!({ "error": format!("{:#}", err) }));
+        } else {
+            eprintln!("Error: {:?}", err);
+        }
+        std::process::exit(exit_code(&err));
+    }
+}
+
+fn link_mode(materialize_links: bool, hardlinks: bool) -> LinkMode {
+    if materialize_links {
+        LinkMode::Copy
+    } else if hardlinks {
+        LinkMode::Hardlink
+    } else {
+        LinkMode::Symlink
+    }
+}
+
+/// Print a command's report when `--json` is set
+fn emit_report<T: Serialize>(report: T) -> Result<()> {
+    if json_output() {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    if let Some(icons_dir) = cli.icons_dir {
+        set_icons_dir_override(icons_dir);
+    }
     
+    let command_timeout = cli.command_timeout.map(Duration::from_secs);
+
     match cli.command {
-        Commands::AddLinks { theme_name, source_dir } => {
+        Commands::AddLinks { theme_name, display_name, source_dir, output_dir, output, size, comment, inherits, relink, materialize_links, hardlinks, normalize_names, only, exclude } => {
             let args = AddLinksArgs {
                 theme_name,
+                display_name,
                 source_dir,
+                output_dir: output_dir.or(cli.theme_dir),
+                output,
+                size,
+                relink,
+                link_mode: link_mode(materialize_links, hardlinks),
+                filter: CursorFilter::new(only, exclude)?,
+                normalize_names,
+                comment,
+                inherits,
+                install_scope: cli.install_scope,
+                jobs: cli.jobs,
+                preserve_times: cli.preserve_times,
+                command_timeout,
             };
-            add_missing_links(args)
+            add_missing_links(args).and_then(emit_report)
+        }
+        
+        Commands::CheckTools => {
+            let report = check_tools()?;
+            let missing = report.missing.join(", ");
+            emit_report(&report)?;
+            if !missing.is_empty() {
+                return Err(CursorError::MissingDependency(missing).into());
+            }
+            Ok(())
+        }
+        
+        Commands::Doctor => {
+            let report = doctor()?;
+            let problems = report.problems.len();
+            emit_report(&report)?;
+            if problems > 0 {
+                return Err(CursorError::ValidationFailed { theme: "The environment".to_string(), problems }.into());
+            }
+            Ok(())
+        }
+        
+        Commands::Clean { dir } => {
+            let args = CleanArgs {
+                dir,
+                temp_dir: cli.temp_dir,
+            };
+            clean_scratch_dirs(args).and_then(emit_report)
+        }
+        
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "koosh-cursor-tools", &mut std::io::stdout());
+            Ok(())
         }
         
-        Commands::CreateAnimated { input_theme, output_theme } => {
+        Commands::CreateAnimated { input_theme, output_theme, display_name, comment, inherits, hue_shift, tint, variants, dark_tint, shadow, emit_build_manifest, frame_delay, delay_unit, delay_scale, fail_fast, retries, no_verify, out_format, resize_algorithm, theme_version, sizes, exclude_sizes, source_size, xcursorgen_config, dither, bit_depth, force, from_pngs, hotspots, relink, preserve_source_links, materialize_links, hardlinks, normalize_names, only, exclude } => {
+            let recolor = match (hue_shift, tint) {
+                (Some(degrees), _) => Some(RecolorOp::HueShift(degrees)),
+                (None, Some(color)) => Some(RecolorOp::Tint(color)),
+                (None, None) => None,
+            };
+            let variants = if variants {
+                let dark = dark_tint.map_or(RecolorOp::Invert, RecolorOp::Tint);
+                vec![
+                    VariantSpec { suffix: String::new(), recolor },
+                    VariantSpec { suffix: "-Dark".to_string(), recolor: Some(dark) },
+                ]
+            } else {
+                Vec::new()
+            };
+            let (input_themes, source_kind) = if from_pngs.is_empty() {
+                (input_theme, SourceKind::Xcursor)
+            } else {
+                let dirs = from_pngs.iter().map(|dir| dir.to_string_lossy().into_owned()).collect();
+                (dirs, SourceKind::PngFrames)
+            };
             let args = CreateAnimatedArgs {
-                input_theme,
+                input_themes,
                 output_theme,
+                display_name,
+                temp_dir: cli.temp_dir,
+                theme_dir: cli.theme_dir,
+                install_scope: cli.install_scope,
+                jobs: cli.jobs,
+                preserve_times: cli.preserve_times,
+                command_timeout,
+                retries,
+                verify: !no_verify,
+                recolor,
+                variants,
+                shadow,
+                emit_build_manifest,
+                frame_delay_ms: delay_unit.to_ms(frame_delay),
+                delay_scale,
+                progress: !cli.no_progress && !cli.json && std::io::stdout().is_terminal(),
+                fail_fast,
+                source_kind,
+                hotspot_overrides: hotspots.map(load_hotspot_overrides).transpose()?.unwrap_or_default(),
+                relink,
+                preserve_source_links,
+                link_mode: link_mode(materialize_links, hardlinks),
+                filter: CursorFilter::new(only, exclude)?,
+                comment,
+                inherits,
+                force,
+                normalize_names,
+                out_format,
+                resize_algorithm,
+                theme_version,
+                sizes,
+                exclude_sizes,
+                source_size,
+                xcursorgen_config,
+                frame_depth: FrameDepth { bit_depth, dither },
             };
-            create_animated_theme(args)
+            create_animated_theme(args).and_then(emit_report)
         }
         
-        Commands::CreateHyprcursor { source_theme, dest_theme } => {
+        Commands::CreateHyprcursor { source, dest_theme, display_name, comment, inherits, svg_dir, retries, keep_temp, resize_algorithm, theme_version } => {
             let args = CreateHyprcursorArgs {
-                source_theme,
+                source,
                 dest_theme,
+                display_name,
+                comment,
+                inherits,
+                install_scope: cli.install_scope,
+                svg_dir,
+                temp_dir: cli.temp_dir,
+                jobs: cli.jobs,
+                preserve_times: cli.preserve_times,
+                command_timeout,
+                retries,
+                keep_temp,
+                resize_algorithm,
+                theme_version,
             };
-            create_hyprcursor_theme(args)
+            create_hyprcursor_theme(args).and_then(emit_report)
+        }
+        
+        Commands::Css { theme, out } => {
+            let args = CssArgs { theme, out };
+            export_css(args).and_then(emit_report)
+        }
+        
+        Commands::Relink { theme } => {
+            let args = RelinkArgs { theme };
+            relink_theme(args).and_then(emit_report)
+        }
+        
+        Commands::Dedupe { theme } => {
+            let args = DedupeArgs { theme };
+            dedupe_theme(args).and_then(emit_report)
+        }
+        
+        Commands::Minimize { source, dest, keep } => {
+            let args = MinimizeArgs { source, dest, keep, preserve_times: cli.preserve_times };
+            minimize_theme(args).and_then(emit_report)
+        }
+        
+        Commands::Fingerprint { theme } => {
+            let args = FingerprintArgs { theme };
+            fingerprint_theme(args).and_then(emit_report)
+        }
+        
+        Commands::Inspect { cursor } => {
+            let args = InspectArgs { cursor };
+            inspect_cursor(args).and_then(emit_report)
+        }
+        
+        Commands::List { theme: Some(theme), pretty } => {
+            let args = ListArgs { theme, pretty };
+            list_cursors(args).and_then(emit_report)
+        }
+        
+        Commands::List { theme: None, pretty } => {
+            list_themes(ListThemesArgs { pretty }).and_then(emit_report)
         }
         
-        Commands::RenameCursors { input_dir, output_theme } => {
+        Commands::Manpage { out_dir } => write_manpages(&out_dir),
+        
+        Commands::RenameCursors { input_dir, inf, output_theme, display_name, output, comment, inherits, relink, preserve_source_links, materialize_links, hardlinks, only, exclude, source_format, max_depth } => {
             let args = RenameCursorsArgs {
-                input_dir,
+                input_dirs: input_dir,
                 output_theme,
+                display_name,
+                output,
+                theme_dir: cli.theme_dir,
+                relink,
+                preserve_source_links,
+                link_mode: link_mode(materialize_links, hardlinks),
+                filter: CursorFilter::new(only, exclude)?,
+                source_format,
+                max_depth,
+                inf,
+                comment,
+                inherits,
+                install_scope: cli.install_scope,
+                jobs: cli.jobs,
+                preserve_times: cli.preserve_times,
+                command_timeout,
             };
-            rename_cursors(args)
+            rename_cursors(args).and_then(emit_report)
+        }
+        
+        Commands::Retheme { theme, sizes } => {
+            let args = RethemeArgs { theme, sizes };
+            retheme(args).and_then(emit_report)
+        }
+        
+        Commands::Validate { theme, pretty } => {
+            let report = validate_theme(ValidateArgs { theme, pretty })?;
+            let problems = report.problems.len();
+            emit_report(&report)?;
+            if problems > 0 {
+                return Err(CursorError::ValidationFailed { theme: report.theme, problems }.into());
+            }
+            Ok(())
         }
     }
 }
 
+/// Render one man page for the top-level command and one per visible subcommand
+fn write_manpages(out_dir: &Path) -> Result<()> {
+    fs::create_dir_all(out_dir)?;
+    
+    let mut cli = Cli::command();
+    cli.build();
+    let name = cli.get_name().to_string();
+    
+    render_manpage(cli.clone(), &name, out_dir)?;
+    for sub in cli.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help") {
+        let page_name = format!("{}-{}", name, sub.get_name());
+        render_manpage(sub.clone(), &page_name, out_dir)?;
+    }
+    
+    Ok(())
+}
+
+fn render_manpage(cmd: clap::Command, page_name: &str, out_dir: &Path) -> Result<()> {
+    let path = out_dir.join(format!("{}.1", page_name));
+    let mut buffer = Vec::new();
+    clap_mangen::Man::new(cmd).title(page_name.to_uppercase()).render(&mut buffer)?;
+    fs::write(&path, buffer)
+        .map_err(|e| anyhow::anyhow!("Failed to write {:?}: {}", path, e))?;
+    status!("Wrote {:?}", path);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
     #[test]
     fn verify_cli() {
-        use clap::CommandFactory;
         Cli::command().debug_assert()
     }
+
+    #[test]
+    fn exit_codes_follow_the_error_category() {
+        let err = anyhow::Error::from(CursorError::MissingDependency("xcursorgen".to_string()));
+        assert_eq!(exit_code(&err), EXIT_MISSING_DEPENDENCY);
+
+        let err = anyhow::Error::from(CursorError::ThemeNotFound(PathBuf::from("x")))
+            .context("Failed to resolve input theme");
+        assert_eq!(exit_code(&err), EXIT_NOT_FOUND);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("boom")), EXIT_FAILURE);
+    }
 }
@@ -0,0 +1,304 @@
+use anyhow::{Context, Result};
+use image::{Rgba32FImage, RgbaImage};
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Colors an indexed frame can hold
+const PALETTE_SIZE: usize = 256;
+
+/// Bits stored per pixel of a scaled frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    /// 8 bits per RGBA channel
+    #[default]
+    Eight,
+    /// An index into a palette of at most 256 RGBA colors
+    Indexed,
+}
+
+/// How the native image pipeline stores scaled frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameDepth {
+    pub bit_depth: BitDepth,
+    /// Floyd–Steinberg dither wherever colors are reduced
+    pub dither: bool,
+}
+
+impl FrameDepth {
+    /// Write a frame as a PNG at this depth
+    pub fn save(&self, frame: &RgbaImage, path: &Path) -> Result<()> {
+        match self.bit_depth {
+            BitDepth::Eight => frame.save(path)
+                .with_context(|| format!("Failed to write frame: {:?}", path)),
+            BitDepth::Indexed => write_indexed_png(&quantize(frame, self.dither), path),
+        }
+    }
+}
+
+/// A frame as palette indices
+#[derive(Debug)]
+pub struct IndexedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<[u8; 4]>,
+    /// One palette index per pixel, row by row
+    pub indices: Vec<u8>,
+}
+
+/// Round a float frame to 8 bits per channel, diffusing the rounding error
+///
+/// Only the color channels are dithered: alpha is rounded as it is, so fully
+/// transparent and fully opaque pixels stay that way, and no error spreads
+/// into or out of transparent pixels.
+pub fn dither_to_rgba8(frame: &Rgba32FImage) -> RgbaImage {
+    let (width, height) = frame.dimensions();
+    let mut values: Vec<[f32; 4]> = frame.pixels()
+        .map(|pixel| pixel.0.map(|c| c.clamp(0.0, 1.0) * 255.0))
+        .collect();
+    let mut out = RgbaImage::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let [r, g, b, a] = values[i];
+            let alpha = a.round() as u8;
+            if alpha == 0 {
+                out.put_pixel(x, y, image::Rgba([0, 0, 0, 0]));
+                continue;
+            }
+            let rgb = [r, g, b].map(|c| c.round().clamp(0.0, 255.0));
+            out.put_pixel(x, y, image::Rgba([rgb[0] as u8, rgb[1] as u8, rgb[2] as u8, alpha]));
+            let error = [r - rgb[0], g - rgb[1], b - rgb[2], 0.0];
+            diffuse(&mut values, width, height, x, y, error, |value| value[3].round() > 0.0);
+        }
+    }
+    out
+}
+
+/// Reduce a frame to a palette of at most 256 colors
+///
+/// A frame with few enough colors keeps all of them exactly; otherwise the
+/// palette comes from a median cut over the frame's colors. Fully transparent
+/// pixels always map to a transparent entry of their own.
+pub fn quantize(frame: &RgbaImage, dither: bool) -> IndexedFrame {
+    let (width, height) = frame.dimensions();
+    let pixel_of = |pixel: &image::Rgba<u8>| if pixel[3] == 0 { [0; 4] } else { pixel.0 };
+
+    let mut counts: HashMap<[u8; 4], u32> = HashMap::new();
+    for pixel in frame.pixels() {
+        *counts.entry(pixel_of(pixel)).or_default() += 1;
+    }
+    let transparent = counts.remove(&[0; 4]).is_some();
+    let mut colors: Vec<([u8; 4], u32)> = counts.into_iter().collect();
+    colors.sort();
+
+    let mut palette = Vec::new();
+    if transparent {
+        palette.push([0; 4]);
+    }
+    let available = PALETTE_SIZE - palette.len();
+    let exact = colors.len() <= available;
+    if exact {
+        palette.extend(colors.iter().map(|(color, _)| *color));
+    } else {
+        palette.extend(median_cut(colors, available));
+    }
+
+    let indices = if exact || !dither {
+        let mut nearest_cache: HashMap<[u8; 4], u8> = HashMap::new();
+        frame.pixels()
+            .map(|pixel| {
+                let color = pixel_of(pixel);
+                *nearest_cache.entry(color).or_insert_with(|| nearest(&palette, color.map(f32::from)))
+            })
+            .collect()
+    } else {
+        dither_to_palette(frame, &palette)
+    };
+
+    IndexedFrame { width, height, palette, indices }
+}
+
+/// Write an indexed frame as a palette PNG, with alpha in its tRNS chunk
+pub fn write_indexed_png(frame: &IndexedFrame, path: &Path) -> Result<()> {
+    let file = fs::File::create(path)
+        .with_context(|| format!("Failed to write frame: {:?}", path))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frame.width, frame.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(frame.palette.iter().flat_map(|color| [color[0], color[1], color[2]]).collect::<Vec<u8>>());
+    encoder.set_trns(frame.palette.iter().map(|color| color[3]).collect::<Vec<u8>>());
+
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(&frame.indices))
+        .with_context(|| format!("Failed to write frame: {:?}", path))
+}
+
+/// Map every pixel to the palette, diffusing each pixel's error onward
+///
+/// Transparent pixels keep the transparent entry and take no error, so the
+/// outline of the cursor doesn't grow speckles.
+fn dither_to_palette(frame: &RgbaImage, palette: &[[u8; 4]]) -> Vec<u8> {
+    let (width, height) = frame.dimensions();
+    let mut values: Vec<[f32; 4]> = frame.pixels().map(|pixel| pixel.0.map(f32::from)).collect();
+    let opaque: Vec<bool> = frame.pixels().map(|pixel| pixel[3] > 0).collect();
+    let transparent = palette.iter().position(|color| *color == [0; 4]);
+    let mut indices = vec![0; values.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            if !opaque[i] {
+                indices[i] = transparent.unwrap_or(0) as u8;
+                continue;
+            }
+            let value = values[i].map(|c| c.clamp(0.0, 255.0));
+            let index = nearest(palette, value);
+            indices[i] = index;
+            let chosen = palette[index as usize].map(f32::from);
+            let error = [0, 1, 2, 3].map(|c| value[c] - chosen[c]);
+            diffuse(&mut values, width, height, x, y, error, |value| value[3] > 0.0);
+        }
+    }
+    indices
+}
+
+/// Spread `error` from (x, y) to the unvisited neighbours, Floyd–Steinberg style
+fn diffuse(
+    values: &mut [[f32; 4]],
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    error: [f32; 4],
+    takes_error: impl Fn(&[f32; 4]) -> bool,
+) {
+    const WEIGHTS: [(i64, i64, f32); 4] = [(1, 0, 7.0 / 16.0), (-1, 1, 3.0 / 16.0), (0, 1, 5.0 / 16.0), (1, 1, 1.0 / 16.0)];
+    for (dx, dy, weight) in WEIGHTS {
+        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+        if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+            continue;
+        }
+        let value = &mut values[(ny * width as i64 + nx) as usize];
+        if takes_error(value) {
+            for c in 0..4 {
+                value[c] += error[c] * weight;
+            }
+        }
+    }
+}
+
+/// The palette entry closest to `color`
+fn nearest(palette: &[[u8; 4]], color: [f32; 4]) -> u8 {
+    palette.iter()
+        .map(|entry| entry.iter().zip(color).map(|(&e, c)| (f32::from(e) - c).powi(2)).sum::<f32>())
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index as u8)
+}
+
+/// Split weighted colors into at most `boxes` groups and average each one
+fn median_cut(colors: Vec<([u8; 4], u32)>, boxes: usize) -> Vec<[u8; 4]> {
+    let mut groups = vec![colors];
+    while groups.len() < boxes {
+        // Split the group with the widest channel
+        let Some((index, channel)) = groups.iter()
+            .enumerate()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|(index, group)| {
+                let (channel, range) = (0..4)
+                    .map(|c| {
+                        let (min, max) = group.iter()
+                            .fold((u8::MAX, u8::MIN), |(min, max), (color, _)| (min.min(color[c]), max.max(color[c])));
+                        (c, max - min)
+                    })
+                    .max_by_key(|&(_, range)| range)
+                    .unwrap_or((0, 0));
+                (index, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+
+        let mut group = groups.swap_remove(index);
+        group.sort_by_key(|(color, _)| color[channel]);
+        let total: u32 = group.iter().map(|(_, count)| count).sum();
+        let mut seen = 0;
+        let split = group.iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen * 2 >= total
+            })
+            .map_or(1, |position| position + 1)
+            .clamp(1, group.len() - 1);
+        let rest = group.split_off(split);
+        groups.push(group);
+        groups.push(rest);
+    }
+
+    groups.iter()
+        .map(|group| {
+            let total: u64 = group.iter().map(|(_, count)| u64::from(*count)).sum();
+            [0, 1, 2, 3].map(|c| {
+                let sum: u64 = group.iter().map(|(color, count)| u64::from(color[c]) * u64::from(*count)).sum();
+                ((sum + total / 2) / total.max(1)) as u8
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn quantize_keeps_few_colors_exactly() {
+        let mut frame = RgbaImage::new(4, 4);
+        frame.put_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        frame.put_pixel(2, 2, Rgba([0, 0, 255, 128]));
+        frame.put_pixel(3, 3, Rgba([9, 9, 9, 0]));
+
+        let indexed = quantize(&frame, true);
+        assert_eq!(indexed.palette.len(), 3);
+        for (pixel, &index) in frame.pixels().zip(&indexed.indices) {
+            let expected = if pixel[3] == 0 { [0; 4] } else { pixel.0 };
+            assert_eq!(indexed.palette[index as usize], expected);
+        }
+    }
+
+    #[test]
+    fn quantize_caps_the_palette_and_keeps_transparency() {
+        let mut frame = RgbaImage::new(32, 32);
+        for (x, y, pixel) in frame.enumerate_pixels_mut() {
+            *pixel = if x < 4 { Rgba([0, 0, 0, 0]) } else { Rgba([(x * 8) as u8, (y * 8) as u8, 77, 255]) };
+        }
+
+        for dither in [false, true] {
+            let indexed = quantize(&frame, dither);
+            assert!(indexed.palette.len() <= PALETTE_SIZE);
+            for ((x, _, _), &index) in frame.enumerate_pixels().zip(&indexed.indices) {
+                let alpha = indexed.palette[index as usize][3];
+                assert_eq!(alpha, if x < 4 { 0 } else { 255 });
+            }
+        }
+    }
+
+    #[test]
+    fn dithering_keeps_alpha_and_averages_to_the_source() {
+        let frame = Rgba32FImage::from_fn(16, 16, |x, _| {
+            image::Rgba([100.4 / 255.0, 0.0, 0.0, if x == 0 { 0.0 } else { 1.0 }])
+        });
+        let out = dither_to_rgba8(&frame);
+        assert!(out.pixels().all(|pixel| pixel[3] == 0 || pixel[3] == 255));
+
+        let reds: Vec<f32> = out.pixels().filter(|pixel| pixel[3] == 255).map(|pixel| f32::from(pixel[0])).collect();
+        let mean = reds.iter().sum::<f32>() / reds.len() as f32;
+        assert!((mean - 100.4).abs() < 0.1, "mean {}", mean);
+        assert!(reds.contains(&101.0));
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::Result;
+use image::RgbaImage;
+
+/// A color transformation applied to every cursor frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecolorOp {
+    /// Rotate the hue of every pixel by this many degrees
+    HueShift(f32),
+    /// Colorize with this RGB color, keeping each pixel's brightness
+    Tint([u8; 3]),
+    /// Invert every color channel
+    Invert,
+}
+
+/// Apply a recolor operation to a frame in place
+///
+/// Only the color channels change; alpha is left untouched so outlines and
+/// anti-aliased edges stay crisp.
+pub fn recolor_frame(img: &mut RgbaImage, op: RecolorOp) {
+    for pixel in img.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        if a == 0 {
+            continue;
+        }
+
+        let [r, g, b] = match op {
+            RecolorOp::HueShift(degrees) => hue_shift([r, g, b], degrees),
+            RecolorOp::Tint(color) => tint([r, g, b], color),
+            RecolorOp::Invert => [255 - r, 255 - g, 255 - b],
+        };
+        pixel.0 = [r, g, b, a];
+    }
+}
+
+fn hue_shift(rgb: [u8; 3], degrees: f32) -> [u8; 3] {
+    let (h, s, v) = rgb_to_hsv(rgb);
+    hsv_to_rgb((h + degrees).rem_euclid(360.0), s, v)
+}
+
+fn tint(rgb: [u8; 3], color: [u8; 3]) -> [u8; 3] {
+    let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    color.map(|c| (c as f32 * luminance).round().clamp(0.0, 255.0) as u8)
+}
+
+fn rgb_to_hsv(rgb: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+
+    (hue, saturation, max)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [u8; 3] {
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = v - c;
+
+    let (r, g, b) = match h as u32 {
+        0..=59 => (c, x, 0.0),
+        60..=119 => (x, c, 0.0),
+        120..=179 => (0.0, c, x),
+        180..=239 => (0.0, x, c),
+        240..=299 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Parse a `#RRGGBB` color for `--tint`
+pub fn parse_tint(value: &str) -> Result<[u8; 3]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!("Invalid color '{}', expected #RRGGBB", value));
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn hue_shift_rotates_colors_and_keeps_alpha() {
+        let mut img = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 128]));
+        recolor_frame(&mut img, RecolorOp::HueShift(120.0));
+        assert_eq!(img.get_pixel(0, 0).0, [0, 255, 0, 128]);
+    }
+
+    #[test]
+    fn tint_keeps_brightness_and_alpha() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 200]));
+        recolor_frame(&mut img, RecolorOp::Tint([0x20, 0x40, 0x80]));
+        assert_eq!(img.get_pixel(0, 0).0, [0x20, 0x40, 0x80, 200]);
+    }
+
+    #[test]
+    fn invert_flips_colors_and_keeps_alpha() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([255, 200, 0, 77]));
+        recolor_frame(&mut img, RecolorOp::Invert);
+        assert_eq!(img.get_pixel(0, 0).0, [0, 55, 255, 77]);
+    }
+
+    #[test]
+    fn transparent_pixels_are_untouched() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([10, 20, 30, 0]));
+        recolor_frame(&mut img, RecolorOp::Tint([255, 0, 0]));
+        assert_eq!(img.get_pixel(0, 0).0, [10, 20, 30, 0]);
+    }
+
+    #[test]
+    fn parses_hex_tints() {
+        assert_eq!(parse_tint("#1a2B3c").unwrap(), [0x1a, 0x2b, 0x3c]);
+        assert!(parse_tint("#12345").is_err());
+        assert!(parse_tint("#zzzzzz").is_err());
+    }
+}
@@ -1,6 +1,95 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::xcursor::Xcursor;
+
+/// Themes inherited when none are given
+pub const DEFAULT_INHERITS: &[&str] = &["hicolor"];
+
+/// Value of an `Inherits=` line: the given themes, or [`DEFAULT_INHERITS`]
+pub fn inherits_value(inherits: &[String]) -> String {
+    if inherits.is_empty() {
+        DEFAULT_INHERITS.join(",")
+    } else {
+        inherits.join(",")
+    }
+}
+
+/// Values substituted into a comment template by [`render_comment`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentCtx {
+    pub name: String,
+    /// The theme's effective sizes, for `{sizes}`
+    pub sizes: Vec<u32>,
+    /// Build date as `YYYY-MM-DD`, for `{date}`
+    pub date: String,
+}
+
+impl CommentCtx {
+    /// Context for a theme built today
+    pub fn new(name: &str, sizes: Vec<u32>) -> Self {
+        Self { name: name.to_string(), sizes, date: build_date() }
+    }
+}
+
+/// Substitute `{name}`, `{sizes}` and `{date}` in a comment template
+///
+/// Sizes are written as `24, 32, 48`. Any other `{...}` is kept as written.
+pub fn render_comment(template: &str, ctx: &CommentCtx) -> String {
+    let sizes = ctx.sizes.iter().map(u32::to_string).collect::<Vec<_>>().join(", ");
+    template
+        .replace("{name}", &ctx.name)
+        .replace("{sizes}", &sizes)
+        .replace("{date}", &ctx.date)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`, or that of `SOURCE_DATE_EPOCH` when set
+fn build_date() -> String {
+    let secs = std::env::var("SOURCE_DATE_EPOCH").ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) Gregorian date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Nominal sizes found in the XCursor files of a cursors directory, sorted
+///
+/// Symlinks and files that don't parse are ignored; a missing directory has
+/// no sizes.
+pub fn cursor_sizes<P: AsRef<Path>>(cursors_dir: P) -> Vec<u32> {
+    let mut sizes = Vec::new();
+    let Ok(entries) = fs::read_dir(cursors_dir) else {
+        return sizes;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_symlink() {
+            continue;
+        }
+        if let Ok(cursor) = Xcursor::read(&path) {
+            sizes.extend(cursor.nominal_sizes());
+        }
+    }
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
 
 /// Create an index.theme file for a cursor theme
 pub fn create_index_theme<P: AsRef<Path>>(
@@ -8,43 +97,22 @@ pub fn create_index_theme<P: AsRef<Path>>(
     theme_name: &str,
     comment: &str,
     sizes: Option<&[u32]>,
+    inherits: &[String],
 ) -> Result<()> {
-    let theme_path = theme_path.as_ref();
-    let index_path = theme_path.join("index.theme");
-    
-    let mut content = format!(
-        r#"[Icon Theme]
-Name={}
-Comment={}
-Inherits=hicolor
-
-# Directory list
-Directories=cursors
-
-[cursors]
-Context=Cursors
-Type=Fixed
-"#,
-        theme_name, comment
-    );
-    
-    // Add size-specific sections if sizes are provided
-    if let Some(sizes) = sizes {
-        for &size in sizes {
-            content.push_str(&format!(
-                r#"
-[cursors/{}]
-Size={}
-Context=Cursors
-Type=Fixed
-"#,
-                size, size
-            ));
-        }
-    }
-    
-    fs::write(index_path, content)?;
-    Ok(())
+    let inherits = if inherits.is_empty() {
+        DEFAULT_INHERITS.iter().map(|theme| theme.to_string()).collect()
+    } else {
+        inherits.to_vec()
+    };
+    let index = IndexTheme {
+        name: Some(theme_name.to_string()),
+        comment: Some(comment.to_string()),
+        inherits,
+        directories: vec!["cursors".to_string()],
+        sizes: sizes.map(<[u32]>::to_vec).unwrap_or_default(),
+        ..Default::default()
+    };
+    index.write(theme_path)
 }
 
 /// Create a cursor.theme file for a cursor theme
@@ -52,6 +120,7 @@ pub fn create_cursor_theme<P: AsRef<Path>>(
     theme_path: P,
     theme_name: &str,
     comment: &str,
+    inherits: &[String],
 ) -> Result<()> {
     let theme_path = theme_path.as_ref();
     let cursor_theme_path = theme_path.join("cursor.theme");
@@ -62,7 +131,7 @@ Name={}
 Comment={}
 Inherits={}
 "#,
-        theme_name, comment, theme_name
+        theme_name, comment, inherits_value(inherits)
     );
     
     fs::write(cursor_theme_path, content)?;
@@ -70,16 +139,28 @@ Inherits={}
 }
 
 /// Create both theme configuration files
+///
+/// `Name=` is `display_name` when given, otherwise `theme_name`. `comment` is
+/// a template for [`render_comment`]; `{sizes}` are the given sizes, or those
+/// found in the theme's cursors.
 pub fn create_theme_files<P: AsRef<Path>>(
     theme_path: P,
     theme_name: &str,
+    display_name: Option<&str>,
     comment: &str,
     sizes: Option<&[u32]>,
+    inherits: &[String],
 ) -> Result<()> {
     let theme_path = theme_path.as_ref();
+    let name = display_name.unwrap_or(theme_name);
+    let effective_sizes = match sizes {
+        Some(sizes) => sizes.to_vec(),
+        None => cursor_sizes(theme_path.join("cursors")),
+    };
+    let comment = render_comment(comment, &CommentCtx::new(name, effective_sizes));
     
-    create_index_theme(theme_path, theme_name, comment, sizes)?;
-    create_cursor_theme(theme_path, theme_name, comment)?;
+    create_index_theme(theme_path, name, &comment, sizes, inherits)?;
+    create_cursor_theme(theme_path, name, &comment, inherits)?;
     
     Ok(())
 }
@@ -87,25 +168,779 @@ pub fn create_theme_files<P: AsRef<Path>>(
 /// Standard cursor sizes used by modern themes
 pub const STANDARD_SIZES: &[u32] = &[24, 32, 48, 64, 72, 96];
 
+/// Environment variable that overrides [`STANDARD_SIZES`] (e.g. `48,64,96`)
+pub const SIZES_ENV: &str = "KOOSH_SIZES";
+
+/// The sizes to generate
+///
+/// Resolution order:
+/// 1. the `--sizes` flag
+/// 2. the `KOOSH_SIZES` environment variable, when set and non-empty
+/// 3. the sizes from a config file
+/// 4. [`STANDARD_SIZES`]
+///
+/// Whichever is used must not contain zeros or duplicates. The sizes are
+/// returned smallest first.
+pub fn resolve_sizes(flag: Option<&[u32]>, config: Option<&[u32]>) -> Result<Vec<u32>> {
+    let env = std::env::var(SIZES_ENV).ok().filter(|value| !value.trim().is_empty());
+    pick_sizes(flag, env.as_deref(), config)
+}
+
+fn pick_sizes(flag: Option<&[u32]>, env: Option<&str>, config: Option<&[u32]>) -> Result<Vec<u32>> {
+    let (source, mut sizes) = match (flag, env, config) {
+        (Some(sizes), _, _) => ("--sizes", sizes.to_vec()),
+        (None, Some(value), _) => {
+            let sizes = value
+                .split(',')
+                .map(|size| size.trim().parse::<u32>())
+                .collect::<Result<Vec<u32>, _>>()
+                .with_context(|| format!("{}: {:?} is not a comma-separated list of sizes", SIZES_ENV, value))?;
+            (SIZES_ENV, sizes)
+        }
+        (None, None, Some(sizes)) => ("config", sizes.to_vec()),
+        (None, None, None) => return Ok(STANDARD_SIZES.to_vec()),
+    };
+
+    if sizes.is_empty() {
+        anyhow::bail!("{}: no sizes given", source);
+    }
+    if sizes.contains(&0) {
+        anyhow::bail!("{}: sizes must be greater than 0 ({:?})", source, sizes);
+    }
+    sizes.sort_unstable();
+    if let Some(pair) = sizes.windows(2).find(|pair| pair[0] == pair[1]) {
+        anyhow::bail!("{}: size {} is listed twice", source, pair[0]);
+    }
+    Ok(sizes)
+}
+
+/// Manifest `version` used when none is given
+pub const DEFAULT_THEME_VERSION: &str = "1.0";
+
+/// Check a theme version for a hyprcursor manifest
+///
+/// [`HyprManifest`] trims values and reads one per line, and hyprlang starts
+/// a comment at `#`, so the version is trimmed and must be non-empty and free
+/// of control characters and `#` to read back as written.
+pub fn check_theme_version(version: &str) -> Result<String> {
+    let trimmed = version.trim();
+    if trimmed.is_empty() {
+        anyhow::bail!("Theme version is empty");
+    }
+    if let Some(c) = trimmed.chars().find(|c| c.is_control()) {
+        anyhow::bail!("Theme version {:?} contains control character {:?}", version, c);
+    }
+    if trimmed.contains('#') {
+        anyhow::bail!("Theme version {:?} contains '#', which starts a comment in manifest.hl", version);
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Check a theme comment for index.theme, cursor.theme and a manifest `description`
+///
+/// A line break would add keys of its own to the theme files, and hyprlang
+/// starts a comment at `#`, so the comment is trimmed and must be free of
+/// control characters and `#`. It may be empty.
+pub fn check_theme_comment(comment: &str) -> Result<String> {
+    let trimmed = comment.trim();
+    if let Some(c) = trimmed.chars().find(|c| c.is_control()) {
+        anyhow::bail!("Theme comment {:?} contains control character {:?}", comment, c);
+    }
+    if trimmed.contains('#') {
+        anyhow::bail!("Theme comment {:?} contains '#', which starts a comment in manifest.hl", comment);
+    }
+    Ok(trimmed.to_string())
+}
+
 /// Create a hyprcursor manifest file
+///
+/// The manifest `name` is `display_name` when given, otherwise `theme_name`.
+/// `description` should have passed [`check_theme_comment`] and `version`
+/// [`check_theme_version`].
 pub fn create_hyprcursor_manifest<P: AsRef<Path>>(
     theme_path: P,
     theme_name: &str,
+    display_name: Option<&str>,
     description: &str,
     version: &str,
+    cursors_directory: &str,
 ) -> Result<()> {
+    let mut manifest = HyprManifest::default();
+    manifest.set("name", display_name.unwrap_or(theme_name));
+    manifest.set("description", description);
+    manifest.set("version", version);
+    manifest.set("cursors_directory", cursors_directory);
+    manifest.write(theme_path.as_ref().join("manifest.hl"))
+}
+
+/// Parsed contents of an `index.theme` file
+///
+/// Keys, comments and sections this type doesn't model are kept as they are
+/// and written back, so rewriting a theme's index loses nothing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexTheme {
+    pub name: Option<String>,
+    pub comment: Option<String>,
+    /// Themes from `Inherits=`, in order
+    pub inherits: Vec<String>,
+    pub directories: Vec<String>,
+    /// Sizes declared by `[cursors/N]` sections
+    pub sizes: Vec<u32>,
+    /// Other lines of the `[Icon Theme]` section, e.g. localized names
+    pub extra: Vec<String>,
+    /// Other sections with their lines, in order; `""` holds lines before the first section
+    pub sections: Vec<(String, Vec<String>)>,
+}
+
+/// Comment written above `Directories=`, not kept as an extra line
+const DIRECTORY_LIST_COMMENT: &str = "# Directory list";
+
+impl IndexTheme {
+    /// Parse the text of an `index.theme` file
+    pub fn parse(content: &str) -> Self {
+        let mut theme = IndexTheme::default();
+        let mut section = String::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line == DIRECTORY_LIST_COMMENT {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.to_string();
+                if section != "Icon Theme" {
+                    theme.sections.push((section.clone(), Vec::new()));
+                }
+                continue;
+            }
+
+            let keep_line = |theme: &mut IndexTheme| match theme.sections.last_mut() {
+                Some((name, lines)) if *name == section => lines.push(line.to_string()),
+                _ if section == "Icon Theme" => theme.extra.push(line.to_string()),
+                _ => theme.sections.push((section.clone(), vec![line.to_string()])),
+            };
+            let Some((key, value)) = line.split_once('=').filter(|_| !line.starts_with('#')) else {
+                keep_line(&mut theme);
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            if section == "Icon Theme" {
+                match key {
+                    "Name" => theme.name = Some(value.to_string()),
+                    "Comment" => theme.comment = Some(value.to_string()),
+                    "Inherits" => {
+                        theme.inherits = value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|t| !t.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                    }
+                    "Directories" => {
+                        theme.directories = value
+                            .split([',', ' '])
+                            .filter(|d| !d.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                    }
+                    _ => keep_line(&mut theme),
+                }
+            } else if section.starts_with("cursors/") && key == "Size" {
+                if let Ok(size) = value.parse::<u32>() {
+                    if !theme.sizes.contains(&size) {
+                        theme.sizes.push(size);
+                    }
+                }
+                keep_line(&mut theme);
+            } else {
+                keep_line(&mut theme);
+            }
+        }
+
+        theme
+    }
+
+    /// Read `index.theme` from a theme directory, if it has one
+    pub fn read<P: AsRef<Path>>(theme_path: P) -> Result<Option<Self>> {
+        let index_path = theme_path.as_ref().join("index.theme");
+        if !index_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&index_path)
+            .with_context(|| format!("Failed to read {:?}", index_path))?;
+        Ok(Some(Self::parse(&content)))
+    }
+
+    /// Write this theme to `index.theme` in a theme directory
+    pub fn write<P: AsRef<Path>>(&self, theme_path: P) -> Result<()> {
+        let index_path = theme_path.as_ref().join("index.theme");
+        fs::write(&index_path, self.to_string())
+            .with_context(|| format!("Failed to write {:?}", index_path))
+    }
+}
+
+/// Themes a theme directory inherits from, per `index.theme` and `cursor.theme`
+///
+/// Cursor loaders read either file, so the `Inherits` lists of both are
+/// combined, in order and without duplicates.
+pub fn read_inherits<P: AsRef<Path>>(theme_path: P) -> Result<Vec<String>> {
     let theme_path = theme_path.as_ref();
-    let manifest_path = theme_path.join("manifest.hl");
-    
-    let content = format!(
-        r#"name = {}
-description = {}
-version = {}
-cursors_directory = cursors
-"#,
-        theme_name, description, version
-    );
-    
-    fs::write(manifest_path, content)?;
-    Ok(())
+    let mut inherits: Vec<String> = Vec::new();
+    for file in ["index.theme", "cursor.theme"] {
+        let path = theme_path.join(file);
+        if !path.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+        for parent in IndexTheme::parse(&content).inherits {
+            if !inherits.contains(&parent) {
+                inherits.push(parent);
+            }
+        }
+    }
+    Ok(inherits)
+}
+
+/// Follow `Inherits` chains from `start` and return the first loop found
+///
+/// `inherits_of` gives the parents of a theme (empty for themes that aren't
+/// installed). The loop is returned as the chain of names from the repeated
+/// theme back to itself, e.g. `["A", "B", "A"]`.
+pub fn find_inherits_cycle<F>(start: &str, inherits_of: F) -> Result<Option<Vec<String>>>
+where
+    F: Fn(&str) -> Result<Vec<String>>,
+{
+    fn visit<F: Fn(&str) -> Result<Vec<String>>>(
+        name: &str,
+        inherits_of: &F,
+        chain: &mut Vec<String>,
+        done: &mut Vec<String>,
+    ) -> Result<Option<Vec<String>>> {
+        if let Some(pos) = chain.iter().position(|n| n == name) {
+            let mut cycle = chain[pos..].to_vec();
+            cycle.push(name.to_string());
+            return Ok(Some(cycle));
+        }
+        if done.iter().any(|n| n == name) {
+            return Ok(None);
+        }
+
+        chain.push(name.to_string());
+        for parent in inherits_of(name)? {
+            if let Some(cycle) = visit(&parent, inherits_of, chain, done)? {
+                return Ok(Some(cycle));
+            }
+        }
+        chain.pop();
+        done.push(name.to_string());
+        Ok(None)
+    }
+
+    visit(start, &inherits_of, &mut Vec::new(), &mut Vec::new())
+}
+
+impl std::fmt::Display for IndexTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((_, preamble)) = self.sections.iter().find(|(name, _)| name.is_empty()) {
+            for line in preamble {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        writeln!(f, "[Icon Theme]")?;
+        if let Some(name) = &self.name {
+            writeln!(f, "Name={}", name)?;
+        }
+        if let Some(comment) = &self.comment {
+            writeln!(f, "Comment={}", comment)?;
+        }
+        if !self.inherits.is_empty() {
+            writeln!(f, "Inherits={}", self.inherits.join(","))?;
+        }
+        for line in &self.extra {
+            writeln!(f, "{}", line)?;
+        }
+
+        let directories = if self.directories.is_empty() {
+            vec!["cursors".to_string()]
+        } else {
+            self.directories.clone()
+        };
+        writeln!(f)?;
+        writeln!(f, "# Directory list")?;
+        writeln!(f, "Directories={}", directories.join(" "))?;
+
+        for directory in &directories {
+            write!(f, "\n[{}]\nContext=Cursors\nType=Fixed\n", directory)?;
+        }
+        for size in &self.sizes {
+            write!(f, "\n[cursors/{}]\nSize={}\nContext=Cursors\nType=Fixed\n", size, size)?;
+        }
+
+        // Directory and size sections were written from the fields above
+        let regenerated = |name: &str| {
+            name.is_empty()
+                || directories.iter().any(|directory| directory == name)
+                || name.strip_prefix("cursors/").is_some_and(|size| size.parse::<u32>().is_ok())
+        };
+        for (name, lines) in self.sections.iter().filter(|(name, _)| !regenerated(name)) {
+            write!(f, "\n[{}]\n", name)?;
+            for line in lines {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single line of a `manifest.hl` file
+#[derive(Debug, Clone, PartialEq)]
+enum ManifestLine {
+    Entry { key: String, value: String },
+    /// Comments, blank lines and anything else, kept verbatim
+    Other(String),
+}
+
+/// Parsed contents of a hyprcursor `manifest.hl` file
+///
+/// Keys keep their original order and unknown keys and comments survive a
+/// round trip, so only the fields that are [`set`](Self::set) change.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HyprManifest {
+    lines: Vec<ManifestLine>,
+}
+
+impl HyprManifest {
+    /// Parse the text of a `manifest.hl` file
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim();
+                match trimmed.split_once('=') {
+                    Some((key, value)) if !trimmed.starts_with('#') && !key.trim().is_empty() => {
+                        ManifestLine::Entry {
+                            key: key.trim().to_string(),
+                            value: value.trim().to_string(),
+                        }
+                    }
+                    _ => ManifestLine::Other(line.to_string()),
+                }
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    /// Read and parse a `manifest.hl` file
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+        Ok(Self::parse(&content))
+    }
+
+    /// Value of the first entry with this key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            ManifestLine::Entry { key: k, value } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Set a key, replacing an existing entry (and dropping duplicates) or appending a new one
+    pub fn set(&mut self, key: &str, value: &str) {
+        let mut found = false;
+        self.lines.retain_mut(|line| match line {
+            ManifestLine::Entry { key: k, value: v } if k == key => {
+                if found {
+                    return false;
+                }
+                found = true;
+                *v = value.to_string();
+                true
+            }
+            _ => true,
+        });
+
+        if !found {
+            self.lines.push(ManifestLine::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+
+    /// Write the manifest back to disk
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string())
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for HyprManifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            match line {
+                ManifestLine::Entry { key, value } => writeln!(f, "{} = {}", key, value)?,
+                ManifestLine::Other(text) => writeln!(f, "{}", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How hyprcursor scales a shape's images to sizes it has no image for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResizeAlgorithm {
+    /// No scaling; the closest image is used as-is
+    None,
+    /// Sharp pixels, for pixel-art cursors
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+impl ResizeAlgorithm {
+    /// The value written to `meta.hl`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Nearest => "nearest",
+            Self::Bilinear => "bilinear",
+        }
+    }
+}
+
+impl std::str::FromStr for ResizeAlgorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "none" => Ok(Self::None),
+            "nearest" => Ok(Self::Nearest),
+            "bilinear" => Ok(Self::Bilinear),
+            _ => Err("expected none, nearest or bilinear".to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ResizeAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One line of a hyprcursor shape's `meta.hl`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaEntry {
+    /// How hyprcursor scales the images to sizes without one
+    ResizeAlgorithm(ResizeAlgorithm),
+    /// Hotspot as a ratio of the image size, from the top left
+    Hotspot { x: f64, y: f64 },
+    /// One image at a nominal size; animated shapes list several with their delay
+    Size { size: u32, file: String, delay_ms: Option<u32> },
+    /// Other cursor names that resolve to this shape
+    Override(Vec<String>),
+}
+
+impl std::fmt::Display for MetaEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ResizeAlgorithm(algorithm) => write!(f, "resize_algorithm = {}", algorithm),
+            Self::Hotspot { x, y } => write!(f, "hotspot_x = {:.3}\nhotspot_y = {:.3}", x, y),
+            Self::Size { size, file, delay_ms: Some(delay) } => write!(f, "define_size = {}, {}, {}", size, file, delay),
+            Self::Size { size, file, delay_ms: None } => write!(f, "define_size = {}, {}", size, file),
+            Self::Override(names) => write!(f, "define_override = {}", names.join(";")),
+        }
+    }
+}
+
+/// Text of a `meta.hl` with the entries in order
+pub fn render_cursor_meta(entries: &[MetaEntry]) -> String {
+    entries.iter().map(|entry| format!("{}\n", entry)).collect()
+}
+
+/// Write a hyprcursor shape's `meta.hl` into `dir`
+pub fn write_cursor_meta(dir: &Path, entries: &[MetaEntry]) -> Result<()> {
+    let path = dir.join(crate::hyprcursor::META_FILE);
+    fs::write(&path, render_cursor_meta(entries))
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// One image line of an xcursorgen config: `<size> <xhot> <yhot> <image> [<delay>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLine {
+    pub size: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    /// Image path as written, relative to the directory xcursorgen runs in
+    pub image: PathBuf,
+    /// Frame delay in milliseconds, for animated cursors
+    pub delay_ms: Option<u32>,
+}
+
+impl std::str::FromStr for ConfigLine {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if !(4..=5).contains(&fields.len()) {
+            anyhow::bail!("expected \"<size> <xhot> <yhot> <image> [<delay>]\", got {:?}", line);
+        }
+        let number = |index: usize, what: &str| {
+            fields[index].parse::<u32>()
+                .with_context(|| format!("{} {:?} is not a number", what, fields[index]))
+        };
+        Ok(Self {
+            size: number(0, "size")?,
+            xhot: number(1, "x hotspot")?,
+            yhot: number(2, "y hotspot")?,
+            image: PathBuf::from(fields[3]),
+            delay_ms: fields.get(4).map(|_| number(4, "delay")).transpose()?,
+        })
+    }
+}
+
+impl std::fmt::Display for ConfigLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {} {}", self.size, self.xhot, self.yhot, self.image.display())?;
+        if let Some(delay) = self.delay_ms {
+            write!(f, " {}", delay)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the image lines of an xcursorgen config, skipping blank lines and `#` comments
+pub fn parse_xcursorgen_config(path: &Path) -> Result<Vec<ConfigLine>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| line.parse()
+            .with_context(|| format!("{}:{}: invalid xcursorgen config line", path.display(), index + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn xcursorgen_configs_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join("wait.config");
+        fs::write(&config, "# hand-tuned\n24 11 11 wait_24_000.png 50\n\n32 15 15 wait_32_000.png 50\n48 23 23 wait.png\n").unwrap();
+
+        let lines = parse_xcursorgen_config(&config).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], ConfigLine { size: 24, xhot: 11, yhot: 11, image: "wait_24_000.png".into(), delay_ms: Some(50) });
+        assert_eq!(lines[2].delay_ms, None);
+        assert_eq!(lines[1].to_string(), "32 15 15 wait_32_000.png 50");
+
+        fs::write(&config, "24 11 11 wait.png\n24 x 11 wait.png\n").unwrap();
+        let err = parse_xcursorgen_config(&config).unwrap_err();
+        assert!(format!("{:#}", err).contains("wait.config:2"), "{:#}", err);
+    }
+
+    #[test]
+    fn resize_algorithms_parse_only_known_values() {
+        assert_eq!("nearest".parse(), Ok(ResizeAlgorithm::Nearest));
+        assert_eq!(" none ".parse(), Ok(ResizeAlgorithm::None));
+        assert!("bicubic".parse::<ResizeAlgorithm>().is_err());
+        assert_eq!(ResizeAlgorithm::default().to_string(), "bilinear");
+    }
+
+    #[test]
+    fn cursor_meta_matches_hyprcursor_format() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_cursor_meta(dir.path(), &[
+            MetaEntry::ResizeAlgorithm(ResizeAlgorithm::Bilinear),
+            MetaEntry::Hotspot { x: 0.25, y: 0.0 },
+            MetaEntry::Override(vec!["watch".to_string(), "wait_cursor".to_string()]),
+            MetaEntry::Size { size: 24, file: "wait_24_1.png".to_string(), delay_ms: Some(40) },
+            MetaEntry::Size { size: 24, file: "wait_24_2.png".to_string(), delay_ms: Some(40) },
+            MetaEntry::Size { size: 0, file: "wait.svg".to_string(), delay_ms: None },
+        ]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("meta.hl")).unwrap(),
+            "resize_algorithm = bilinear\n\
+             hotspot_x = 0.250\n\
+             hotspot_y = 0.000\n\
+             define_override = watch;wait_cursor\n\
+             define_size = 24, wait_24_1.png, 40\n\
+             define_size = 24, wait_24_2.png, 40\n\
+             define_size = 0, wait.svg\n",
+        );
+    }
+
+    #[test]
+    fn sizes_come_from_the_flag_then_env_then_config() {
+        let flag: &[u32] = &[32];
+        let config: &[u32] = &[48, 24];
+        assert_eq!(pick_sizes(Some(flag), Some("48,64"), Some(config)).unwrap(), [32]);
+        assert_eq!(pick_sizes(None, Some("96, 48,64"), Some(config)).unwrap(), [48, 64, 96]);
+        assert_eq!(pick_sizes(None, None, Some(config)).unwrap(), [24, 48]);
+        assert_eq!(pick_sizes(Some(&[64, 24]), None, None).unwrap(), [24, 64]);
+        assert_eq!(pick_sizes(None, None, None).unwrap(), STANDARD_SIZES);
+
+        assert!(pick_sizes(None, Some("48,big"), None).is_err());
+        assert!(pick_sizes(None, Some("0,48"), None).unwrap_err().to_string().contains("greater than 0"));
+        assert!(pick_sizes(Some(&[48, 64, 48]), None, None).unwrap_err().to_string().contains("listed twice"));
+        assert!(pick_sizes(None, None, Some(&[24, 24])).unwrap_err().to_string().contains("config: size 24"));
+    }
+
+    #[test]
+    fn inherits_cycles_are_found_through_other_themes() {
+        let graph: HashMap<&str, Vec<String>> = HashMap::from([
+            ("Koosh", vec!["Adwaita".to_string()]),
+            ("Adwaita", vec!["hicolor".to_string(), "Koosh-Base".to_string()]),
+            ("Koosh-Base", vec!["Koosh".to_string()]),
+            ("Self", vec!["Self".to_string()]),
+        ]);
+        let inherits_of = |name: &str| Ok(graph.get(name).cloned().unwrap_or_default());
+
+        assert_eq!(
+            find_inherits_cycle("Koosh", inherits_of).unwrap(),
+            Some(vec!["Koosh", "Adwaita", "Koosh-Base", "Koosh"].into_iter().map(String::from).collect()),
+        );
+        assert_eq!(find_inherits_cycle("Self", inherits_of).unwrap(), Some(vec!["Self".to_string(), "Self".to_string()]));
+        assert_eq!(find_inherits_cycle("hicolor", inherits_of).unwrap(), None);
+    }
+
+    #[test]
+    fn comment_placeholders_are_substituted() {
+        let ctx = CommentCtx { name: "Koosh".to_string(), sizes: vec![24, 32, 48], date: "2024-03-01".to_string() };
+
+        assert_eq!(
+            render_comment("{name} cursors ({sizes}) built {date}", &ctx),
+            "Koosh cursors (24, 32, 48) built 2024-03-01",
+        );
+        assert_eq!(render_comment("{name} {version} {", &ctx), "Koosh {version} {");
+    }
+
+    #[test]
+    fn civil_dates_from_unix_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn read_inherits_combines_both_theme_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("index.theme"), "[Icon Theme]\nInherits=Adwaita,hicolor\n").unwrap();
+        fs::write(dir.path().join("cursor.theme"), "[Icon Theme]\nInherits=Koosh\n").unwrap();
+
+        assert_eq!(read_inherits(dir.path()).unwrap(), ["Adwaita", "hicolor", "Koosh"]);
+    }
+
+    #[test]
+    fn display_name_is_written_as_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        create_theme_files(dir.path(), "Koosh-HiDPI", Some("Koosh Animated (HiDPI)"), "{name} cursors", None, &[])
+            .unwrap();
+
+        let index = IndexTheme::read(dir.path()).unwrap().unwrap();
+        assert_eq!(index.name.as_deref(), Some("Koosh Animated (HiDPI)"));
+        assert_eq!(index.comment.as_deref(), Some("Koosh Animated (HiDPI) cursors"));
+        let cursor_theme = fs::read_to_string(dir.path().join("cursor.theme")).unwrap();
+        assert!(cursor_theme.contains("Name=Koosh Animated (HiDPI)\n"));
+
+        create_hyprcursor_manifest(dir.path(), "Koosh-HiDPI", Some("Koosh Animated (HiDPI)"), "", "1.0", "cursors").unwrap();
+        let manifest = HyprManifest::read(dir.path().join("manifest.hl")).unwrap();
+        assert_eq!(manifest.get("name"), Some("Koosh Animated (HiDPI)"));
+    }
+
+    #[test]
+    fn multiple_inherits_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let inherits = vec!["Adwaita".to_string(), "hicolor".to_string()];
+        create_theme_files(dir.path(), "Test", None, "Test theme", None, &inherits).unwrap();
+
+        let content = fs::read_to_string(dir.path().join("index.theme")).unwrap();
+        assert!(content.contains("Inherits=Adwaita,hicolor\n"));
+        let cursor_theme = fs::read_to_string(dir.path().join("cursor.theme")).unwrap();
+        assert!(cursor_theme.contains("Inherits=Adwaita,hicolor\n"));
+        assert_eq!(IndexTheme::parse(&content).inherits, inherits);
+
+        create_index_theme(dir.path(), "Test", "Test theme", None, &[]).unwrap();
+        let index = IndexTheme::read(dir.path()).unwrap().unwrap();
+        assert_eq!(index.inherits, vec!["hicolor".to_string()]);
+    }
+
+    #[test]
+    fn index_theme_keeps_unknown_lines() {
+        let content = "# Made by hand\n[Icon Theme]\nName=Koosh\nName[de]=Kusch\nExample=left_ptr\n\n\
+            # Directory list\nDirectories=cursors\n\n[cursors]\nContext=Cursors\nType=Fixed\n\n\
+            [cursors/24]\nSize=24\nContext=Cursors\nType=Fixed\n\n[X-Koosh]\n# build notes\nVariant=light\n";
+
+        let mut index = IndexTheme::parse(content);
+        assert_eq!(index.inherits, Vec::<String>::new());
+        index.sizes = vec![32];
+        let written = index.to_string();
+
+        assert!(written.starts_with("# Made by hand\n[Icon Theme]\nName=Koosh\nName[de]=Kusch\nExample=left_ptr\n"));
+        assert!(!written.contains("Inherits="));
+        assert!(written.ends_with("[cursors/32]\nSize=32\nContext=Cursors\nType=Fixed\n\n[X-Koosh]\n# build notes\nVariant=light\n"));
+        assert!(!written.contains("[cursors/24]"));
+        assert_eq!(written.matches("# Directory list").count(), 1);
+        assert_eq!(IndexTheme::parse(&written).to_string(), written);
+    }
+
+    #[test]
+    fn manifest_set_adds_missing_keys_and_keeps_others() {
+        let mut manifest = HyprManifest::parse(
+            "# generated\nname=Old\ndescription   =  old text\ncursors_directory = hyprcursors\n",
+        );
+
+        manifest.set("name", "New");
+        manifest.set("description", "New text");
+        manifest.set("version", "1.0");
+
+        assert_eq!(
+            manifest.to_string(),
+            "# generated\nname = New\ndescription = New text\ncursors_directory = hyprcursors\nversion = 1.0\n",
+        );
+        assert_eq!(manifest.get("cursors_directory"), Some("hyprcursors"));
+    }
+
+    #[test]
+    fn theme_versions_round_trip_through_the_manifest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let version = check_theme_version("  2.1.0-beta+build=7 ").unwrap();
+        create_hyprcursor_manifest(dir.path(), "Koosh", None, "", &version, "cursors").unwrap();
+        let manifest = HyprManifest::read(dir.path().join("manifest.hl")).unwrap();
+        assert_eq!(manifest.get("version"), Some("2.1.0-beta+build=7"));
+
+        assert!(check_theme_version(" ").is_err());
+        assert!(check_theme_version("1.0\nname = Evil").is_err());
+        assert!(check_theme_version("1.0 # beta").is_err());
+    }
+
+    #[test]
+    fn theme_comments_cannot_add_keys_or_cut_the_description() {
+        assert_eq!(check_theme_comment("  Drawn by hand ").unwrap(), "Drawn by hand");
+        assert_eq!(check_theme_comment("").unwrap(), "");
+        assert!(check_theme_comment("Mine\nInherits=Evil").is_err());
+        assert!(check_theme_comment("Theme #2").is_err());
+    }
+
+    #[test]
+    fn manifest_set_collapses_duplicate_keys() {
+        let mut manifest = HyprManifest::parse("name = A\nname = B\n");
+
+        manifest.set("name", "C");
+
+        assert_eq!(manifest.to_string(), "name = C\n");
+    }
 }
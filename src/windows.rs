@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Cursor roles in the order Windows stores them in a scheme's registry value
+///
+/// Names match the keys of
+/// [`get_windows_to_x11_mapping`](crate::cursor_mapping::get_windows_to_x11_mapping).
+/// Older schemes stop after `Link`.
+pub const SCHEME_ROLES: &[&str] = &[
+    "Normal",
+    "Help",
+    "Working",
+    "Busy",
+    "Precision",
+    "Text",
+    "Handwriting",
+    "Unavailable",
+    "Vertical",
+    "Horizontal",
+    "Diagonal1",
+    "Diagonal2",
+    "Move",
+    "Alternate",
+    "Link",
+    "Pin",
+    "Person",
+];
+
+/// Registry key that scheme lines are written under
+const SCHEMES_KEY: &str = r"control panel\cursors\schemes";
+
+/// Read the file → role mapping of a cursor pack's `install.inf`
+///
+/// The role of each file comes from its position in the `Schemes` registry
+/// line, with `%name%` references resolved from `[Strings]`. When the inf has
+/// a `[Scheme.Cur]` file list, file names are spelled as listed there and
+/// files missing from it are ignored.
+pub fn parse_inf_scheme(inf: &Path) -> Result<HashMap<String, String>> {
+    let content = fs::read(inf).with_context(|| format!("Failed to read {:?}", inf))?;
+    // Some packs are saved as Latin-1 rather than UTF-8
+    let content = String::from_utf8_lossy(&content);
+    parse_inf_scheme_str(&content).with_context(|| format!("Invalid cursor scheme in {:?}", inf))
+}
+
+/// [`parse_inf_scheme`] on the text of an inf file
+pub fn parse_inf_scheme_str(content: &str) -> Result<HashMap<String, String>> {
+    let mut strings = HashMap::new();
+    let mut scheme_files: Option<Vec<String>> = None;
+    let mut scheme_value = None;
+    let mut section = String::new();
+
+    for line in content.lines() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_ascii_lowercase();
+            continue;
+        }
+
+        match section.as_str() {
+            "strings" => {
+                if let Some((key, value)) = line.split_once('=') {
+                    strings.insert(key.trim().to_ascii_lowercase(), unquote(value.trim()));
+                }
+            }
+            "scheme.cur" => {
+                let file = unquote(line);
+                if !file.is_empty() {
+                    scheme_files.get_or_insert_with(Vec::new).push(file);
+                }
+            }
+            _ => {
+                let fields = split_fields(line);
+                let is_scheme = fields.get(1)
+                    .is_some_and(|key| unquote(key).to_ascii_lowercase() == SCHEMES_KEY);
+                if is_scheme {
+                    if let Some(value) = fields.last() {
+                        scheme_value = Some(unquote(value));
+                    }
+                }
+            }
+        }
+    }
+
+    let scheme_value = scheme_value
+        .ok_or_else(|| anyhow::anyhow!("no Control Panel\\Cursors\\Schemes registry line"))?;
+    let scheme_value = expand_strings(&scheme_value, &strings);
+
+    let mut mapping = HashMap::new();
+    for (path, role) in scheme_value.split(',').zip(SCHEME_ROLES) {
+        let file = path.trim().rsplit(['\\', '/']).next().unwrap_or_default();
+        if file.is_empty() {
+            continue;
+        }
+        let file = match &scheme_files {
+            Some(files) => match files.iter().find(|listed| listed.eq_ignore_ascii_case(file)) {
+                Some(listed) => listed.clone(),
+                None => continue,
+            },
+            None => file.to_string(),
+        };
+        mapping.insert(file, role.to_string());
+    }
+
+    if mapping.is_empty() {
+        return Err(anyhow::anyhow!("the scheme names no cursor files"));
+    }
+    Ok(mapping)
+}
+
+/// Drop a `;` comment, ignoring semicolons inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Split a line on commas outside quotes
+fn split_fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(line[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(line[start..].trim());
+    fields
+}
+
+/// Remove surrounding quotes; `""` inside a quoted value is a literal quote
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => value.to_string(),
+    }
+}
+
+/// Replace `%name%` with its `[Strings]` value; unknown names (like `%10%`) are dropped
+fn expand_strings(value: &str, strings: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        if let Some(expansion) = strings.get(&after[..end].to_ascii_lowercase()) {
+            out.push_str(expansion);
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INF: &str = r#"; Koosh cursor pack
+[Version]
+signature="$CHICAGO$"
+
+[DefaultInstall]
+CopyFiles = Scheme.Cur
+AddReg    = Scheme.Reg
+
+[Scheme.Reg]
+HKCU,"Control Panel\Cursors\Schemes","%SCHEME_NAME%",,"%10%\%CUR_DIR%\%pointer%,%10%\%CUR_DIR%\%help%,%10%\%CUR_DIR%\%work%,%10%\%CUR_DIR%\%busy%,,%10%\%CUR_DIR%\%text%"
+
+[Scheme.Cur]
+"Normal.ani"   ; the arrow
+"Help.cur"
+"Working; In Background.ani"
+Busy.ani
+
+[Strings]
+CUR_DIR     = "Cursors\Koosh"
+SCHEME_NAME = "Koosh"
+pointer     = "normal.ani"
+help        = "Help.cur"
+work        = "Working; In Background.ani"
+busy        = "Busy.ani"
+text        = "Text.cur"
+"#;
+
+    #[test]
+    fn scheme_line_positions_give_roles() {
+        let mapping = parse_inf_scheme_str(INF).unwrap();
+
+        assert_eq!(mapping.get("Normal.ani").map(String::as_str), Some("Normal"));
+        assert_eq!(mapping.get("Help.cur").map(String::as_str), Some("Help"));
+        assert_eq!(mapping.get("Working; In Background.ani").map(String::as_str), Some("Working"));
+        assert_eq!(mapping.get("Busy.ani").map(String::as_str), Some("Busy"));
+        // Not in [Scheme.Cur]
+        assert!(!mapping.contains_key("Text.cur"));
+        assert_eq!(mapping.len(), 4);
+    }
+
+    #[test]
+    fn inf_without_a_scheme_line_is_an_error() {
+        let err = parse_inf_scheme_str("[Scheme.Cur]\nNormal.ani\n").unwrap_err();
+        assert!(err.to_string().contains("Schemes registry line"), "{}", err);
+    }
+}
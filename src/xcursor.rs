@@ -0,0 +1,339 @@
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::fs;
+use std::path::Path;
+
+/// Magic bytes at the start of every XCursor file
+pub const XCURSOR_MAGIC: &[u8; 4] = b"Xcur";
+
+/// Chunk type of an image chunk in the table of contents
+const IMAGE_CHUNK_TYPE: u32 = 0xfffd_0002;
+
+/// Size of the file header, and the version written to it
+const FILE_HEADER_SIZE: u32 = 16;
+const FILE_VERSION: u32 = 0x0001_0000;
+
+/// Size of an image chunk header, and the version written to it
+const IMAGE_HEADER_SIZE: u32 = 36;
+const IMAGE_VERSION: u32 = 1;
+
+/// A single image chunk from an XCursor file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XcursorImage {
+    pub nominal_size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    /// Frame delay in milliseconds
+    pub delay: u32,
+    /// Premultiplied ARGB pixels, row by row
+    pub pixels: Vec<u32>,
+}
+
+/// The images contained in an XCursor file, in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Xcursor {
+    pub images: Vec<XcursorImage>,
+}
+
+impl Xcursor {
+    /// Read and parse an XCursor file
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read cursor file: {:?}", path))?;
+        Self::parse(&data)
+            .with_context(|| format!("Invalid XCursor file: {:?}", path))
+    }
+
+    /// Parse XCursor data from memory
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.get(..4) != Some(XCURSOR_MAGIC.as_slice()) {
+            return Err(anyhow::anyhow!("Missing Xcur magic"));
+        }
+
+        let header_size = read_u32(data, 4)? as usize;
+        let ntoc = read_u32(data, 12)? as usize;
+
+        let mut images = Vec::new();
+        for i in 0..ntoc {
+            let toc = header_size + i * 12;
+            let chunk_type = read_u32(data, toc)?;
+            let position = read_u32(data, toc + 8)? as usize;
+
+            if chunk_type == IMAGE_CHUNK_TYPE {
+                images.push(parse_image(data, position)?);
+            }
+        }
+
+        Ok(Self { images })
+    }
+
+    /// Encode as XCursor file data, with the images in order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let toc_size = 12 * self.images.len() as u32;
+        let mut data = Vec::new();
+        data.extend_from_slice(XCURSOR_MAGIC);
+        for value in [FILE_HEADER_SIZE, FILE_VERSION, self.images.len() as u32] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut position = FILE_HEADER_SIZE + toc_size;
+        for image in &self.images {
+            for value in [IMAGE_CHUNK_TYPE, image.nominal_size, position] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            position += IMAGE_HEADER_SIZE + 4 * image.pixels.len() as u32;
+        }
+
+        for image in &self.images {
+            let header = [
+                IMAGE_HEADER_SIZE,
+                IMAGE_CHUNK_TYPE,
+                image.nominal_size,
+                IMAGE_VERSION,
+                image.width,
+                image.height,
+                image.xhot,
+                image.yhot,
+                image.delay,
+            ];
+            for value in header.into_iter().chain(image.pixels.iter().copied()) {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    /// Write an XCursor file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes())
+            .with_context(|| format!("Failed to write cursor file: {:?}", path))
+    }
+
+    /// Distinct nominal sizes, sorted ascending
+    pub fn nominal_sizes(&self) -> Vec<u32> {
+        let mut sizes: Vec<u32> = self.images.iter().map(|img| img.nominal_size).collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    /// The animation frames for one nominal size, in file order
+    pub fn frames(&self, nominal_size: u32) -> Vec<&XcursorImage> {
+        self.images.iter().filter(|img| img.nominal_size == nominal_size).collect()
+    }
+
+    /// Number of animation frames: the most frames any nominal size has
+    pub fn frame_count(&self) -> usize {
+        self.nominal_sizes().into_iter()
+            .map(|size| self.frames(size).len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Whether any nominal size has more than one frame
+    pub fn is_animated(&self) -> bool {
+        self.frame_count() > 1
+    }
+
+    /// "animated (N frames)" or "static (1 frame)"
+    pub fn animation_label(&self) -> String {
+        match self.frame_count() {
+            1 => "static (1 frame)".to_string(),
+            count if count > 1 => format!("animated ({} frames)", count),
+            _ => "static (no frames)".to_string(),
+        }
+    }
+}
+
+/// Whether the cursor file at `cursor` is animated
+pub fn is_animated(cursor: &Path) -> Result<bool> {
+    Ok(Xcursor::read(cursor)?.is_animated())
+}
+
+impl XcursorImage {
+    /// Build an image from straight-alpha RGBA pixels, premultiplying them
+    pub fn from_rgba(img: &RgbaImage, nominal_size: u32, hotspot: (u32, u32), delay: u32) -> Self {
+        let pixels = img.pixels()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.0;
+                let premultiply = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+                u32::from_be_bytes([a, premultiply(r), premultiply(g), premultiply(b)])
+            })
+            .collect();
+
+        Self {
+            nominal_size,
+            width: img.width(),
+            height: img.height(),
+            xhot: hotspot.0,
+            yhot: hotspot.1,
+            delay,
+            pixels,
+        }
+    }
+
+    /// Convert the premultiplied ARGB pixels to a straight-alpha RGBA image
+    pub fn to_rgba(&self) -> RgbaImage {
+        let mut img = RgbaImage::new(self.width, self.height);
+        for (pixel, &argb) in img.pixels_mut().zip(&self.pixels) {
+            let [a, r, g, b] = argb.to_be_bytes();
+            let unpremultiply = |c: u8| match a {
+                0 => 0,
+                a => ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+            };
+            *pixel = image::Rgba([unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+        }
+        img
+    }
+}
+
+fn parse_image(data: &[u8], position: usize) -> Result<XcursorImage> {
+    let header_size = read_u32(data, position)? as usize;
+    if read_u32(data, position + 4)? != IMAGE_CHUNK_TYPE {
+        return Err(anyhow::anyhow!("Table of contents points at a non-image chunk"));
+    }
+
+    let width = read_u32(data, position + 16)?;
+    let height = read_u32(data, position + 20)?;
+    let pixel_start = position + header_size;
+    let pixel_count = width as usize * height as usize;
+
+    let pixels = (0..pixel_count)
+        .map(|i| read_u32(data, pixel_start + i * 4))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(XcursorImage {
+        nominal_size: read_u32(data, position + 8)?,
+        width,
+        height,
+        xhot: read_u32(data, position + 24)?,
+        yhot: read_u32(data, position + 28)?,
+        delay: read_u32(data, position + 32)?,
+        pixels,
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow::anyhow!("Truncated XCursor data at offset {}", offset))
+}
+
+/// Detect the native size of the cursors in a directory
+///
+/// Looks at `left_ptr`, or the first real cursor file by name when there is
+/// none. The native size is the largest nominal size the cursor contains.
+/// Returns `None` when the directory has no cursor to inspect.
+pub fn detect_native_size(cursors_dir: &Path) -> Result<Option<u32>> {
+    let left_ptr = cursors_dir.join("left_ptr");
+    let candidate = if left_ptr.is_file() {
+        Some(left_ptr)
+    } else {
+        let mut files: Vec<_> = fs::read_dir(cursors_dir)
+            .with_context(|| format!("Failed to read cursors directory: {:?}", cursors_dir))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && !path.is_symlink())
+            .collect();
+        files.sort();
+        files.into_iter().next()
+    };
+
+    match candidate {
+        Some(path) => Ok(Xcursor::read(path)?.nominal_sizes().last().copied()),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn parses_fixture_cursor() {
+        let cursor = Xcursor::read(fixture("x11/cursors/left_ptr")).unwrap();
+
+        assert_eq!(cursor.images.len(), 1);
+        let image = &cursor.images[0];
+        assert_eq!((image.nominal_size, image.width, image.height), (24, 24, 24));
+        assert_eq!((image.xhot, image.yhot), (3, 3));
+        assert_eq!(image.pixels.len(), 24 * 24);
+        assert_eq!(cursor.nominal_sizes(), vec![24]);
+        assert!(!cursor.is_animated());
+        assert_eq!(cursor.animation_label(), "static (1 frame)");
+    }
+
+    #[test]
+    fn multiple_frames_at_one_size_are_animated() {
+        let image = |nominal_size| XcursorImage {
+            nominal_size,
+            width: 1,
+            height: 1,
+            xhot: 0,
+            yhot: 0,
+            delay: 50,
+            pixels: vec![0],
+        };
+
+        // One frame per size is a static multi-size cursor
+        let multi_size = Xcursor { images: vec![image(24), image(32)] };
+        assert!(!multi_size.is_animated());
+
+        let animated = Xcursor { images: vec![image(24), image(24), image(24), image(32)] };
+        assert!(animated.is_animated());
+        assert_eq!(animated.animation_label(), "animated (3 frames)");
+    }
+
+    #[test]
+    fn converts_premultiplied_pixels_to_rgba() {
+        let image = XcursorImage {
+            nominal_size: 1,
+            width: 2,
+            height: 1,
+            xhot: 0,
+            yhot: 0,
+            delay: 0,
+            pixels: vec![0x80_40_00_80, 0x00_00_00_00],
+        };
+
+        let rgba = image.to_rgba();
+        assert_eq!(rgba.get_pixel(0, 0).0, [128, 0, 255, 128]);
+        assert_eq!(rgba.get_pixel(1, 0).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn written_cursors_parse_back() {
+        let mut rgba = RgbaImage::new(3, 2);
+        rgba.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        rgba.put_pixel(2, 1, image::Rgba([128, 0, 255, 128]));
+        let cursor = Xcursor {
+            images: vec![
+                XcursorImage::from_rgba(&rgba, 3, (1, 1), 0),
+                XcursorImage::from_rgba(&rgba, 6, (2, 2), 40),
+            ],
+        };
+
+        let parsed = Xcursor::parse(&cursor.to_bytes()).unwrap();
+        assert_eq!(parsed, cursor);
+        assert_eq!(parsed.images[0].pixels[0], 0xff_ff_00_00);
+        assert_eq!(parsed.images[0].to_rgba(), rgba);
+    }
+
+    #[test]
+    fn rejects_non_xcursor_data() {
+        assert!(Xcursor::parse(b"not a cursor").is_err());
+        assert!(Xcursor::parse(b"Xcur\x10\x00\x00\x00").is_err());
+    }
+
+    #[test]
+    fn detects_native_size_from_left_ptr() {
+        assert_eq!(detect_native_size(&fixture("x11/cursors")).unwrap(), Some(24));
+    }
+}
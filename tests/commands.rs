@@ -0,0 +1,2023 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Path to a committed test fixture
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+/// A scratch directory with its own HOME so installs never touch ~/.icons
+struct Sandbox {
+    root: TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let root = TempDir::new().unwrap();
+        fs::create_dir_all(root.path().join("home")).unwrap();
+        fs::create_dir_all(root.path().join("work")).unwrap();
+        Self { root }
+    }
+
+    fn home(&self) -> PathBuf {
+        self.root.path().join("home")
+    }
+
+    fn work(&self) -> PathBuf {
+        self.root.path().join("work")
+    }
+
+    fn icons(&self) -> PathBuf {
+        self.home().join(".icons")
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::cargo_bin("koosh-cursor-tools").unwrap();
+        cmd.current_dir(self.work())
+            .env("HOME", self.home())
+            .env_remove("KOOSH_ICONS_DIR")
+            .env_remove("KOOSH_SYSTEM_ICONS_DIR")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("XCURSOR_PATH")
+            .env("TMPDIR", self.root.path());
+        cmd
+    }
+}
+
+/// A `bin` directory whose `hyprcursor-util` extracts a single `left_ptr` shape
+///
+/// `None` where `which`, which finds the tool, isn't available to link in.
+fn fake_hyprcursor_util(sandbox: &Sandbox) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let which = Path::new("/usr/bin/which");
+    if !which.exists() {
+        return None;
+    }
+    let bin = sandbox.work().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    std::os::unix::fs::symlink(which, bin.join("which")).unwrap();
+    fs::write(
+        bin.join("hyprcursor-util"),
+        concat!(
+            "#!/bin/sh\n",
+            "dir=\"$4/extracted_${2##*/}\"\n",
+            "/bin/mkdir -p \"$dir/hyprcursors/left_ptr\"\n",
+            "printf 'name = Extracted\\ncursors_directory = hyprcursors\\n' > \"$dir/manifest.hl\"\n",
+            "printf 'hotspot_x = 0.0\\nhotspot_y = 0.0\\ndefine_size = 24, left_ptr.png\\n' > \"$dir/hyprcursors/left_ptr/meta.hl\"\n",
+            "printf 'png' > \"$dir/hyprcursors/left_ptr/left_ptr.png\"\n",
+        ),
+    ).unwrap();
+    fs::set_permissions(bin.join("hyprcursor-util"), fs::Permissions::from_mode(0o755)).unwrap();
+    Some(bin)
+}
+
+fn assert_link(path: &Path, target: &str) {
+    assert!(path.is_symlink(), "{:?} should be a symlink", path);
+    assert_eq!(fs::read_link(path).unwrap(), PathBuf::from(target));
+}
+
+#[test]
+fn rename_cursors_builds_and_installs_x11_theme() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Test-X11", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Test-X11/cursors");
+    for (windows, x11) in [("Normal", "left_ptr"), ("Person", "pointer"), ("Text", "text"), ("Busy", "wait"), ("Move", "move")] {
+        let copied = cursors.join(x11);
+        assert!(copied.is_file() && !copied.is_symlink(), "{} should be a real file", x11);
+        assert_eq!(fs::read(&copied).unwrap(), fs::read(fixture("windows").join(windows)).unwrap());
+    }
+    assert!(!cursors.join("Unmapped").exists());
+
+    assert_link(&cursors.join("arrow"), "left_ptr");
+    assert_link(&cursors.join("hand2"), "pointer");
+    assert_link(&cursors.join("xterm"), "text");
+    assert_link(&cursors.join("watch"), "wait");
+    assert_link(&cursors.join("fleur"), "move");
+    assert!(!cursors.join("cross").exists(), "links to missing targets must not be created");
+
+    let index = fs::read_to_string(sandbox.work().join("Test-X11/index.theme")).unwrap();
+    assert!(index.contains("Name=Test-X11"));
+    assert!(index.contains("Directories=cursors"));
+    // The declared sizes are those the copied cursors hold
+    let mut sizes: Vec<u32> = ["Normal", "Person", "Text", "Busy", "Move"].iter()
+        .flat_map(|name| koosh_cursor_tools::xcursor::Xcursor::read(fixture("windows").join(name)).unwrap().nominal_sizes())
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+    assert!(!sizes.is_empty());
+    assert_eq!(koosh_cursor_tools::theme_config::IndexTheme::parse(&index).sizes, sizes);
+    assert!(sandbox.work().join("Test-X11/cursor.theme").is_file());
+
+    let installed = sandbox.icons().join("Test-X11");
+    assert!(installed.join("cursors/left_ptr").is_file());
+    assert!(installed.join("index.theme").is_file());
+}
+
+#[test]
+fn add_links_copies_source_and_creates_aliases() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Test-Complete", "--size", "32", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("env = XCURSOR_SIZE,32"))
+        .stdout(predicates::str::contains("    size = 32"))
+        .stdout(predicates::str::contains("native size of these cursor files is 24"));
+
+    let theme = sandbox.work().join("Test-Complete");
+    let cursors = theme.join("cursors");
+    for name in ["left_ptr", "pointer", "text", "wait"] {
+        assert!(cursors.join(name).is_file() && !cursors.join(name).is_symlink());
+    }
+
+    assert_link(&cursors.join("default"), "left_ptr");
+    assert_link(&cursors.join("hand1"), "pointer");
+    assert_link(&cursors.join("ibeam"), "text");
+    assert_link(&cursors.join("watch"), "wait");
+
+    let index = fs::read_to_string(theme.join("index.theme")).unwrap();
+    assert!(index.contains("Name=Test-Complete"));
+    assert!(index.contains("Comment=Koosh cursor theme with all necessary symlinks"));
+
+    let installed = sandbox.icons().join("Test-Complete");
+    assert!(installed.join("cursors/left_ptr").is_file());
+    assert!(installed.join("cursors/default").exists());
+    assert!(installed.join("index.theme").is_file());
+}
+
+#[test]
+fn display_name_is_written_to_theme_files_only() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Test-Display", "--display-name", "Koosh Display (HiDPI)", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("env = XCURSOR_THEME,Test-Display"));
+
+    let theme = sandbox.work().join("Test-Display");
+    let index = fs::read_to_string(theme.join("index.theme")).unwrap();
+    assert!(index.contains("Name=Koosh Display (HiDPI)\n"));
+    let cursor_theme = fs::read_to_string(theme.join("cursor.theme")).unwrap();
+    assert!(cursor_theme.contains("Name=Koosh Display (HiDPI)\n"));
+    assert!(sandbox.icons().join("Test-Display/index.theme").is_file());
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Test-Display", "--display-name", "Bad\nInherits=evil", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .failure();
+}
+
+#[test]
+fn rename_cursors_wraps_png_sources_into_cursors() {
+    use koosh_cursor_tools::xcursor::Xcursor;
+
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("pngs");
+    fs::create_dir_all(&input).unwrap();
+    image::RgbaImage::from_pixel(32, 32, image::Rgba([0, 0, 0, 255]))
+        .save(input.join("Normal.png"))
+        .unwrap();
+    image::RgbaImage::new(48, 48).save(input.join("Person.png")).unwrap();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Test-Png", "--source-format", "png", "--input-dir"])
+        .arg(&input)
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Test-Png/cursors");
+    let left_ptr = Xcursor::read(cursors.join("left_ptr")).unwrap();
+    assert_eq!(left_ptr.nominal_sizes(), vec![32]);
+    assert_eq!((left_ptr.images[0].xhot, left_ptr.images[0].yhot), (4, 4));
+    assert_eq!(left_ptr.images[0].pixels[0], 0xff_00_00_00);
+    assert_eq!(Xcursor::read(cursors.join("pointer")).unwrap().nominal_sizes(), vec![48]);
+    assert_link(&cursors.join("arrow"), "left_ptr");
+
+    // The default format refuses images instead of copying them as cursors
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Test-Png", "--input-dir"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--source-format png"));
+}
+
+#[test]
+fn rename_cursors_finds_nested_sources() {
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("scheme");
+    fs::create_dir_all(input.join("pointers/text")).unwrap();
+    fs::copy(fixture("windows/Normal"), input.join("Normal")).unwrap();
+    fs::copy(fixture("windows/Person"), input.join("pointers/Person")).unwrap();
+    fs::copy(fixture("windows/Text"), input.join("pointers/text/Text")).unwrap();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Nested", "--max-depth", "2", "--input-dir"])
+        .arg(&input)
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Nested/cursors");
+    assert!(cursors.join("left_ptr").is_file());
+    assert!(cursors.join("pointer").is_file());
+    assert!(!cursors.join("text").exists(), "--max-depth 2 must not reach pointers/text");
+
+    // Two sources for one cursor are refused rather than overwritten
+    fs::copy(fixture("windows/Person"), input.join("Person")).unwrap();
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Nested", "--input-dir"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("both map to pointer"));
+}
+
+#[test]
+fn rename_cursors_rejects_missing_input() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["rename-cursors", "--input-dir", "does-not-exist"])
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn icons_dir_env_redirects_install() {
+    let sandbox = Sandbox::new();
+    let redirected = sandbox.root.path().join("redirected-icons");
+
+    sandbox.command()
+        .env("KOOSH_ICONS_DIR", &redirected)
+        .args(["rename-cursors", "--output-theme", "Test-X11", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+
+    assert!(redirected.join("Test-X11/cursors/left_ptr").is_file());
+    assert!(!sandbox.icons().join("Test-X11").exists());
+}
+
+#[test]
+fn icons_dir_env_takes_precedence_over_flag() {
+    let sandbox = Sandbox::new();
+    let from_env = sandbox.root.path().join("env-icons");
+    let from_flag = sandbox.root.path().join("flag-icons");
+
+    sandbox.command()
+        .env("KOOSH_ICONS_DIR", &from_env)
+        .arg("--icons-dir")
+        .arg(&from_flag)
+        .args(["rename-cursors", "--output-theme", "Test-X11", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+
+    assert!(from_env.join("Test-X11/index.theme").is_file());
+    assert!(!from_flag.exists());
+}
+
+#[test]
+fn theme_dir_redirects_local_builds() {
+    let sandbox = Sandbox::new();
+    let builds = sandbox.root.path().join("builds");
+
+    sandbox.command()
+        .arg("--theme-dir")
+        .arg(&builds)
+        .args(["rename-cursors", "--output-theme", "Based-X11", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+    assert!(builds.join("Based-X11/cursors/left_ptr").is_file());
+    assert!(!sandbox.work().join("Based-X11").exists());
+
+    sandbox.command()
+        .arg("--theme-dir")
+        .arg(&builds)
+        .args(["create-animated", "--output-theme", "Based-Animated", "--input-theme"])
+        .arg(builds.join("Based-X11"))
+        .assert()
+        .success();
+    assert!(builds.join("Based-Animated/index.theme").is_file());
+    assert!(sandbox.icons().join("Based-Animated/cursors/left_ptr").exists());
+    assert!(!sandbox.work().join("Based-Animated").exists());
+}
+
+#[test]
+fn install_scope_both_installs_to_user_and_system_dirs() {
+    let sandbox = Sandbox::new();
+    let system = sandbox.root.path().join("system-icons");
+
+    let output = sandbox.command()
+        .env("KOOSH_SYSTEM_ICONS_DIR", &system)
+        .args(["--json", "--install-scope", "both", "rename-cursors", "--output-theme", "Shared", "--input-dir"])
+        .arg(fixture("windows"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let user = sandbox.icons().join("Shared");
+    assert_eq!(report["install_paths"], serde_json::json!([user, system.join("Shared")]));
+    assert!(system.join("Shared/cursors/left_ptr").is_file());
+
+    // An unwritable system dir only costs a warning
+    let blocked = sandbox.root.path().join("not-a-dir");
+    fs::write(&blocked, "").unwrap();
+    sandbox.command()
+        .env("KOOSH_SYSTEM_ICONS_DIR", &blocked)
+        .args(["--install-scope", "both", "rename-cursors", "--output-theme", "Shared", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Skipping system install"));
+
+    sandbox.command()
+        .env("KOOSH_SYSTEM_ICONS_DIR", &blocked)
+        .args(["--install-scope", "system", "rename-cursors", "--output-theme", "Shared", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .failure();
+}
+
+#[test]
+fn missing_input_theme_is_a_typed_error() {
+    use koosh_cursor_tools::commands::create_animated::{create_animated_theme, CreateAnimatedArgs};
+    use koosh_cursor_tools::{CursorError, LinkMode};
+
+    let sandbox = Sandbox::new();
+    let missing = sandbox.work().join("No-Such-Theme");
+
+    let err = create_animated_theme(CreateAnimatedArgs {
+        input_themes: vec![missing.to_string_lossy().into_owned()],
+        output_theme: "Unused".to_string(),
+        display_name: None,
+        temp_dir: None,
+        theme_dir: None,
+        install_scope: Default::default(),
+        jobs: None,
+        preserve_times: false,
+        command_timeout: None,
+        retries: 0,
+        verify: true,
+        recolor: None,
+        variants: Vec::new(),
+        shadow: None,
+        relink: false,
+        preserve_source_links: false,
+        link_mode: LinkMode::Symlink,
+        emit_build_manifest: false,
+        frame_delay_ms: 100,
+        delay_scale: 1.0,
+        progress: false,
+        fail_fast: false,
+        filter: Default::default(),
+        source_kind: Default::default(),
+        hotspot_overrides: Default::default(),
+        comment: None,
+        inherits: Vec::new(),
+        force: false,
+        normalize_names: false,
+        out_format: Default::default(),
+        resize_algorithm: None,
+        theme_version: "1.0".to_string(),
+        sizes: None,
+        exclude_sizes: Vec::new(),
+        source_size: None,
+        xcursorgen_config: None,
+        frame_depth: Default::default(),
+    })
+    .unwrap_err();
+
+    match err.downcast_ref::<CursorError>() {
+        Some(CursorError::ThemeNotFound(path)) => assert_eq!(path, &missing),
+        other => panic!("expected ThemeNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn dedupe_links_identical_cursors() {
+    let sandbox = Sandbox::new();
+    let cursors = sandbox.work().join("Dup-Theme/cursors");
+    fs::create_dir_all(&cursors).unwrap();
+    fs::write(cursors.join("wait"), b"same bytes").unwrap();
+    fs::write(cursors.join("progress"), b"same bytes").unwrap();
+    fs::write(cursors.join("watch"), b"same bytes").unwrap();
+    fs::write(cursors.join("left_ptr"), b"unique").unwrap();
+    std::os::unix::fs::symlink("left_ptr", cursors.join("arrow")).unwrap();
+
+    sandbox.command()
+        .args(["dedupe", "Dup-Theme"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("reclaimed 20 bytes"));
+
+    assert!(cursors.join("progress").is_file() && !cursors.join("progress").is_symlink());
+    assert_link(&cursors.join("wait"), "progress");
+    assert_link(&cursors.join("watch"), "progress");
+    assert!(!cursors.join("left_ptr").is_symlink());
+    assert_link(&cursors.join("arrow"), "left_ptr");
+}
+
+#[test]
+fn relink_only_adds_missing_aliases() {
+    let sandbox = Sandbox::new();
+    let cursors = sandbox.icons().join("Hand-Edited/cursors");
+    fs::create_dir_all(&cursors).unwrap();
+    fs::write(cursors.join("left_ptr"), b"arrow").unwrap();
+    fs::write(cursors.join("text"), b"ibeam").unwrap();
+    std::os::unix::fs::symlink("left_ptr", cursors.join("xterm")).unwrap();
+
+    let output = sandbox.command()
+        .args(["--json", "relink", "Hand-Edited"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let created = report["created"].as_array().unwrap();
+
+    assert!(created.contains(&"arrow".into()) && created.contains(&"ibeam".into()));
+    assert!(!created.contains(&"xterm".into()));
+    assert_link(&cursors.join("ibeam"), "text");
+    assert_link(&cursors.join("xterm"), "left_ptr");
+    assert!(!cursors.join("hand2").exists());
+}
+
+#[test]
+fn minimize_keeps_cursors_and_their_aliases() {
+    let sandbox = Sandbox::new();
+    let cursors = sandbox.icons().join("Full/cursors");
+    fs::create_dir_all(&cursors).unwrap();
+    fs::write(cursors.join("left_ptr"), b"arrow").unwrap();
+    fs::write(cursors.join("hand2"), b"hand").unwrap();
+    fs::write(cursors.join("crosshair"), [0u8; 4096]).unwrap();
+    std::os::unix::fs::symlink("left_ptr", cursors.join("default")).unwrap();
+    std::os::unix::fs::symlink("hand2", cursors.join("pointer")).unwrap();
+    std::os::unix::fs::symlink("pointer", cursors.join("hand1")).unwrap();
+    std::os::unix::fs::symlink("crosshair", cursors.join("cross")).unwrap();
+    let dest = sandbox.work().join("Full-Min");
+
+    let output = sandbox.command()
+        .args(["--json", "minimize", "Full"])
+        .arg(&dest)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(report["cursors"], serde_json::json!(["hand2", "left_ptr"]));
+    assert_eq!(report["links"], serde_json::json!(["default", "hand1", "pointer"]));
+    assert_eq!(report["missing"], serde_json::json!(["text", "wait", "watch"]));
+    assert!(report["bytes"].as_u64().unwrap() < report["source_bytes"].as_u64().unwrap());
+    assert_link(&dest.join("cursors/hand1"), "hand2");
+    assert!(!dest.join("cursors/crosshair").exists());
+    assert!(dest.join("index.theme").exists());
+}
+
+#[test]
+fn verify_install_accepts_a_faithful_install() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["--verify-install", "add-links", "--theme-name", "Verified", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success();
+
+    assert_link(&sandbox.icons().join("Verified/cursors/arrow"), "left_ptr");
+}
+
+#[test]
+fn sync_reinstall_leaves_unchanged_files_alone() {
+    let sandbox = Sandbox::new();
+    let install = || {
+        sandbox.command()
+            .args(["--sync", "add-links", "--theme-name", "Synced", "--source-dir"])
+            .arg(fixture("x11/cursors"))
+            .assert()
+            .success();
+    };
+    install();
+
+    let installed = sandbox.icons().join("Synced");
+    let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(installed.join("cursors/left_ptr"), old).unwrap();
+    fs::write(installed.join("cursors/stale"), b"old").unwrap();
+    install();
+
+    let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(installed.join("cursors/left_ptr")).unwrap());
+    assert_eq!(mtime, old);
+    assert!(!installed.join("cursors/stale").exists());
+    assert!(installed.join("index.theme").is_file());
+}
+
+#[test]
+fn add_links_builds_in_output_dir() {
+    let sandbox = Sandbox::new();
+    let output_dir = sandbox.root.path().join("build");
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Test-Complete", "--output-dir"])
+        .arg(&output_dir)
+        .arg("--source-dir")
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success();
+
+    assert!(output_dir.join("Test-Complete/cursors/left_ptr").is_file());
+    assert!(!sandbox.work().join("Test-Complete").exists());
+}
+
+#[test]
+fn preserve_times_keeps_source_mtimes_through_install() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.root.path().join("source");
+    fs::create_dir_all(&source).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), source.join("left_ptr")).unwrap();
+    let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(source.join("left_ptr"), old).unwrap();
+
+    // Serial, parallel and --sync installs all keep it
+    for extra in [&[][..], &["--jobs", "4"], &["--sync"]] {
+        fs::remove_dir_all(sandbox.icons().join("Timed")).ok();
+        sandbox.command()
+            .args(["--preserve-times", "add-links", "--theme-name", "Timed", "--source-dir"])
+            .arg(&source)
+            .args(extra)
+            .assert()
+            .success();
+
+        for copy in [sandbox.work().join("Timed/cursors/left_ptr"), sandbox.icons().join("Timed/cursors/left_ptr")] {
+            let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(&copy).unwrap());
+            assert_eq!(mtime, old, "{:?} should keep the source mtime with {:?}", copy, extra);
+        }
+    }
+
+    // Without the flag copies are new
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Timed", "--source-dir"])
+        .arg(&source)
+        .assert()
+        .success();
+    let copy = sandbox.icons().join("Timed/cursors/left_ptr");
+    assert_ne!(filetime::FileTime::from_last_modification_time(&fs::metadata(&copy).unwrap()), old);
+}
+
+#[test]
+fn output_path_is_independent_of_theme_name() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.root.path().join("custom-location");
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Named-Theme", "--output"])
+        .arg(&output)
+        .arg("--input-dir")
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+
+    assert!(output.join("cursors/left_ptr").is_file());
+    let index = fs::read_to_string(output.join("index.theme")).unwrap();
+    assert!(index.contains("Name=Named-Theme"));
+    assert!(sandbox.icons().join("Named-Theme/cursors/left_ptr").is_file());
+}
+
+#[test]
+fn create_animated_variants_get_distinct_names() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Test-Animated", "--variants", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success();
+
+    for name in ["Test-Animated", "Test-Animated-Dark"] {
+        let theme = sandbox.work().join(name);
+        assert!(theme.join("cursors/left_ptr").is_file(), "{} should have cursors", name);
+        let index = fs::read_to_string(theme.join("index.theme")).unwrap();
+        assert!(index.contains(&format!("Name={}\n", name)));
+        assert!(sandbox.icons().join(name).join("cursors/left_ptr").is_file());
+    }
+}
+
+#[test]
+fn dark_variant_frames_are_recolored() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    // Stands in for xcursorgen: the "cursor" it writes is the scaled 24px frame
+    let bin = sandbox.work().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("xcursorgen"), "#!/bin/sh\n/bin/cp 24_000.png \"$2\"\n").unwrap();
+    fs::set_permissions(bin.join("xcursorgen"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let frames = sandbox.work().join("pngs/left_ptr");
+    fs::create_dir_all(&frames).unwrap();
+    image::RgbaImage::from_pixel(24, 24, image::Rgba([200, 200, 200, 255]))
+        .save(frames.join("frame_1.png"))
+        .unwrap();
+
+    let pixel = |theme: &str| {
+        let cursor = sandbox.work().join(theme).join("cursors/left_ptr");
+        *image::load_from_memory(&fs::read(cursor).unwrap()).unwrap().to_rgba8().get_pixel(12, 12)
+    };
+    let build = |theme: &str, extra: &[&str]| {
+        sandbox.command()
+            .env("PATH", &bin)
+            .args(["create-animated", "--output-theme", theme, "--no-verify", "--sizes", "24", "--variants"])
+            .args(extra)
+            .arg("--from-pngs")
+            .arg(sandbox.work().join("pngs"))
+            .assert()
+            .success();
+    };
+
+    build("Inverted", &[]);
+    assert_eq!(pixel("Inverted"), image::Rgba([200, 200, 200, 255]));
+    assert_eq!(pixel("Inverted-Dark"), image::Rgba([55, 55, 55, 255]));
+
+    build("Tinted", &["--dark-tint", "#ff0000"]);
+    assert_eq!(pixel("Tinted"), image::Rgba([200, 200, 200, 255]));
+    let tinted = pixel("Tinted-Dark");
+    assert!(tinted[0] > 150 && tinted[1] == 0 && tinted[2] == 0, "{:?}", tinted);
+}
+
+#[test]
+fn path_unsafe_theme_names_are_rejected() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "../escaped", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("path separator '/'"));
+
+    assert!(!sandbox.root.path().join("escaped").exists());
+    assert!(!sandbox.icons().exists());
+}
+
+#[test]
+fn later_input_sources_override_earlier_ones() {
+    let sandbox = Sandbox::new();
+    let overlay = sandbox.work().join("overlay");
+    fs::create_dir_all(&overlay).unwrap();
+    fs::copy(fixture("windows/Text"), overlay.join("Normal")).unwrap();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Merged-X11", "--input-dir"])
+        .arg(fixture("windows"))
+        .arg("--input-dir")
+        .arg(&overlay)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("overrides"));
+
+    let cursors = sandbox.work().join("Merged-X11/cursors");
+    assert_eq!(fs::read(cursors.join("left_ptr")).unwrap(), fs::read(fixture("windows/Text")).unwrap());
+    assert_eq!(fs::read(cursors.join("pointer")).unwrap(), fs::read(fixture("windows/Person")).unwrap());
+
+    let overlay_theme = sandbox.work().join("Overlay-X11");
+    fs::create_dir_all(overlay_theme.join("cursors")).unwrap();
+    fs::copy(fixture("x11/cursors/text"), overlay_theme.join("cursors/left_ptr")).unwrap();
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Merged-Animated", "--input-theme"])
+        .arg(cursors.parent().unwrap())
+        .arg("--input-theme")
+        .arg(&overlay_theme)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("overrides"));
+    assert!(sandbox.work().join("Merged-Animated/cursors/pointer").is_file());
+}
+
+#[test]
+fn source_index_theme_gives_default_name_and_comment() {
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("Described-X11");
+    fs::create_dir_all(input.join("cursors")).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), input.join("cursors/left_ptr")).unwrap();
+    fs::write(input.join("index.theme"), "[Icon Theme]\nName=Described Cursors\nComment=Drawn by hand\n").unwrap();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Inherited", "--input-theme"])
+        .arg(&input)
+        .assert()
+        .success();
+    let index = fs::read_to_string(sandbox.work().join("Inherited/index.theme")).unwrap();
+    assert!(index.contains("Name=Described Cursors\n"));
+    assert!(index.contains("Comment=Drawn by hand\n"));
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Overridden", "--display-name", "Mine", "--comment", "Tweaked", "--input-theme"])
+        .arg(&input)
+        .assert()
+        .success();
+    let index = fs::read_to_string(sandbox.work().join("Overridden/index.theme")).unwrap();
+    assert!(index.contains("Name=Mine\n"));
+    assert!(index.contains("Comment=Tweaked\n"));
+
+    // A Name that only repeats the source directory's name isn't carried over
+    fs::write(input.join("index.theme"), "[Icon Theme]\nName=Described-X11\n").unwrap();
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Renamed", "--input-theme"])
+        .arg(&input)
+        .assert()
+        .success();
+    let index = fs::read_to_string(sandbox.work().join("Renamed/index.theme")).unwrap();
+    assert!(index.contains("Name=Renamed\n"), "{}", index);
+
+    // Neither is one that isn't a valid display name, nor an index that can't be read
+    for (contents, warning) in [
+        (&b"[Icon Theme]\nName=Tab\there\n"[..], "Ignoring the Name of"),
+        (&b"[Icon Theme]\nName=\xff\n"[..], "Ignoring the index.theme of"),
+    ] {
+        fs::write(input.join("index.theme"), contents).unwrap();
+        sandbox.command()
+            .args(["create-animated", "--output-theme", "Unreadable", "--input-theme"])
+            .arg(&input)
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(warning));
+        let index = fs::read_to_string(sandbox.work().join("Unreadable/index.theme")).unwrap();
+        assert!(index.contains("Name=Unreadable\n"), "{}", index);
+
+        sandbox.command()
+            .args(["create-hyprcursor", "--source"])
+            .arg(&input)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(warning))
+            .stderr(predicate::str::contains("Required tool not found: hyprcursor-util"));
+    }
+}
+
+#[test]
+fn relink_replaces_stale_aliases() {
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("Stale-X11");
+    fs::create_dir_all(input.join("cursors")).unwrap();
+    for name in ["left_ptr", "pointer", "text", "wait"] {
+        fs::copy(fixture("x11/cursors").join(name), input.join("cursors").join(name)).unwrap();
+    }
+    std::os::unix::fs::symlink("text", input.join("cursors/arrow")).unwrap();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Kept", "--input-theme"])
+        .arg(&input)
+        .assert()
+        .success();
+    assert_link(&sandbox.work().join("Kept/cursors/arrow"), "text");
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Relinked", "--relink", "--input-theme"])
+        .arg(&input)
+        .assert()
+        .success();
+    let cursors = sandbox.work().join("Relinked/cursors");
+    assert_link(&cursors.join("arrow"), "left_ptr");
+    assert!(cursors.join("left_ptr").is_file() && !cursors.join("left_ptr").is_symlink());
+
+    // The alias now decodes to the arrow's images, not the text cursor's
+    let pixels = |path: PathBuf| {
+        koosh_cursor_tools::xcursor::Xcursor::read(path).unwrap().images.into_iter()
+            .map(|image| image.pixels)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(pixels(cursors.join("arrow")), pixels(cursors.join("left_ptr")));
+    assert_ne!(pixels(cursors.join("arrow")), pixels(cursors.join("text")));
+}
+
+#[test]
+fn aliases_with_missing_targets_are_reported() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Test-Partial", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"\d+ aliases skipped due to missing targets").unwrap())
+        .stdout(predicates::str::contains("cross: target crosshair does not exist"))
+        .stderr(predicates::str::contains("Skipping alias").not());
+
+    assert!(!sandbox.work().join("Test-Partial/cursors/cross").exists());
+}
+
+#[test]
+fn clean_removes_only_known_scratch_dirs() {
+    let sandbox = Sandbox::new();
+    for name in ["koosh_animated_temp", "koosh_archive_temp.x7Yz", "koosh_extract", "koosh_other"] {
+        fs::create_dir_all(sandbox.work().join(name).join("nested")).unwrap();
+    }
+    for name in ["koosh_hyprcursor", "koosh_extract.a1B2c3", "koosh_extract_other", "koosh_extract."] {
+        fs::create_dir_all(sandbox.root.path().join(name)).unwrap();
+    }
+
+    sandbox.command()
+        .arg("clean")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 5 scratch directories"));
+
+    assert!(!sandbox.work().join("koosh_animated_temp").exists());
+    assert!(!sandbox.work().join("koosh_archive_temp.x7Yz").exists());
+    assert!(!sandbox.work().join("koosh_extract").exists());
+    assert!(!sandbox.root.path().join("koosh_hyprcursor").exists());
+    assert!(!sandbox.root.path().join("koosh_extract.a1B2c3").exists());
+    assert!(sandbox.work().join("koosh_other").is_dir());
+    assert!(sandbox.root.path().join("koosh_extract_other").is_dir());
+    assert!(sandbox.root.path().join("koosh_extract.").is_dir());
+}
+
+#[test]
+fn completions_are_written_to_stdout() {
+    let sandbox = Sandbox::new();
+
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        sandbox.command()
+            .args(["completions", shell])
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("create-animated"));
+    }
+}
+
+#[test]
+fn manpage_writes_one_page_per_command() {
+    let sandbox = Sandbox::new();
+    let out = sandbox.work().join("man");
+
+    sandbox.command()
+        .arg("manpage")
+        .arg(&out)
+        .assert()
+        .success();
+
+    for page in ["koosh-cursor-tools", "koosh-cursor-tools-add-links", "koosh-cursor-tools-create-animated", "koosh-cursor-tools-rename-cursors"] {
+        let text = fs::read_to_string(out.join(format!("{}.1", page))).unwrap();
+        assert!(text.contains(".TH"), "{} should be a man page", page);
+    }
+    assert!(!out.join("koosh-cursor-tools-manpage.1").exists());
+}
+
+#[test]
+fn json_flag_prints_only_a_report() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.command()
+        .args(["--json", "add-links", "--theme-name", "Test-Json", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["theme"], "Test-Json");
+    assert_eq!(report["cursors"], 4);
+    assert!(report["links"].as_u64().unwrap() > 0);
+    assert!(report["bytes"].as_u64().unwrap() > 0);
+    assert_eq!(report["install_path"], sandbox.icons().join("Test-Json").to_str().unwrap());
+    let warning = &report["warnings"][0];
+    assert_eq!(warning["kind"], "skipped_alias");
+    assert!(warning["cursor"].is_string());
+    assert!(warning["message"].as_str().unwrap().contains("does not exist"));
+}
+
+#[test]
+fn json_errors_are_reported_on_stderr() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.command()
+        .args(["rename-cursors", "--json", "--input-dir", "missing"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("Theme directory not found"));
+}
+
+#[test]
+fn materialize_links_writes_real_files() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Test-Copies", "--materialize-links", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Test-Copies/cursors");
+    for (alias, target) in [("default", "left_ptr"), ("hand1", "pointer"), ("watch", "wait")] {
+        let alias = cursors.join(alias);
+        assert!(alias.is_file() && !alias.is_symlink(), "{:?} should be a real file", alias);
+        assert_eq!(fs::read(&alias).unwrap(), fs::read(cursors.join(target)).unwrap());
+    }
+}
+
+#[test]
+fn create_hyprcursor_accepts_a_source_path() {
+    let sandbox = Sandbox::new();
+
+    // A directory on disk is used as-is; without hyprcursor-util the run stops at the tool check
+    sandbox.command()
+        .args(["create-hyprcursor", "--source"])
+        .arg(fixture("x11"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Required tool not found: hyprcursor-util"));
+
+    // A bare name falls back to the icons directory
+    sandbox.command()
+        .args(["create-hyprcursor", "--source", "Not-Installed"])
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains("Theme directory not found"))
+        .stderr(predicates::str::contains(".icons/Not-Installed"));
+
+    // A missing path is reported as one, not as an invalid name
+    for missing in ["./build/Koosh-X11", "build/Koosh-X11", ".Koosh-X11"] {
+        sandbox.command()
+            .args(["create-hyprcursor", "--source", missing])
+            .assert()
+            .code(4)
+            .stderr(predicates::str::contains("Theme directory not found"))
+            .stderr(predicates::str::contains(missing))
+            .stderr(predicates::str::contains(".icons").not());
+    }
+}
+
+#[test]
+fn create_hyprcursor_installs_the_finished_theme_in_one_step() {
+    let sandbox = Sandbox::new();
+    let Some(bin) = fake_hyprcursor_util(&sandbox) else {
+        return;
+    };
+    let installed = sandbox.icons().join("Hypr-Whole");
+    fs::create_dir_all(&installed).unwrap();
+    fs::write(installed.join("stale"), "old").unwrap();
+
+    sandbox.command()
+        .env("PATH", &bin)
+        .args(["create-hyprcursor", "--dest-theme", "Hypr-Whole", "--source"])
+        .arg(fixture("x11"))
+        .assert()
+        .success();
+
+    assert!(installed.join("manifest.hl").is_file());
+    assert!(installed.join("hyprcursors/left_ptr.hlc").is_file());
+    assert!(installed.join("cursors/left_ptr").is_file());
+    assert!(installed.join("index.theme").is_file());
+    assert!(installed.join("cursor.theme").is_file());
+    assert!(!installed.join("stale").exists());
+    // The staging and replaced directories are gone
+    let entries: Vec<_> = fs::read_dir(sandbox.icons()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(entries, ["Hypr-Whole"]);
+}
+
+#[test]
+fn create_hyprcursor_sync_updates_the_install_in_place() {
+    let sandbox = Sandbox::new();
+    let Some(bin) = fake_hyprcursor_util(&sandbox) else {
+        return;
+    };
+    let build = || {
+        sandbox.command()
+            .env("PATH", &bin)
+            .args(["--sync", "create-hyprcursor", "--dest-theme", "Hypr-Synced", "--source"])
+            .arg(fixture("x11"))
+            .assert()
+            .success();
+    };
+    build();
+
+    let installed = sandbox.icons().join("Hypr-Synced");
+    let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    for file in ["cursors/left_ptr", "hyprcursors/left_ptr.hlc"] {
+        filetime::set_file_mtime(installed.join(file), old).unwrap();
+    }
+    fs::write(installed.join("stale"), "old").unwrap();
+    build();
+
+    // Unchanged files aren't copied again; what the build doesn't produce is removed
+    for file in ["cursors/left_ptr", "hyprcursors/left_ptr.hlc"] {
+        let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(installed.join(file)).unwrap());
+        assert_eq!(mtime, old, "{} should not have been copied again", file);
+    }
+    assert!(installed.join("manifest.hl").is_file());
+    assert!(!installed.join("stale").exists());
+}
+
+#[test]
+fn create_hyprcursor_verifies_the_install() {
+    let sandbox = Sandbox::new();
+    let Some(bin) = fake_hyprcursor_util(&sandbox) else {
+        return;
+    };
+
+    sandbox.command()
+        .env("PATH", &bin)
+        .args(["--verify-install", "-v", "create-hyprcursor", "--dest-theme", "Hypr-Verified", "--source"])
+        .arg(fixture("x11"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Verified"))
+        .stdout(predicate::str::contains("Hypr-Verified"));
+}
+
+#[test]
+fn create_hyprcursor_reads_the_source_cursors_directory() {
+    let sandbox = Sandbox::new();
+    let Some(bin) = fake_hyprcursor_util(&sandbox) else {
+        return;
+    };
+    let source = sandbox.work().join("Elsewhere");
+    koosh_cursor_tools::FileUtils::copy_dir_recursive(fixture("x11/cursors"), source.join("shapes")).unwrap();
+    fs::write(source.join("manifest.hl"), "name = Elsewhere\ncursors_directory = shapes\n").unwrap();
+
+    sandbox.command()
+        .env("PATH", &bin)
+        .args(["create-hyprcursor", "--dest-theme", "Hypr-Elsewhere", "--comment", "Sizes: {sizes}", "--source"])
+        .arg(&source)
+        .assert()
+        .success();
+
+    let installed = sandbox.icons().join("Hypr-Elsewhere");
+    for name in ["left_ptr", "pointer", "text", "wait"] {
+        assert!(installed.join("cursors").join(name).is_file(), "{} should be copied", name);
+    }
+    let index = fs::read_to_string(installed.join("index.theme")).unwrap();
+    assert!(index.contains("Comment=Sizes: 24\n"), "{}", index);
+}
+
+#[test]
+fn create_animated_records_build_parameters() {
+    let sandbox = Sandbox::new();
+
+    // Without ImageMagick on PATH frames are scaled natively
+    let no_tools = sandbox.work().join("no-tools");
+    fs::create_dir_all(&no_tools).unwrap();
+    let output = sandbox.command()
+        .env("PATH", &no_tools)
+        .args(["--json", "create-animated", "--output-theme", "Test-Build", "--emit-build-manifest", "--input-theme"])
+        .arg(fixture("x11"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let build = &report["themes"][0]["build"];
+    assert_eq!(report["themes"][0]["theme"], "Test-Build");
+    assert_eq!(build["frame_delay_ms"], 100);
+    assert_eq!(build["delay_scale"], 1.0);
+    assert_eq!(build["sizes"], serde_json::json!([24, 32, 48, 64, 72, 96]));
+    assert_eq!(build["scale_filter"], "lanczos3");
+    assert_eq!(build["hotspots"]["text"], serde_json::json!([0.5, 0.5]));
+
+    let manifest = fs::read_to_string(sandbox.work().join("Test-Build/.koosh-build.json")).unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&manifest).unwrap(), *build);
+    assert!(!sandbox.icons().join("Test-Build/.koosh-build.json").exists());
+}
+
+#[test]
+fn create_animated_out_format_both_installs_a_hyprcursor_theme() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.command()
+        .args(["--json", "create-animated", "--output-theme", "Test-Both", "--out-format", "both", "--resize-algorithm", "nearest", "--input-theme"])
+        .arg(fixture("x11"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["themes"][0]["hyprcursor"]["theme"], "Test-Both-Hyprcursor");
+
+    assert!(sandbox.icons().join("Test-Both/cursors/left_ptr").is_file());
+    let hyprcursor = sandbox.icons().join("Test-Both-Hyprcursor");
+    let shape = koosh_cursor_tools::hyprcursor::read_hlc(&hyprcursor.join("hyprcursors/left_ptr.hlc")).unwrap();
+    let meta = shape.iter().find(|(name, _)| name == "meta.hl").unwrap();
+    assert!(String::from_utf8_lossy(&meta.1).contains("resize_algorithm = nearest\n"));
+    assert!(hyprcursor.join("cursors/left_ptr").is_file());
+    let manifest = fs::read_to_string(hyprcursor.join("manifest.hl")).unwrap();
+    assert!(manifest.contains("name = Test-Both-Hyprcursor\n"));
+    assert!(manifest.contains("cursors_directory = hyprcursors\n"));
+    assert!(manifest.contains("version = 1.0\n"));
+}
+
+#[test]
+fn resize_algorithm_needs_a_hyprcursor_out_format() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Plain", "--resize-algorithm", "nearest", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--resize-algorithm only applies to --out-format hyprcursor or both"));
+    assert!(!sandbox.icons().join("Plain").exists());
+}
+
+#[test]
+fn create_animated_out_format_hyprcursor_installs_only_the_hyprcursor_theme() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.command()
+        .args(["--json", "create-animated", "--output-theme", "Test-Hypr", "--out-format", "hyprcursor", "--input-theme"])
+        .arg(fixture("x11"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["themes"][0]["install_paths"], serde_json::json!([]));
+    assert_eq!(report["themes"][0]["hyprcursor"]["theme"], "Test-Hypr-Hyprcursor");
+
+    assert!(!sandbox.icons().join("Test-Hypr").exists());
+    let hyprcursor = sandbox.icons().join("Test-Hypr-Hyprcursor");
+    assert!(hyprcursor.join("manifest.hl").is_file());
+    assert!(hyprcursor.join("hyprcursors/left_ptr.hlc").is_file());
+    assert!(hyprcursor.join("cursors/left_ptr").is_file());
+}
+
+#[test]
+fn hyprcursor_theme_honors_install_scope() {
+    let sandbox = Sandbox::new();
+    let system = sandbox.root.path().join("system-icons");
+
+    sandbox.command()
+        .env("KOOSH_SYSTEM_ICONS_DIR", &system)
+        .args(["--install-scope", "system", "create-animated", "--output-theme", "Scoped", "--out-format", "both", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success();
+
+    let hyprcursor = system.join("Scoped-Hyprcursor");
+    assert!(hyprcursor.join("manifest.hl").is_file());
+    assert!(hyprcursor.join("hyprcursors/left_ptr.hlc").is_file());
+    assert!(hyprcursor.join("cursors/left_ptr").is_file());
+    assert!(hyprcursor.join("index.theme").is_file());
+    assert!(!sandbox.icons().join("Scoped-Hyprcursor").exists());
+}
+
+#[test]
+fn theme_version_is_written_to_the_manifest() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Versioned", "--out-format", "hyprcursor", "--theme-version", "2.3.1", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success();
+    let manifest = koosh_cursor_tools::theme_config::HyprManifest::read(sandbox.icons().join("Versioned-Hyprcursor/manifest.hl")).unwrap();
+    assert_eq!(manifest.get("version"), Some("2.3.1"));
+
+    sandbox.command()
+        .args(["create-hyprcursor", "--theme-version", " "])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Theme version is empty"));
+}
+
+#[test]
+fn bit_depth_indexed_hands_palette_frames_to_xcursorgen() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    // Stands in for xcursorgen, keeping the scaled 24px frame it was handed
+    let bin = sandbox.work().join("bin");
+    let handed = sandbox.work().join("handed.png");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("xcursorgen"), format!("#!/bin/sh\n/bin/cp 24_000.png '{}'\n/bin/cp 24_000.png \"$2\"\n", handed.display())).unwrap();
+    fs::set_permissions(bin.join("xcursorgen"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let frames = sandbox.work().join("pngs/left_ptr");
+    fs::create_dir_all(&frames).unwrap();
+    image::RgbaImage::from_fn(48, 48, |x, y| {
+        if x < 8 { image::Rgba([0, 0, 0, 0]) } else { image::Rgba([(x * 5) as u8, (y * 5) as u8, 200, 255]) }
+    })
+    .save(frames.join("frame_1.png"))
+    .unwrap();
+
+    let color_type = |depth: &str| {
+        sandbox.command()
+            .env("PATH", &bin)
+            .args(["create-animated", "--output-theme", "Indexed", "--no-verify", "--sizes", "24,48", "--dither", "--bit-depth", depth, "--from-pngs"])
+            .arg(sandbox.work().join("pngs"))
+            .assert()
+            .success();
+        let decoder = png::Decoder::new(std::io::BufReader::new(fs::File::open(&handed).unwrap()));
+        decoder.read_info().unwrap().info().color_type
+    };
+
+    assert_eq!(color_type("8"), png::ColorType::Rgba);
+    assert_eq!(color_type("indexed"), png::ColorType::Indexed);
+    let frame = image::open(&handed).unwrap().to_rgba8();
+    assert_eq!(frame.get_pixel(0, 0)[3], 0);
+    assert_eq!(frame.get_pixel(20, 20)[3], 255);
+
+    sandbox.command()
+        .args(["create-animated", "--bit-depth", "4"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected 8 or indexed"));
+}
+
+#[test]
+fn bit_depth_warning_names_variants_imagemagick_scales() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let which = Path::new("/usr/bin/which");
+    if !which.exists() {
+        return;
+    }
+
+    let sandbox = Sandbox::new();
+    let bin = sandbox.work().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    std::os::unix::fs::symlink(which, bin.join("which")).unwrap();
+    fs::write(bin.join("magick"), "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(bin.join("magick"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    // The dark variant is recolored, so only the plain one goes through ImageMagick
+    sandbox.command()
+        .env("PATH", &bin)
+        .args(["create-animated", "--output-theme", "Depth", "--variants", "--bit-depth", "indexed", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("ImageMagick scales Depth as usual"));
+}
+
+#[test]
+fn create_animated_reuses_unchanged_cursors() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("Cached-X11/cursors");
+    fs::create_dir_all(&input).unwrap();
+    for name in ["left_ptr", "pointer"] {
+        fs::copy(fixture("x11/cursors").join(name), input.join(name)).unwrap();
+    }
+
+    // Stand-ins for xcur2png (one 24px frame per cursor) and xcursorgen
+    // (the "cursor" it writes is that frame), so cursors are really built
+    let bin = sandbox.work().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    image::RgbaImage::from_pixel(24, 24, image::Rgba([9, 9, 9, 255])).save(bin.join("frame.png")).unwrap();
+    let scripts = [
+        ("xcur2png", format!("#!/bin/sh\n/bin/cp '{}' \"$3/${{1##*/}}_000.png\"\n", bin.join("frame.png").display())),
+        ("xcursorgen", "#!/bin/sh\n/bin/cp 24_000.png \"$2\"\n".to_string()),
+    ];
+    for (tool, script) in scripts {
+        fs::write(bin.join(tool), script).unwrap();
+        fs::set_permissions(bin.join(tool), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let build = |path: &Path, extra: &[&str]| {
+        let output = sandbox.command()
+            .env("PATH", path)
+            .args(["--json", "create-animated", "--output-theme", "Cached", "--no-verify", "--sizes", "24"])
+            .args(extra)
+            .arg("--input-theme")
+            .arg(sandbox.work().join("Cached-X11"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report
+    };
+    let reused = |extra: &[&str]| build(&bin, extra)["reused"].clone();
+
+    assert_eq!(reused(&[]), serde_json::json!([]));
+    assert!(sandbox.work().join("Cached/.koosh-cache.json").is_file());
+    assert_eq!(reused(&[]), serde_json::json!(["left_ptr", "pointer"]));
+
+    fs::copy(fixture("x11/cursors/text"), input.join("pointer")).unwrap();
+    assert_eq!(reused(&[]), serde_json::json!(["left_ptr"]));
+    assert_eq!(reused(&["--force"]), serde_json::json!([]));
+    assert_eq!(reused(&["--delay-scale", "2"]), serde_json::json!([]));
+
+    assert!(sandbox.work().join("Cached/cursors/left_ptr").is_file());
+    assert!(!sandbox.work().join(".Cached.previous").exists());
+
+    // Without the tools the originals are copied, and that is never reused
+    let no_tools = sandbox.work().join("no-tools");
+    for _ in 0..2 {
+        let report = build(&no_tools, &[]);
+        assert_eq!(report["reused"], serde_json::json!([]));
+        assert!(report["warnings"].to_string().contains("xcur2png failed"));
+    }
+}
+
+#[test]
+fn create_animated_falls_back_to_installed_theme() {
+    let sandbox = Sandbox::new();
+    let installed = sandbox.icons().join("Installed-X11/cursors");
+    fs::create_dir_all(&installed).unwrap();
+    for name in ["left_ptr", "pointer"] {
+        fs::copy(fixture("x11/cursors").join(name), installed.join(name)).unwrap();
+    }
+
+    sandbox.command()
+        .args(["create-animated", "--input-theme", "Installed-X11", "--output-theme", "From-Installed"])
+        .assert()
+        .success();
+    assert!(sandbox.work().join("From-Installed/cursors/pointer").is_file());
+
+    sandbox.command()
+        .args(["create-animated", "--input-theme", "Missing-X11", "--output-theme", "Unused"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("tried \"Missing-X11\""))
+        .stderr(predicates::str::contains(".icons/Missing-X11"));
+}
+
+#[test]
+fn delay_scale_is_applied_and_clamped() {
+    let sandbox = Sandbox::new();
+
+    for (scale, expected) in [("0.5", 50), ("0.001", 1)] {
+        let output = sandbox.command()
+            .args(["--json", "create-animated", "--output-theme", "Test-Delay", "--delay-scale", scale, "--input-theme"])
+            .arg(fixture("x11"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["themes"][0]["build"]["frame_delay_ms"], expected);
+    }
+
+    sandbox.command()
+        .args(["create-animated", "--delay-scale", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("must be a positive number"));
+}
+
+#[test]
+fn frame_delay_accepts_centiseconds() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.command()
+        .args(["--json", "create-animated", "--output-theme", "Test-Cs", "--frame-delay", "6", "--delay-unit", "cs", "--input-theme"])
+        .arg(fixture("x11"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["themes"][0]["build"]["frame_delay_ms"], 60);
+
+    sandbox.command()
+        .args(["create-animated", "--delay-unit", "s"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("expected ms or cs"));
+}
+
+#[test]
+fn create_animated_follows_link_chains_and_skips_broken_links() {
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("Linked-X11/cursors");
+    fs::create_dir_all(&input).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), input.join("c")).unwrap();
+    std::os::unix::fs::symlink("b", input.join("a")).unwrap();
+    std::os::unix::fs::symlink("c", input.join("b")).unwrap();
+    std::os::unix::fs::symlink("missing", input.join("dangling")).unwrap();
+    std::os::unix::fs::symlink("loop_b", input.join("loop_a")).unwrap();
+    std::os::unix::fs::symlink("loop_a", input.join("loop_b")).unwrap();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Linked", "--input-theme"])
+        .arg(sandbox.work().join("Linked-X11"))
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Skipping symlink \"dangling\""))
+        .stderr(predicates::str::contains("Skipping symlink \"loop_a\""));
+
+    let cursors = sandbox.work().join("Linked/cursors");
+    assert_link(&cursors.join("a"), "b");
+    assert_link(&cursors.join("b"), "c");
+    assert_eq!(fs::read(cursors.join("a")).unwrap(), fs::read(fixture("x11/cursors/left_ptr")).unwrap());
+    for name in ["dangling", "loop_a", "loop_b"] {
+        assert!(!cursors.join(name).is_symlink(), "{} should be skipped", name);
+    }
+}
+
+#[test]
+fn preserve_source_links_adds_no_standard_aliases() {
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("Curated-X11/cursors");
+    fs::create_dir_all(&input).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), input.join("left_ptr")).unwrap();
+    std::os::unix::fs::symlink("left_ptr", input.join("pointer")).unwrap();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Curated", "--preserve-source-links", "--input-theme"])
+        .arg(sandbox.work().join("Curated-X11"))
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Curated/cursors");
+    assert_link(&cursors.join("pointer"), "left_ptr");
+    assert!(!cursors.join("arrow").exists() && !cursors.join("hand2").exists());
+
+    let windows = sandbox.work().join("windows");
+    fs::create_dir_all(&windows).unwrap();
+    fs::copy(fixture("windows/Normal"), windows.join("Normal")).unwrap();
+    std::os::unix::fs::symlink("Normal", windows.join("Person")).unwrap();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Curated-Renamed", "--preserve-source-links", "--input-dir"])
+        .arg(&windows)
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Curated-Renamed/cursors");
+    assert_link(&cursors.join("pointer"), "left_ptr");
+    assert!(!cursors.join("arrow").exists());
+
+    // The carried-over links follow the link mode too
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Curated-Copies", "--preserve-source-links", "--materialize-links", "--input-dir"])
+        .arg(&windows)
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("Curated-Copies/cursors");
+    assert!(!cursors.join("pointer").is_symlink());
+    assert_eq!(fs::read(cursors.join("pointer")).unwrap(), fs::read(cursors.join("left_ptr")).unwrap());
+}
+
+#[test]
+fn xcursorgen_config_lines_are_passed_through() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    // Stands in for xcursorgen: the "cursor" it writes is its config
+    let bin = sandbox.work().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("xcursorgen"), "#!/bin/sh\n/bin/cp \"$1\" \"$2\"\n").unwrap();
+    fs::set_permissions(bin.join("xcursorgen"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let configs = sandbox.work().join("configs");
+    fs::create_dir_all(configs.join("frames")).unwrap();
+    fs::write(configs.join("frames/wait_0.png"), b"frame 0").unwrap();
+    fs::write(configs.join("frames/wait_1.png"), b"frame 1").unwrap();
+    fs::write(configs.join("wait.config"), "# tuned by hand\n32 5 7 frames/wait_0.png 30\n32 5 7 frames/wait_1.png 70\n").unwrap();
+
+    let build = || {
+        let output = sandbox.command()
+            .env("PATH", &bin)
+            .args(["--json", "create-animated", "--output-theme", "Test-Config", "--no-verify", "--xcursorgen-config"])
+            .arg(&configs)
+            .arg("--input-theme")
+            .arg(fixture("x11"))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+    let report = build();
+
+    let cursor = fs::read_to_string(sandbox.work().join("Test-Config/cursors/wait")).unwrap();
+    assert_eq!(cursor, "32 5 7 000.png 30\n32 5 7 001.png 70\n");
+    assert_eq!(report["themes"][0]["build"]["hotspots"]["wait"], serde_json::json!([5.0 / 32.0, 7.0 / 32.0]));
+
+    // Rebuilt when the config or an image it names changes
+    assert_eq!(build()["reused"], serde_json::json!(["wait"]));
+    fs::write(configs.join("frames/wait_1.png"), b"frame 1, retouched").unwrap();
+    assert_eq!(build()["reused"], serde_json::json!([]));
+    fs::write(configs.join("wait.config"), "32 6 7 frames/wait_0.png 30\n32 6 7 frames/wait_1.png 70\n").unwrap();
+    assert_eq!(build()["reused"], serde_json::json!([]));
+    assert_eq!(build()["reused"], serde_json::json!(["wait"]));
+
+    // Variants can't restyle the config's images, and each one says so
+    let output = sandbox.command()
+        .env("PATH", &bin)
+        .args(["--json", "create-animated", "--output-theme", "Test-Styled", "--no-verify", "--variants", "--shadow", "offset=1,1", "--xcursorgen-config"])
+        .arg(&configs)
+        .arg("--input-theme")
+        .arg(fixture("x11"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let unstyled: Vec<_> = report["warnings"].as_array().unwrap().iter()
+        .filter(|warning| warning["kind"] == "unstyled")
+        .collect();
+    assert_eq!(unstyled.len(), 2, "{}", report["warnings"]);
+    assert!(unstyled.iter().all(|warning| warning["cursor"] == "wait"));
+}
+
+#[test]
+fn per_cursor_detail_needs_verbose() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Quiet", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Processing cursor files..."))
+        .stdout(predicates::str::contains("Processing: left_ptr").not());
+
+    sandbox.command()
+        .args(["-v", "create-animated", "--output-theme", "Chatty", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Processing: left_ptr"));
+}
+
+#[test]
+fn failing_cursors_are_collected_unless_fail_fast() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let sandbox = Sandbox::new();
+    let input = sandbox.work().join("Bad-X11/cursors");
+    fs::create_dir_all(&input).unwrap();
+    for name in ["left_ptr", "pointer"] {
+        fs::copy(fixture("x11/cursors").join(name), input.join(name)).unwrap();
+    }
+    // Not valid UTF-8, so the cursor can't be processed
+    fs::write(input.join(OsStr::from_bytes(b"bad\xff")), b"junk").unwrap();
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Partial", "--input-theme"])
+        .arg(sandbox.work().join("Bad-X11"))
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("Failed cursors:"))
+        .stderr(predicates::str::contains("1 cursor(s) failed: bad\u{fffd}"));
+    assert!(sandbox.work().join("Partial/cursors/pointer").is_file());
+    assert!(sandbox.work().join("Partial/index.theme").is_file());
+
+    sandbox.command()
+        .args(["create-animated", "--fail-fast", "--output-theme", "Aborted", "--input-theme"])
+        .arg(sandbox.work().join("Bad-X11"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Failed to process cursor bad"));
+    assert!(!sandbox.work().join("Aborted/index.theme").exists());
+}
+
+#[test]
+fn only_and_exclude_filter_source_cursors() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Only-X11", "--only", "Normal,Text", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+    let cursors = sandbox.work().join("Only-X11/cursors");
+    assert!(cursors.join("left_ptr").is_file() && cursors.join("text").is_file());
+    assert!(!cursors.join("pointer").exists());
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Excluded", "--exclude", "wait", "--input-theme"])
+        .arg(fixture("x11"))
+        .assert()
+        .success();
+    let cursors = sandbox.work().join("Excluded/cursors");
+    assert!(cursors.join("pointer").is_file());
+    assert!(!cursors.join("wait").exists());
+
+    sandbox.command()
+        .args(["add-links", "--only", "left_ptr", "--exclude", "wait"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("cannot be used with"));
+}
+
+#[test]
+fn css_maps_keywords_to_cursor_pngs() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["css", "--out", "web/cursors.css"])
+        .arg(fixture("x11"))
+        .assert()
+        .success();
+
+    let css = fs::read_to_string(sandbox.work().join("web/cursors.css")).unwrap();
+    assert!(css.contains("cursor: url(pointer.png) 3 3, pointer;"));
+    // left_ptr stands in for `default` through its alias
+    assert!(css.contains("cursor: url(default.png) 3 3, default;"));
+    assert!(css.contains("cursor: url(wait.png) 3 3, wait;"));
+    assert!(!css.contains("crosshair"));
+    assert_eq!(image::image_dimensions(sandbox.work().join("web/pointer.png")).unwrap(), (24, 24));
+}
+
+#[test]
+fn validate_compares_cursor_sizes_with_index_theme() {
+    let sandbox = Sandbox::new();
+    let theme = sandbox.work().join("Sized");
+    fs::create_dir_all(theme.join("cursors")).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), theme.join("cursors/left_ptr")).unwrap();
+    std::os::unix::fs::symlink("left_ptr", theme.join("cursors/arrow")).unwrap();
+    fs::write(theme.join("index.theme"), "[Icon Theme]\nName=Sized\n\n[cursors/24]\nSize=24\n").unwrap();
+
+    sandbox.command()
+        .args(["validate", "Sized"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("OK: 1 cursors"));
+
+    fs::write(
+        theme.join("index.theme"),
+        "[Icon Theme]\nName=Sized\n\n[cursors/24]\nSize=24\n\n[cursors/32]\nSize=32\n",
+    ).unwrap();
+    fs::write(theme.join("cursors/text"), b"junk").unwrap();
+
+    sandbox.command()
+        .args(["validate", "Sized"])
+        .assert()
+        .code(5)
+        .stdout(predicates::str::contains("left_ptr: missing declared size(s) 32 (has 24)"))
+        .stdout(predicates::str::contains("text: Invalid XCursor file"))
+        .stderr(predicates::str::contains("Sized has 2 problem(s)"));
+}
+
+#[test]
+fn validate_reports_inheritance_cycles() {
+    let sandbox = Sandbox::new();
+    for (name, inherits) in [("Looped", "Looped"), ("Ping", "Pong"), ("Pong", "hicolor,Ping")] {
+        let theme = sandbox.icons().join(name);
+        fs::create_dir_all(theme.join("cursors")).unwrap();
+        fs::copy(fixture("x11/cursors/left_ptr"), theme.join("cursors/left_ptr")).unwrap();
+        fs::write(theme.join("cursor.theme"), format!("[Icon Theme]\nName={}\nInherits={}\n", name, inherits)).unwrap();
+    }
+
+    sandbox.command()
+        .args(["validate", "Looped"])
+        .assert()
+        .code(5)
+        .stdout(predicates::str::contains("inheritance cycle: Looped -> Looped"));
+
+    sandbox.command()
+        .args(["validate", "Ping"])
+        .assert()
+        .code(5)
+        .stdout(predicates::str::contains("inheritance cycle: Ping -> Pong -> Ping"));
+}
+
+#[test]
+fn inspect_lists_image_chunks() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .arg("inspect")
+        .arg(fixture("x11/cursors/left_ptr"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Images: 1, static (1 frame)"))
+        .stdout(predicates::str::contains("size  24: 24x24 hotspot 3,3"));
+
+    let output = sandbox.command()
+        .args(["--json", "inspect"])
+        .arg(fixture("x11/cursors/pointer"))
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["sizes"], serde_json::json!([24]));
+    assert_eq!(report["animated"], false);
+    assert_eq!(report["images"][0]["xhot"], 3);
+
+    fs::write(sandbox.work().join("junk"), b"not a cursor").unwrap();
+    sandbox.command()
+        .args(["inspect", "junk"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid XCursor file"));
+}
+
+#[test]
+fn list_labels_static_cursors_and_aliases() {
+    let sandbox = Sandbox::new();
+    let theme = sandbox.work().join("Listed");
+    fs::create_dir_all(theme.join("cursors")).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), theme.join("cursors/left_ptr")).unwrap();
+    std::os::unix::fs::symlink("left_ptr", theme.join("cursors/arrow")).unwrap();
+
+    sandbox.command()
+        .args(["list", "Listed"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("left_ptr: static (1 frame)"))
+        .stdout(predicates::str::contains("arrow -> left_ptr"));
+
+    sandbox.command()
+        .args(["-v", "validate", "Listed"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("left_ptr: static (1 frame)"))
+        .stdout(predicates::str::contains("0 of 1 cursors are animated"));
+}
+
+#[test]
+fn list_without_a_theme_tabulates_installed_themes() {
+    let sandbox = Sandbox::new();
+    let theme = sandbox.icons().join("Tabled");
+    fs::create_dir_all(theme.join("cursors")).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), theme.join("cursors/left_ptr")).unwrap();
+    std::os::unix::fs::symlink("left_ptr", theme.join("cursors/arrow")).unwrap();
+    fs::write(theme.join("manifest.hl"), "name = Tabled\n").unwrap();
+    fs::create_dir_all(sandbox.icons().join("hicolor/48x48")).unwrap();
+    let broken = sandbox.icons().join("Broken");
+    fs::create_dir_all(broken.join("cursors")).unwrap();
+    fs::write(broken.join("index.theme"), b"[Icon Theme]\nName=\xff\xfe\n").unwrap();
+
+    sandbox.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Tabled: 1 cursors, 0 animated, sizes 24, hyprcursor"))
+        .stdout(predicates::str::contains("hicolor").not())
+        .stdout(predicates::str::contains("Broken").not())
+        .stderr(predicates::str::contains("Skipping unreadable theme"));
+
+    sandbox.command()
+        .args(["list", "--pretty"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("NAME    CURSORS  ANIMATED  SIZES  HYPRCURSOR"))
+        .stdout(predicates::str::contains("Tabled  1        0         24     yes"));
+
+    sandbox.command()
+        .args(["validate", "--pretty", "Tabled"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("CURSOR    SIZES  FRAMES  STATUS"))
+        .stdout(predicates::str::contains("left_ptr  24     1       ok"));
+}
+
+#[test]
+fn comment_templates_fill_in_name_sizes_and_date() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .env("SOURCE_DATE_EPOCH", "86400")
+        .args(["add-links", "--theme-name", "Templated", "--comment", "{name} ({sizes}) built {date} {unknown}", "--source-dir"])
+        .arg(fixture("x11/cursors"))
+        .assert()
+        .success();
+
+    let index = fs::read_to_string(sandbox.work().join("Templated/index.theme")).unwrap();
+    assert!(index.contains("Comment=Templated (24) built 1970-01-02 {unknown}"), "{}", index);
+}
+
+#[test]
+fn rename_cursors_reads_roles_from_an_inf_scheme() {
+    let sandbox = Sandbox::new();
+    let input = sandbox.root.path().join("pack");
+    fs::create_dir_all(&input).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), input.join("Koosh Arrow")).unwrap();
+    fs::copy(fixture("x11/cursors/wait"), input.join("koosh_busy.ani")).unwrap();
+    fs::write(input.join("install.inf"), concat!(
+        "[Scheme.Reg]\n",
+        "HKCU,\"Control Panel\\Cursors\\Schemes\",\"Koosh\",,\"%10%\\%CUR_DIR%\\%pointer%,,,%10%\\%CUR_DIR%\\%busy%\"\n",
+        "[Strings]\n",
+        "CUR_DIR = \"Cursors\\Koosh\" ; where Windows puts them\n",
+        "pointer = \"Koosh Arrow.ani\"\n",
+        "busy = \"Koosh_Busy.ani\"\n",
+    )).unwrap();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "From-Inf", "--inf"])
+        .arg(input.join("install.inf"))
+        .arg("--input-dir")
+        .arg(&input)
+        .assert()
+        .success();
+
+    let cursors = sandbox.work().join("From-Inf/cursors");
+    assert_eq!(fs::read(cursors.join("left_ptr")).unwrap(), fs::read(fixture("x11/cursors/left_ptr")).unwrap());
+    assert_eq!(fs::read(cursors.join("wait")).unwrap(), fs::read(fixture("x11/cursors/wait")).unwrap());
+    assert!(!cursors.join("install").exists());
+}
+
+#[test]
+fn normalize_names_lowercases_cursors_and_link_targets() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.root.path().join("Mixed/cursors");
+    fs::create_dir_all(&source).unwrap();
+    fs::copy(fixture("x11/cursors/left_ptr"), source.join("Left_Ptr")).unwrap();
+    fs::copy(fixture("x11/cursors/pointer"), source.join("Pointer")).unwrap();
+    std::os::unix::fs::symlink("Pointer", source.join("Hand2")).unwrap();
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "As-Is", "--source-dir"])
+        .arg(&source)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("arrow: target left_ptr does not exist"));
+
+    sandbox.command()
+        .args(["add-links", "--theme-name", "Lowered", "--normalize-names", "--source-dir"])
+        .arg(&source)
+        .assert()
+        .success();
+    let cursors = sandbox.work().join("Lowered/cursors");
+    assert!(cursors.join("left_ptr").is_file() && !cursors.join("Left_Ptr").exists());
+    assert_link(&cursors.join("arrow"), "left_ptr");
+
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Lowered-Animated", "--normalize-names", "--input-theme"])
+        .arg(sandbox.root.path().join("Mixed"))
+        .assert()
+        .success();
+    let cursors = sandbox.work().join("Lowered-Animated/cursors");
+    assert!(cursors.join("pointer").is_file());
+    assert_link(&cursors.join("hand2"), "pointer");
+}
+
+#[test]
+fn comment_and_inherit_flags_reach_theme_files() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Branded", "--comment", "My own cursors"])
+        .args(["--inherit", "Adwaita", "--inherit", "hicolor", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+
+    for file in ["index.theme", "cursor.theme"] {
+        let content = fs::read_to_string(sandbox.work().join("Branded").join(file)).unwrap();
+        assert!(content.contains("Comment=My own cursors"), "{} should carry the comment", file);
+        assert!(content.contains("Inherits=Adwaita,hicolor"), "{} should inherit both themes", file);
+    }
+}
+
+#[test]
+fn comments_that_would_add_keys_or_cut_the_description_are_rejected() {
+    let sandbox = Sandbox::new();
+
+    for comment in ["Mine\nInherits=Evil", "Theme #2"] {
+        sandbox.command()
+            .args(["rename-cursors", "--output-theme", "Injected", "--comment", comment, "--input-dir"])
+            .arg(fixture("windows"))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Theme comment"));
+        sandbox.command()
+            .args(["add-links", "--theme-name", "Injected", "--comment", comment, "--source-dir"])
+            .arg(fixture("x11/cursors"))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Theme comment"));
+        sandbox.command()
+            .args(["create-animated", "--output-theme", "Injected", "--comment", comment, "--input-theme"])
+            .arg(fixture("x11"))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Theme comment"));
+        sandbox.command()
+            .args(["create-hyprcursor", "--comment", comment, "--source"])
+            .arg(fixture("x11"))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Theme comment"));
+    }
+    assert!(!sandbox.work().join("Injected").exists());
+
+    // A source's own comment is dropped instead
+    let input = sandbox.work().join("Commented-X11");
+    koosh_cursor_tools::FileUtils::copy_dir_recursive(fixture("x11"), &input).unwrap();
+    fs::write(input.join("index.theme"), "[Icon Theme]\nComment=Theme #2\n").unwrap();
+    sandbox.command()
+        .args(["create-animated", "--output-theme", "Uncommented", "--input-theme"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Ignoring the Comment of"));
+    let index = fs::read_to_string(sandbox.work().join("Uncommented/index.theme")).unwrap();
+    assert!(!index.contains("Theme #2"), "{}", index);
+}
+
+#[test]
+fn retheme_rewrites_declared_sizes_only() {
+    let sandbox = Sandbox::new();
+
+    sandbox.command()
+        .args(["rename-cursors", "--output-theme", "Resized", "--comment", "Kept", "--input-dir"])
+        .arg(fixture("windows"))
+        .assert()
+        .success();
+    let theme = sandbox.work().join("Resized");
+    let before = fs::read(theme.join("cursors/left_ptr")).unwrap();
+
+    sandbox.command()
+        .args(["retheme", "--sizes", "32,24"])
+        .arg(&theme)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("size 32 is missing from 5 cursor(s)"))
+        .stderr(predicates::str::contains("size 24").not());
+
+    let index = fs::read_to_string(theme.join("index.theme")).unwrap();
+    assert!(index.contains("Comment=Kept"));
+    assert!(index.contains("[cursors/24]\nSize=24"));
+    assert!(index.contains("[cursors/32]\nSize=32"));
+    assert_eq!(fs::read(theme.join("cursors/left_ptr")).unwrap(), before);
+}
+
+#[test]
+fn doctor_fails_on_dangling_aliases() {
+    let sandbox = Sandbox::new();
+    let cursors = sandbox.icons().join("Half-Broken/cursors");
+    fs::create_dir_all(&cursors).unwrap();
+    fs::write(cursors.join("left_ptr"), b"arrow").unwrap();
+    std::os::unix::fs::symlink("left_ptr", cursors.join("arrow")).unwrap();
+    std::os::unix::fs::symlink("text", cursors.join("xterm")).unwrap();
+
+    sandbox.command()
+        .env("XCURSOR_THEME", "Half-Broken")
+        .arg("doctor")
+        .assert()
+        .code(5)
+        .stdout(predicates::str::contains("Installed themes: 1 (1 broken)"))
+        .stdout(predicates::str::contains("Half-Broken has 1 dangling aliases: xterm"))
+        .stdout(predicates::str::contains("XCURSOR_THEME=Half-Broken"));
+
+    fs::remove_file(cursors.join("xterm")).unwrap();
+    sandbox.command()
+        .env("XCURSOR_THEME", "Half-Broken")
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No problems found"));
+}
+
+#[test]
+fn doctor_finds_the_cursor_theme_in_xdg_and_xcursor_path_dirs() {
+    let sandbox = Sandbox::new();
+    fs::create_dir_all(sandbox.home().join(".local/share/icons/Xdg-Theme/cursors")).unwrap();
+    let extra = sandbox.root.path().join("extra-icons");
+    fs::create_dir_all(extra.join("Path-Theme/cursors")).unwrap();
+
+    for theme in ["Xdg-Theme", "Path-Theme"] {
+        sandbox.command()
+            .env("XCURSOR_THEME", theme)
+            .env("XCURSOR_PATH", format!("~/.nowhere:{}", extra.display()))
+            .arg("doctor")
+            .assert()
+            .stdout(predicates::str::contains("which is not installed").not());
+    }
+
+    sandbox.command()
+        .env("XCURSOR_THEME", "Path-Theme")
+        .arg("doctor")
+        .assert()
+        .code(5)
+        .stdout(predicates::str::contains("XCURSOR_THEME is Path-Theme, which is not installed"));
+}
+
+#[test]
+fn check_tools_reports_missing_required_tools() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let which = Path::new("/usr/bin/which");
+    if !which.exists() {
+        return;
+    }
+
+    let sandbox = Sandbox::new();
+    let bin = sandbox.work().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    std::os::unix::fs::symlink(which, bin.join("which")).unwrap();
+    for tool in ["xcur2png", "convert"] {
+        fs::write(bin.join(tool), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(tool), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    sandbox.command()
+        .env("PATH", &bin)
+        .arg("check-tools")
+        .assert()
+        .code(3)
+        .stdout(predicates::str::is_match(r"xcur2png\s+found\s+create-animated").unwrap())
+        .stdout(predicates::str::is_match(r"gtk-update-icon-cache\s+optional").unwrap())
+        .stderr(predicates::str::contains("Required tool not found: xcursorgen, hyprcursor-util"));
+}